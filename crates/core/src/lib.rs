@@ -1,31 +1,53 @@
 //! Core shared types and utilities for the voxel sandbox.
 
+pub mod loader;
 pub mod math;
 pub mod registry;
 
+pub use loader::*;
 pub use math::*;
 pub use registry::*;
 
+use serde::{Deserialize, Serialize};
+
 /// Unique identifier for block types.
 pub type BlockId = u16;
 
 /// Air block ID constant.
 pub const AIR_BLOCK: BlockId = 0;
 
-/// Basic classification of block behavior.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Brightest light level a block can emit.
+pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+/// How a block is drawn and how much of its neighbours it hides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BlockKind {
+    /// Not drawn at all
     Air,
+    /// Opaque cube that hides the faces behind it
     Solid,
+    /// See-through but fully drawn, like glass
+    Transparent,
+    /// Texture with fully clear holes, like leaves or tall grass
+    Cutout,
+    /// Translucent fluid, like water
+    Liquid,
 }
 
 impl BlockKind {
-    pub fn is_solid(self) -> bool {
+    /// Check if the block hides the faces of blocks behind it.
+    pub fn is_opaque(self) -> bool {
         matches!(self, BlockKind::Solid)
     }
 
-    pub fn is_air(self) -> bool {
-        matches!(self, BlockKind::Air)
+    /// Check if the block has any faces to draw.
+    pub fn is_visible(self) -> bool {
+        !matches!(self, BlockKind::Air)
     }
-}
 
+    /// Whether blocks of this kind collide unless their definition says
+    /// otherwise.
+    pub fn default_collidable(self) -> bool {
+        matches!(self, BlockKind::Solid | BlockKind::Transparent | BlockKind::Cutout)
+    }
+}
@@ -0,0 +1,472 @@
+//! Loading block definitions and other settings from RON, TOML or JSON
+//! files.
+//!
+//! A block file holds a list of blocks, e.g. in TOML:
+//!
+//! ```toml
+//! [[blocks]]
+//! name = "mymod:cobblestone"
+//! kind = "Solid"
+//! texture_id = 5
+//! texture = "cobblestone"
+//! faces = { top = "cobblestone_top" }
+//! ```
+//!
+//! IDs are assigned by the registry, so files don't list them.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::{qualified_name, BlockDef, BlockRegistry, MAX_LIGHT_LEVEL};
+
+/// Contents of one block definition file.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct BlockFile {
+    pub blocks: Vec<BlockDef>,
+}
+
+/// Why block files couldn't be loaded.
+#[derive(Debug)]
+pub enum BlockLoadError {
+    /// A file couldn't be read
+    Io { path: PathBuf, error: io::Error },
+    /// A file isn't valid for its format
+    Parse { path: PathBuf, message: String },
+    /// Two loaded blocks share a name
+    DuplicateName { name: String, path: PathBuf },
+    /// A block refers to a texture file that doesn't exist
+    MissingTexture { block: String, texture: String, path: PathBuf },
+    /// A property has no possible values
+    InvalidProperty { block: String, property: String },
+    /// A block gives off more light than `MAX_LIGHT_LEVEL`
+    InvalidLight { block: String, level: u8 },
+    /// A block's hardness is negative or not a number
+    InvalidHardness { block: String, hardness: f32 },
+    /// There are more block states than block IDs
+    TooManyStates { block: String },
+}
+
+impl fmt::Display for BlockLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockLoadError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            BlockLoadError::Parse { path, message } => write!(f, "{}: {}", path.display(), message),
+            BlockLoadError::DuplicateName { name, path } => {
+                write!(f, "{}: block \"{}\" is defined more than once", path.display(), name)
+            }
+            BlockLoadError::MissingTexture { block, texture, path } => {
+                write!(f, "block \"{}\" uses texture \"{}\" but {} doesn't exist", block, texture, path.display())
+            }
+            BlockLoadError::InvalidProperty { block, property } => {
+                write!(f, "property \"{}\" of block \"{}\" has no values", property, block)
+            }
+            BlockLoadError::InvalidLight { block, level } => {
+                write!(f, "block \"{}\" emits light level {} but the maximum is {}", block, level, MAX_LIGHT_LEVEL)
+            }
+            BlockLoadError::InvalidHardness { block, hardness } => {
+                write!(f, "block \"{}\" has invalid hardness {}", block, hardness)
+            }
+            BlockLoadError::TooManyStates { block } => {
+                write!(f, "no block IDs left for the states of block \"{}\"", block)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BlockLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BlockLoadError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Why a settings file couldn't be loaded.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file couldn't be read
+    Io { path: PathBuf, error: io::Error },
+    /// The file isn't valid for its format
+    Parse { path: PathBuf, message: String },
+    /// The extension isn't `ron`, `toml` or `json`
+    UnknownFormat { path: PathBuf },
+    /// The file parsed, but its values can't be used
+    Invalid { path: PathBuf, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ConfigError::Parse { path, message } | ConfigError::Invalid { path, message } => {
+                write!(f, "{}: {}", path.display(), message)
+            }
+            ConfigError::UnknownFormat { path } => {
+                write!(f, "{}: expected a .ron, .toml or .json file", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a document, picking the format from the extension of `path`
+/// (`ron`, `toml` or `json`). Returns `None` for other extensions.
+pub fn parse_by_extension<T: DeserializeOwned>(path: &Path, contents: &str) -> Option<Result<T, String>> {
+    Some(match path.extension()?.to_str()? {
+        // Optional fields can be written without `Some(..)`
+        "ron" => ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(contents)
+            .map_err(|err| err.to_string()),
+        "toml" => toml::from_str(contents).map_err(|err| err.to_string()),
+        "json" => serde_json::from_str(contents).map_err(|err| err.to_string()),
+        _ => return None,
+    })
+}
+
+/// Read and parse a RON, TOML or JSON settings file.
+pub fn load_config<T: DeserializeOwned>(path: &Path) -> Result<T, ConfigError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|error| ConfigError::Io { path: path.to_path_buf(), error })?;
+    match parse_by_extension(path, &contents) {
+        Some(parsed) => parsed.map_err(|message| ConfigError::Parse { path: path.to_path_buf(), message }),
+        None => Err(ConfigError::UnknownFormat { path: path.to_path_buf() }),
+    }
+}
+
+/// Parse a block file, picking the format from the extension (`ron`,
+/// `toml` or `json`). Returns `None` for other extensions.
+pub fn parse_block_file(path: &Path, contents: &str) -> Option<Result<Vec<BlockDef>, BlockLoadError>> {
+    let parsed = parse_by_extension::<BlockFile>(path, contents)?;
+    Some(parsed
+        .map(|file| file.blocks)
+        .map_err(|message| BlockLoadError::Parse {
+            path: path.to_path_buf(),
+            message,
+        }))
+}
+
+impl BlockRegistry {
+    /// The built-in blocks merged with the block files in
+    /// `assets_dir/blocks`, with textures looked up in `assets_dir/textures`.
+    pub fn from_assets(assets_dir: &Path) -> Result<Self, Vec<BlockLoadError>> {
+        let mut registry = Self::new();
+        registry.load_block_dir(&assets_dir.join("blocks"), &assets_dir.join("textures"))?;
+        Ok(registry)
+    }
+
+    /// Load every block file in `dir` on top of the registered blocks,
+    /// returning how many blocks were loaded.
+    ///
+    /// A loaded block replaces a registered block of the same name, keeping
+    /// its IDs when the number of states is unchanged. Files
+    /// are read in name order and all of them are validated before anything
+    /// is registered, so on error the registry is unchanged and every
+    /// problem found is reported. A missing directory loads nothing.
+    pub fn load_block_dir(&mut self, dir: &Path, textures_dir: &Path) -> Result<usize, Vec<BlockLoadError>> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(error) => return Err(vec![BlockLoadError::Io { path: dir.to_path_buf(), error }]),
+        };
+
+        let mut paths = Vec::new();
+        let mut errors = Vec::new();
+        for entry in entries {
+            match entry {
+                Ok(entry) => paths.push(entry.path()),
+                Err(error) => errors.push(BlockLoadError::Io { path: dir.to_path_buf(), error }),
+            }
+        }
+        paths.sort();
+
+        let mut loaded: Vec<(PathBuf, BlockDef)> = Vec::new();
+        for path in paths {
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    errors.push(BlockLoadError::Io { path, error });
+                    continue;
+                }
+            };
+            match parse_block_file(&path, &contents) {
+                Some(Ok(blocks)) => loaded.extend(blocks.into_iter().map(|mut block| {
+                    block.name = qualified_name(&block.name).into_owned();
+                    (path.clone(), block)
+                })),
+                Some(Err(err)) => errors.push(err),
+                None => {} // Not a block file
+            }
+        }
+
+        errors.extend(self.validate_blocks(&loaded, textures_dir));
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let count = loaded.len();
+        for (_, block) in loaded {
+            self.register(block);
+        }
+        Ok(count)
+    }
+
+    /// Check loaded blocks on their own and against the blocks they'd be
+    /// merged with.
+    fn validate_blocks(&self, loaded: &[(PathBuf, BlockDef)], textures_dir: &Path) -> Vec<BlockLoadError> {
+        let mut errors = Vec::new();
+
+        let mut names: HashMap<&str, &Path> = HashMap::new();
+        for (path, block) in loaded {
+            if names.insert(&block.name, path).is_some() {
+                errors.push(BlockLoadError::DuplicateName {
+                    name: block.name.clone(),
+                    path: path.clone(),
+                });
+            }
+
+            for property in &block.properties {
+                if property.value_count() == 0 {
+                    errors.push(BlockLoadError::InvalidProperty {
+                        block: block.name.clone(),
+                        property: property.name.clone(),
+                    });
+                }
+            }
+
+            if block.light_emission > MAX_LIGHT_LEVEL {
+                errors.push(BlockLoadError::InvalidLight {
+                    block: block.name.clone(),
+                    level: block.light_emission,
+                });
+            }
+
+            if block.hardness.is_nan() || block.hardness < 0.0 {
+                errors.push(BlockLoadError::InvalidHardness {
+                    block: block.name.clone(),
+                    hardness: block.hardness,
+                });
+            }
+
+            for texture in block.texture_names() {
+                let texture_path = textures_dir.join(format!("{}.png", texture));
+                if !texture_path.is_file() {
+                    errors.push(BlockLoadError::MissingTexture {
+                        block: block.name.clone(),
+                        texture: texture.to_string(),
+                        path: texture_path,
+                    });
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return errors;
+        }
+
+        // Every block needs a run of free IDs for its states; registering
+        // into a copy finds the holes left by overridden blocks too
+        let mut staged = self.clone();
+        for (_, block) in loaded {
+            if staged.try_register(block.clone()).is_err() {
+                errors.push(BlockLoadError::TooManyStates { block: block.name.clone() });
+                break;
+            }
+        }
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockKind, BlockProperty, PropertyKind};
+
+    fn write(dir: &Path, name: &str, contents: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(name), contents).unwrap();
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        let ron = r#"(blocks: [(name: "glass", kind: Solid, texture_id: 9)])"#;
+        let toml = "[[blocks]]\nname = \"glass\"\nkind = \"Solid\"\ntexture_id = 9\n";
+        let json = r#"{"blocks":[{"name":"glass","kind":"Solid","texture_id":9}]}"#;
+        let expected = vec![BlockDef::new("glass", BlockKind::Solid, 9)];
+
+        for (file, contents) in [("a.ron", ron), ("a.toml", toml), ("a.json", json)] {
+            let blocks = parse_block_file(Path::new(file), contents).unwrap().unwrap();
+            assert_eq!(blocks, expected, "{}", file);
+        }
+        assert!(parse_block_file(Path::new("notes.txt"), "").is_none());
+
+        let err = parse_block_file(Path::new("bad.toml"), "[[blocks]]\nname = 3").unwrap().unwrap_err();
+        assert!(matches!(err, BlockLoadError::Parse { .. }));
+        assert!(err.to_string().starts_with("bad.toml: "), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_properties() {
+        let ron = r#"(blocks: [(
+            name: "lamp", kind: Solid, texture_id: 2,
+            properties: [(name: "lit", kind: bool), (name: "level", kind: int(min: 0, max: 3))],
+        )])"#;
+        let blocks = parse_block_file(Path::new("lamp.ron"), ron).unwrap().unwrap();
+        assert_eq!(blocks[0].properties[1].kind, PropertyKind::Int { min: 0, max: 3 });
+        assert_eq!(blocks[0].state_count(), 8);
+
+        let toml = r#"
+            [[blocks]]
+            name = "pillar"
+            kind = "Solid"
+            texture_id = 1
+            properties = [{ name = "axis", kind = { enum = ["y", "x", "z"] } }]
+        "#;
+        let blocks = parse_block_file(Path::new("pillar.toml"), toml).unwrap().unwrap();
+        assert_eq!(blocks[0].state([("axis", "z")]), Some(2));
+
+        let ron = r#"(blocks: [(name: "torch", kind: Cutout, texture_id: 3, collidable: false, light_emission: 14, hardness: 0.0)])"#;
+        let torch = &parse_block_file(Path::new("torch.ron"), ron).unwrap().unwrap()[0];
+        assert_eq!((torch.kind, torch.is_collidable(), torch.light_emission, torch.hardness), (BlockKind::Cutout, false, 14, 0.0));
+    }
+
+    #[test]
+    fn test_merge_over_builtins() {
+        let assets = tempfile::tempdir().unwrap();
+        let blocks_dir = assets.path().join("blocks");
+        write(&assets.path().join("textures"), "marble.png", "");
+        write(&blocks_dir, "a.json", r#"{"blocks":[{"name":"mymod:marble","kind":"Solid","texture_id":9,"texture":"marble"}]}"#);
+        // Overrides the built-in stone
+        write(&blocks_dir, "b.toml", "[[blocks]]\nname = \"stone\"\nkind = \"Solid\"\ntexture_id = 12\n");
+        write(&blocks_dir, "readme.md", "not a block file");
+
+        let registry = BlockRegistry::from_assets(assets.path()).unwrap();
+        let marble = registry.get_by_name("mymod:marble").unwrap();
+        let first_free = BlockRegistry::new().iter().map(|block| block.states().end).max().unwrap();
+        assert_eq!(marble.id, first_free);
+        assert_eq!(marble.texture.as_deref(), Some("marble"));
+        assert!(registry.get_by_name("marble").is_none());
+        let stone = registry.get_by_name("core:stone").unwrap();
+        assert_eq!((stone.id, stone.texture_id), (1, 12));
+        assert_eq!(registry.get_by_name("dirt"), BlockRegistry::new().get_by_name("dirt"));
+        assert_eq!(registry.iter().count(), BlockRegistry::new().iter().count() + 1);
+
+        // No blocks directory leaves the built-ins alone
+        let empty = tempfile::tempdir().unwrap();
+        assert_eq!(BlockRegistry::from_assets(empty.path()).unwrap().iter().count(), BlockRegistry::new().iter().count());
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let assets = tempfile::tempdir().unwrap();
+        let blocks_dir = assets.path().join("blocks");
+        write(&blocks_dir, "a.ron", r#"(blocks: [
+            (name: "marble", kind: Solid, texture_id: 9, texture: "marble"),
+            (name: "slate", kind: Solid, texture_id: 9, faces: (top: "slate_top")),
+            (name: "sign", kind: Solid, texture_id: 9, properties: [(name: "text", kind: enum([]))]),
+            (name: "lava", kind: Liquid, texture_id: 9, light_emission: 16, hardness: -1.0),
+        ])"#);
+        // Same name once namespaced
+        write(&blocks_dir, "b.json", r#"{"blocks":[{"name":"core:slate","kind":"Solid","texture_id":1}]}"#);
+
+        let mut registry = BlockRegistry::new();
+        let errors = registry.load_block_dir(&blocks_dir, &assets.path().join("textures")).unwrap_err();
+        assert_eq!(errors.len(), 6, "{:?}", errors);
+        assert!(errors.iter().any(|err| matches!(err, BlockLoadError::MissingTexture { block, .. } if block == "core:marble")));
+        assert!(errors.iter().any(|err| matches!(err, BlockLoadError::MissingTexture { texture, .. } if texture == "slate_top")));
+        assert!(errors.iter().any(|err| matches!(err, BlockLoadError::DuplicateName { name, .. } if name == "core:slate")));
+        assert!(errors.iter().any(|err| matches!(err, BlockLoadError::InvalidProperty { property, .. } if property == "text")));
+        assert!(errors.iter().any(|err| matches!(err, BlockLoadError::InvalidLight { level: 16, .. })));
+        assert!(errors.iter().any(|err| matches!(err, BlockLoadError::InvalidHardness { block, .. } if block == "core:lava")));
+        assert!(registry.get_by_name("slate").is_none()); // Nothing registered
+
+        // All states together must fit in the block IDs
+        let blocks_dir = assets.path().join("huge");
+        write(&blocks_dir, "a.toml", r#"
+            [[blocks]]
+            name = "counter"
+            kind = "Solid"
+            texture_id = 1
+            properties = [{ name = "value", kind = { int = { min = 0, max = 65530 } } }]
+        "#);
+        let errors = registry.load_block_dir(&blocks_dir, &assets.path().join("textures")).unwrap_err();
+        match &errors[..] {
+            [BlockLoadError::TooManyStates { block }] => assert_eq!(block, "core:counter"),
+            other => panic!("unexpected errors {:?}", other),
+        }
+
+        // A state count overflowing `usize` is reported, not wrapped
+        let blocks_dir = assets.path().join("overflow");
+        write(&blocks_dir, "a.ron", r#"(blocks: [(name: "dial", kind: Solid, texture_id: 1, properties: [
+            (name: "a", kind: int(min: 0, max: 2147483646)),
+            (name: "b", kind: int(min: 0, max: 2147483646)),
+            (name: "c", kind: int(min: 0, max: 2147483646)),
+        ])])"#);
+        let errors = registry.load_block_dir(&blocks_dir, &assets.path().join("textures")).unwrap_err();
+        assert!(matches!(&errors[..], [BlockLoadError::TooManyStates { block }] if block == "core:dial"), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_load_block_dir_fragmented_ids() {
+        let assets = tempfile::tempdir().unwrap();
+        let counter = |name: &str| BlockDef::new(name, BlockKind::Solid, 1)
+            .with_property(BlockProperty::int("value", 0, 29_999));
+
+        let mut registry = BlockRegistry::new();
+        registry.register(counter("wheel"));
+        registry.register(counter("gear"));
+
+        // Fewer states than IDs in total, but no single run of free IDs fits
+        // the bigger wheel once the old one is dropped
+        let blocks_dir = assets.path().join("blocks");
+        write(&blocks_dir, "a.ron", r#"(blocks: [(name: "wheel", kind: Solid, texture_id: 1, properties: [
+            (name: "value", kind: int(min: 0, max: 30000)),
+        ])])"#);
+        let errors = registry.load_block_dir(&blocks_dir, &assets.path().join("textures")).unwrap_err();
+        assert!(matches!(&errors[..], [BlockLoadError::TooManyStates { block }] if block == "core:wheel"), "{:?}", errors);
+        assert_eq!(registry.get_by_name("wheel").unwrap().state_count(), 30_000);
+    }
+
+    #[test]
+    fn test_load_config() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Settings {
+            radius: u32,
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.toml", "radius = 4");
+        write(dir.path(), "b.ron", "(radius: 5)");
+        write(dir.path(), "c.json", r#"{"radius": "far"}"#);
+        write(dir.path(), "d.yaml", "radius: 6");
+
+        assert_eq!(load_config::<Settings>(&dir.path().join("a.toml")).unwrap(), Settings { radius: 4 });
+        assert_eq!(load_config::<Settings>(&dir.path().join("b.ron")).unwrap(), Settings { radius: 5 });
+        assert!(matches!(load_config::<Settings>(&dir.path().join("c.json")), Err(ConfigError::Parse { .. })));
+        assert!(matches!(load_config::<Settings>(&dir.path().join("d.yaml")), Err(ConfigError::UnknownFormat { .. })));
+        assert!(matches!(load_config::<Settings>(&dir.path().join("e.toml")), Err(ConfigError::Io { .. })));
+    }
+
+    #[test]
+    fn test_bundled_block_files() {
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../assets");
+        let registry = BlockRegistry::from_assets(&assets).unwrap();
+        assert!(registry.get_by_name("cobblestone").is_some());
+        assert!(registry.get_by_name("stone").is_some());
+    }
+}
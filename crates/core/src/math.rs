@@ -1,7 +1,9 @@
 //! Math utilities shared across crates.
 
+use serde::{Deserialize, Serialize};
+
 /// 3D integer vector for chunk and block coordinates.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct IVec3 {
     pub x: i32,
     pub y: i32,
@@ -135,6 +137,74 @@ pub fn chunk_local_to_world(chunk_pos: IVec3, local_pos: IVec3) -> IVec3 {
     )
 }
 
+/// How the world is divided into chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ChunkLayout {
+    /// `CHUNK_SIZE × CHUNK_HEIGHT × CHUNK_SIZE` columns covering heights
+    /// `0..CHUNK_HEIGHT`; chunk positions always have `y = 0`.
+    #[default]
+    Column,
+    /// `CHUNK_SIZE`³ cubes with a real y coordinate and no height limit.
+    Cubic,
+}
+
+impl ChunkLayout {
+    /// Height of one chunk in blocks.
+    pub fn chunk_height(self) -> i32 {
+        match self {
+            ChunkLayout::Column => CHUNK_HEIGHT,
+            ChunkLayout::Cubic => CHUNK_SIZE,
+        }
+    }
+
+    /// Number of voxels in one chunk.
+    pub fn chunk_volume(self) -> usize {
+        (CHUNK_SIZE * CHUNK_SIZE * self.chunk_height()) as usize
+    }
+
+    /// Convert world coordinates to chunk coordinates.
+    pub fn world_to_chunk(self, world_pos: IVec3) -> IVec3 {
+        match self {
+            ChunkLayout::Column => world_to_chunk(world_pos),
+            ChunkLayout::Cubic => IVec3::new(
+                world_pos.x.div_euclid(CHUNK_SIZE),
+                world_pos.y.div_euclid(CHUNK_SIZE),
+                world_pos.z.div_euclid(CHUNK_SIZE),
+            ),
+        }
+    }
+
+    /// Convert world coordinates to local block coordinates within a chunk.
+    pub fn world_to_local(self, world_pos: IVec3) -> IVec3 {
+        match self {
+            ChunkLayout::Column => world_to_local(world_pos),
+            ChunkLayout::Cubic => IVec3::new(
+                world_pos.x.rem_euclid(CHUNK_SIZE),
+                world_pos.y.rem_euclid(CHUNK_SIZE),
+                world_pos.z.rem_euclid(CHUNK_SIZE),
+            ),
+        }
+    }
+
+    /// Convert chunk coordinates and local coordinates to world coordinates.
+    pub fn chunk_local_to_world(self, chunk_pos: IVec3, local_pos: IVec3) -> IVec3 {
+        IVec3::new(
+            chunk_pos.x * CHUNK_SIZE + local_pos.x,
+            chunk_pos.y * self.chunk_height() + local_pos.y,
+            chunk_pos.z * CHUNK_SIZE + local_pos.z,
+        )
+    }
+
+    /// Chunk y coordinates of the chunks covering world heights
+    /// `min_y..=max_y`.
+    pub fn chunk_y_range(self, min_y: i32, max_y: i32) -> std::ops::RangeInclusive<i32> {
+        match self {
+            ChunkLayout::Column => 0..=0,
+            ChunkLayout::Cubic => min_y.div_euclid(CHUNK_SIZE)..=max_y.div_euclid(CHUNK_SIZE),
+        }
+    }
+}
+
 /// Convert 3D local coordinates to 1D array index.
 pub fn local_to_index(local_pos: IVec3) -> Option<usize> {
     if local_pos.x < 0 || local_pos.x >= CHUNK_SIZE
@@ -233,4 +303,52 @@ mod tests {
         assert_eq!(max_index, CHUNK_VOLUME - 1);
         assert_eq!(index_to_local(max_index).unwrap(), max_pos);
     }
+
+    #[test]
+    fn test_ivec3_serde() {
+        let pos = IVec3::new(-3, 64, 17);
+        let json = serde_json::to_string(&pos).unwrap();
+        assert_eq!(json, r#"{"x":-3,"y":64,"z":17}"#);
+        assert_eq!(serde_json::from_str::<IVec3>(&json).unwrap(), pos);
+    }
+
+    #[test]
+    fn test_cubic_coordinate_conversions() {
+        let cubic = ChunkLayout::Cubic;
+        assert_eq!(cubic.world_to_chunk(IVec3::new(0, 0, 0)), IVec3::ZERO);
+        assert_eq!(cubic.world_to_chunk(IVec3::new(15, 15, 15)), IVec3::ZERO);
+        assert_eq!(cubic.world_to_chunk(IVec3::new(0, 16, 0)), IVec3::new(0, 1, 0));
+        assert_eq!(cubic.world_to_chunk(IVec3::new(0, 300, 0)), IVec3::new(0, 18, 0));
+        assert_eq!(cubic.world_to_chunk(IVec3::new(0, -1, 0)), IVec3::new(0, -1, 0));
+        assert_eq!(cubic.world_to_chunk(IVec3::new(-1, -16, -1)), IVec3::new(-1, -1, -1));
+        assert_eq!(cubic.world_to_chunk(IVec3::new(0, -17, 0)), IVec3::new(0, -2, 0));
+
+        assert_eq!(cubic.world_to_local(IVec3::new(3, -1, 4)), IVec3::new(3, 15, 4));
+        assert_eq!(cubic.world_to_local(IVec3::new(3, -16, 4)), IVec3::new(3, 0, 4));
+        assert_eq!(cubic.world_to_local(IVec3::new(3, 300, 4)), IVec3::new(3, 12, 4));
+
+        for world_pos in [IVec3::new(123, -45, -67), IVec3::new(-1, -1000, 5), IVec3::new(7, 4000, 7)] {
+            let chunk_pos = cubic.world_to_chunk(world_pos);
+            let local_pos = cubic.world_to_local(world_pos);
+            assert!(local_to_index(local_pos).unwrap() < cubic.chunk_volume());
+            assert_eq!(cubic.chunk_local_to_world(chunk_pos, local_pos), world_pos);
+        }
+
+        assert_eq!(cubic.chunk_y_range(-64, 319), -4..=19);
+        assert_eq!(cubic.chunk_y_range(-1, 0), -1..=0);
+    }
+
+    #[test]
+    fn test_column_layout_matches_free_functions() {
+        let column = ChunkLayout::Column;
+        let world_pos = IVec3::new(-20, 77, 33);
+        assert_eq!(column.world_to_chunk(world_pos), world_to_chunk(world_pos));
+        assert_eq!(column.world_to_local(world_pos), world_to_local(world_pos));
+        assert_eq!(
+            column.chunk_local_to_world(world_to_chunk(world_pos), world_to_local(world_pos)),
+            world_pos
+        );
+        assert_eq!(column.chunk_volume(), CHUNK_VOLUME);
+        assert_eq!(column.chunk_y_range(-64, 319), 0..=0);
+    }
 }
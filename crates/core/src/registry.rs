@@ -1,103 +1,689 @@
 //! Block registry and palette system for efficient voxel storage.
+//!
+//! Blocks may declare typed properties (for example a log's `axis`). Every
+//! combination of property values is a block state with its own runtime ID:
+//! a block with `n` states owns the IDs `id..id + n`, and its default state
+//! (the first value of every property) is `id` itself. Chunks and palettes
+//! store these state IDs, so blocks without properties are stored by their
+//! plain ID.
+//!
+//! Blocks are named `namespace:name` (`core:stone`, `mymod:ore`); a name
+//! without a namespace is in the `core` namespace. IDs are handed out by the
+//! registry in registration order, so they can differ between runs. Saved
+//! worlds keep a [`BlockIdTable`] of the IDs they were written with, and a
+//! [`BlockRemap`] translates those to the current registry's IDs.
 
-use crate::{BlockId, BlockKind, AIR_BLOCK};
-use std::collections::HashMap;
+use crate::{BlockId, BlockKind, AIR_BLOCK, MAX_LIGHT_LEVEL};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+
+/// Namespace of built-in blocks and of names given without one.
+pub const DEFAULT_NAMESPACE: &str = "core";
+
+/// Full `namespace:name` form of a block name.
+pub fn qualified_name(name: &str) -> Cow<'_, str> {
+    if name.contains(':') {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(format!("{}:{}", DEFAULT_NAMESPACE, name))
+    }
+}
+
+/// The values a block property can take.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PropertyKind {
+    /// `false` or `true`
+    Bool,
+    /// Integers in `min..=max`
+    Int { min: i32, max: i32 },
+    /// One of a fixed list of names
+    Enum(Vec<String>),
+}
+
+/// A named, typed block property such as `axis` or `open`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockProperty {
+    pub name: String,
+    pub kind: PropertyKind,
+}
+
+impl BlockProperty {
+    pub fn bool(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            kind: PropertyKind::Bool,
+        }
+    }
+    
+    pub fn int(name: impl Into<String>, min: i32, max: i32) -> Self {
+        assert!(min <= max, "empty int property range {}..={}", min, max);
+        Self {
+            name: name.into(),
+            kind: PropertyKind::Int { min, max },
+        }
+    }
+    
+    pub fn enumeration<S: Into<String>>(name: impl Into<String>, values: impl IntoIterator<Item = S>) -> Self {
+        let values: Vec<String> = values.into_iter().map(Into::into).collect();
+        assert!(!values.is_empty(), "enum property needs at least one value");
+        Self {
+            name: name.into(),
+            kind: PropertyKind::Enum(values),
+        }
+    }
+    
+    /// Number of values this property can take.
+    pub fn value_count(&self) -> usize {
+        match &self.kind {
+            PropertyKind::Bool => 2,
+            PropertyKind::Int { min, max } => (*max as i64 - *min as i64 + 1).max(0) as usize,
+            PropertyKind::Enum(values) => values.len(),
+        }
+    }
+    
+    /// Index of a value given by name, e.g. `"true"`, `"3"` or `"x"`.
+    pub fn value_index(&self, value: &str) -> Option<usize> {
+        match &self.kind {
+            PropertyKind::Bool => match value {
+                "false" => Some(0),
+                "true" => Some(1),
+                _ => None,
+            },
+            PropertyKind::Int { min, max } => {
+                let value: i32 = value.parse().ok()?;
+                (*min..=*max).contains(&value).then(|| (value as i64 - *min as i64) as usize)
+            }
+            PropertyKind::Enum(values) => values.iter().position(|name| name == value),
+        }
+    }
+    
+    /// Name of the value at `index`.
+    pub fn value_name(&self, index: usize) -> String {
+        match &self.kind {
+            PropertyKind::Bool => (index != 0).to_string(),
+            PropertyKind::Int { min, .. } => (*min as i64 + index as i64).to_string(),
+            PropertyKind::Enum(values) => values[index].clone(),
+        }
+    }
+}
+
+/// A face of a block, named by the direction it points. North is -z and
+/// east is +x.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockFace {
+    Top,
+    Bottom,
+    North,
+    South,
+    East,
+    West,
+}
+
+impl BlockFace {
+    pub const ALL: [BlockFace; 6] = [
+        BlockFace::Top,
+        BlockFace::Bottom,
+        BlockFace::North,
+        BlockFace::South,
+        BlockFace::East,
+        BlockFace::West,
+    ];
+    
+    /// Face pointing along `axis` (0 = x, 1 = y, 2 = z), towards positive
+    /// coordinates if `positive`.
+    pub fn from_axis(axis: usize, positive: bool) -> Self {
+        match (axis, positive) {
+            (0, true) => BlockFace::East,
+            (0, false) => BlockFace::West,
+            (1, true) => BlockFace::Top,
+            (1, false) => BlockFace::Bottom,
+            (2, true) => BlockFace::South,
+            (2, false) => BlockFace::North,
+            _ => panic!("invalid axis {}", axis),
+        }
+    }
+    
+    pub fn is_side(self) -> bool {
+        !matches!(self, BlockFace::Top | BlockFace::Bottom)
+    }
+}
+
+/// Textures for particular faces of a block. A side face without its own
+/// texture uses `side`, and any face left over uses the block's `texture`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FaceTextures {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bottom: Option<String>,
+    /// All four sides
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub north: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub south: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub east: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub west: Option<String>,
+}
+
+impl FaceTextures {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+    
+    /// The texture given for exactly this face.
+    fn slot(&mut self, face: BlockFace) -> &mut Option<String> {
+        match face {
+            BlockFace::Top => &mut self.top,
+            BlockFace::Bottom => &mut self.bottom,
+            BlockFace::North => &mut self.north,
+            BlockFace::South => &mut self.south,
+            BlockFace::East => &mut self.east,
+            BlockFace::West => &mut self.west,
+        }
+    }
+    
+    /// Texture for a face, or `None` if the block's texture applies.
+    pub fn get(&self, face: BlockFace) -> Option<&str> {
+        let own = match face {
+            BlockFace::Top => &self.top,
+            BlockFace::Bottom => &self.bottom,
+            BlockFace::North => &self.north,
+            BlockFace::South => &self.south,
+            BlockFace::East => &self.east,
+            BlockFace::West => &self.west,
+        };
+        own.as_deref()
+            .or_else(|| self.side.as_deref().filter(|_| face.is_side()))
+    }
+}
 
 /// Block definition with properties.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BlockDef {
+    /// First state ID, assigned by the registry
+    #[serde(default)]
     pub id: BlockId,
+    /// Block name, namespaced once registered
     pub name: String,
     pub kind: BlockKind,
     pub texture_id: u16,
+    /// Texture file in `assets/textures`, without the `.png` extension,
+    /// used by every face without its own texture
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texture: Option<String>,
+    /// Per-face textures, named like `texture`
+    #[serde(default, skip_serializing_if = "FaceTextures::is_empty")]
+    pub faces: FaceTextures,
+    /// State properties; the last one varies fastest between state IDs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<BlockProperty>,
+    /// Whether entities collide with the block; `None` follows `kind`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collidable: Option<bool>,
+    /// Light level given off, up to `MAX_LIGHT_LEVEL`
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub light_emission: u8,
+    /// How long the block takes to break; stone is 1.5
+    #[serde(default = "default_hardness")]
+    pub hardness: f32,
+}
+
+fn is_zero(level: &u8) -> bool {
+    *level == 0
+}
+
+fn default_hardness() -> f32 {
+    1.0
 }
 
 impl BlockDef {
-    pub fn new(id: BlockId, name: impl Into<String>, kind: BlockKind, texture_id: u16) -> Self {
+    pub fn new(name: impl Into<String>, kind: BlockKind, texture_id: u16) -> Self {
         Self {
-            id,
+            id: AIR_BLOCK,
             name: name.into(),
             kind,
             texture_id,
+            texture: None,
+            faces: FaceTextures::default(),
+            properties: Vec::new(),
+            collidable: None,
+            light_emission: 0,
+            hardness: default_hardness(),
+        }
+    }
+    
+    /// Add a state property.
+    pub fn with_property(mut self, property: BlockProperty) -> Self {
+        self.properties.push(property);
+        self
+    }
+    
+    /// Use a texture for every face.
+    pub fn with_texture(mut self, name: impl Into<String>) -> Self {
+        self.texture = Some(name.into());
+        self
+    }
+    
+    /// Use a texture for one face.
+    pub fn with_face_texture(mut self, face: BlockFace, name: impl Into<String>) -> Self {
+        *self.faces.slot(face) = Some(name.into());
+        self
+    }
+    
+    /// Texture name for a face, if the block has one.
+    pub fn face_texture(&self, face: BlockFace) -> Option<&str> {
+        self.faces.get(face).or(self.texture.as_deref())
+    }
+    
+    /// Every texture name the block refers to, without repeats.
+    pub fn texture_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        let all = [&self.texture, &self.faces.top, &self.faces.bottom, &self.faces.side,
+            &self.faces.north, &self.faces.south, &self.faces.east, &self.faces.west];
+        for name in all.into_iter().flatten() {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
         }
+        names
+    }
+    
+    /// Override whether entities collide with the block.
+    pub fn with_collidable(mut self, collidable: bool) -> Self {
+        self.collidable = Some(collidable);
+        self
+    }
+    
+    /// Make the block give off light.
+    pub fn with_light_emission(mut self, level: u8) -> Self {
+        self.light_emission = level;
+        self
+    }
+    
+    pub fn with_hardness(mut self, hardness: f32) -> Self {
+        self.hardness = hardness;
+        self
+    }
+    
+    /// Check if entities collide with the block.
+    pub fn is_collidable(&self) -> bool {
+        self.collidable.unwrap_or_else(|| self.kind.default_collidable())
     }
     
     pub fn air() -> Self {
-        Self::new(AIR_BLOCK, "air", BlockKind::Air, 0)
+        Self::new("core:air", BlockKind::Air, 0).with_hardness(0.0)
     }
     
     pub fn stone() -> Self {
-        Self::new(1, "stone", BlockKind::Solid, 1)
+        Self::new("core:stone", BlockKind::Solid, 1).with_hardness(1.5)
     }
     
     pub fn dirt() -> Self {
-        Self::new(2, "dirt", BlockKind::Solid, 2)
+        Self::new("core:dirt", BlockKind::Solid, 2).with_hardness(0.5)
     }
     
     pub fn grass() -> Self {
-        Self::new(3, "grass", BlockKind::Solid, 3)
+        Self::new("core:grass", BlockKind::Solid, 3).with_hardness(0.6)
     }
     
     pub fn wood() -> Self {
-        Self::new(4, "wood", BlockKind::Solid, 4)
+        Self::new("core:wood", BlockKind::Solid, 4).with_hardness(2.0)
+    }
+    
+    pub fn sand() -> Self {
+        Self::new("core:sand", BlockKind::Solid, 5).with_hardness(0.5)
+    }
+    
+    /// Tree canopy; light passes through the gaps.
+    pub fn leaves() -> Self {
+        Self::new("core:leaves", BlockKind::Cutout, 6).with_hardness(0.2)
+    }
+    
+    pub fn coal_ore() -> Self {
+        Self::new("core:coal_ore", BlockKind::Solid, 7).with_hardness(3.0)
+    }
+    
+    pub fn iron_ore() -> Self {
+        Self::new("core:iron_ore", BlockKind::Solid, 8).with_hardness(3.0)
+    }
+    
+    pub fn gravel() -> Self {
+        Self::new("core:gravel", BlockKind::Solid, 9).with_hardness(0.6)
+    }
+    
+    /// Still water filling seas and lakes.
+    pub fn water() -> Self {
+        Self::new("core:water", BlockKind::Liquid, 10).with_hardness(100.0)
+    }
+    
+    /// A log with an `axis` property; upright (`y`) by default.
+    pub fn oak_log() -> Self {
+        Self::new("core:oak_log", BlockKind::Solid, 4)
+            .with_hardness(2.0)
+            .with_property(BlockProperty::enumeration("axis", ["y", "x", "z"]))
+    }
+    
+    /// Number of distinct states of this block, saturating at `usize::MAX`.
+    pub fn state_count(&self) -> usize {
+        self.checked_state_count().unwrap_or(usize::MAX)
+    }
+    
+    /// Number of distinct states, or `None` if it overflows `usize`.
+    pub fn checked_state_count(&self) -> Option<usize> {
+        self.properties.iter().try_fold(1usize, |count, property| count.checked_mul(property.value_count()))
+    }
+    
+    /// State IDs owned by this block.
+    pub fn states(&self) -> Range<BlockId> {
+        self.id..self.id + self.state_count() as BlockId
+    }
+    
+    /// State ID for the given property values. Properties that aren't
+    /// listed keep their default (first) value; unknown properties or
+    /// values give `None`.
+    pub fn state<'a>(&self, values: impl IntoIterator<Item = (&'a str, &'a str)>) -> Option<BlockId> {
+        let mut indices = vec![0; self.properties.len()];
+        for (name, value) in values {
+            let slot = self.properties.iter().position(|property| property.name == name)?;
+            indices[slot] = self.properties[slot].value_index(value)?;
+        }
+        
+        let offset = self.properties.iter().zip(&indices)
+            .fold(0, |offset, (property, &index)| offset * property.value_count() + index);
+        Some(self.id + offset as BlockId)
+    }
+    
+    /// Property values of a state ID owned by this block, in declaration
+    /// order, or `None` if the state belongs to another block.
+    pub fn state_values(&self, state: BlockId) -> Option<Vec<(&str, String)>> {
+        if !self.states().contains(&state) {
+            return None;
+        }
+        
+        let mut offset = (state - self.id) as usize;
+        let mut values = Vec::with_capacity(self.properties.len());
+        for property in self.properties.iter().rev() {
+            let count = property.value_count();
+            values.push((property.name.as_str(), property.value_name(offset % count)));
+            offset /= count;
+        }
+        values.reverse();
+        Some(values)
+    }
+}
+
+/// Why a block couldn't be registered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegisterError {
+    /// A property has no values, so the block has no states
+    NoStates { block: String },
+    /// No run of free IDs is long enough for the block's states
+    TooManyStates { block: String },
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterError::NoStates { block } => write!(f, "block {} has no states", block),
+            RegisterError::TooManyStates { block } => write!(f, "no free block IDs left for {}", block),
+        }
     }
 }
 
+impl std::error::Error for RegisterError {}
+
 /// Global block registry.
 #[derive(Debug, Clone)]
 pub struct BlockRegistry {
-    blocks: HashMap<BlockId, BlockDef>,
+    /// Blocks keyed by their first state ID
+    blocks: BTreeMap<BlockId, BlockDef>,
     name_to_id: HashMap<String, BlockId>,
+    /// First state IDs of placeholder blocks
+    placeholders: HashSet<BlockId>,
 }
 
 impl BlockRegistry {
     pub fn new() -> Self {
         let mut registry = Self {
-            blocks: HashMap::new(),
+            blocks: BTreeMap::new(),
             name_to_id: HashMap::new(),
+            placeholders: HashSet::new(),
         };
         
-        // Register default blocks
+        // Register default blocks; air comes first so it gets `AIR_BLOCK`
         registry.register(BlockDef::air());
         registry.register(BlockDef::stone());
         registry.register(BlockDef::dirt());
         registry.register(BlockDef::grass());
         registry.register(BlockDef::wood());
+        registry.register(BlockDef::oak_log());
+        registry.register(BlockDef::sand());
+        registry.register(BlockDef::leaves());
+        registry.register(BlockDef::coal_ore());
+        registry.register(BlockDef::iron_ore());
+        registry.register(BlockDef::gravel());
+        registry.register(BlockDef::water());
         
         registry
     }
     
-    pub fn register(&mut self, block: BlockDef) {
-        self.name_to_id.insert(block.name.clone(), block.id);
-        self.blocks.insert(block.id, block);
+    /// Register a block under its namespaced name and return its ID.
+    ///
+    /// The `id` of `block` is ignored: a new block gets the lowest run of
+    /// free IDs that fits all of its states. Registering a name again
+    /// replaces its definition, keeping its IDs unless the number of states
+    /// changed.
+    ///
+    /// Panics if the block can't be registered; see `try_register`.
+    pub fn register(&mut self, block: BlockDef) -> BlockId {
+        self.try_register(block).unwrap_or_else(|error| panic!("{}", error))
+    }
+    
+    /// Register a block like `register`, returning an error if it has no
+    /// states or no run of free IDs fits them. The registry is unchanged on
+    /// error.
+    pub fn try_register(&mut self, mut block: BlockDef) -> Result<BlockId, RegisterError> {
+        block.name = qualified_name(&block.name).into_owned();
+        let count = match block.checked_state_count() {
+            Some(0) => return Err(RegisterError::NoStates { block: block.name }),
+            Some(count) => count,
+            None => return Err(RegisterError::TooManyStates { block: block.name }),
+        };
+        
+        let existing = self.name_to_id.get(&block.name).copied();
+        let id = match existing {
+            Some(id) if self.blocks[&id].state_count() == count => id,
+            _ => match self.allocate(count, existing) {
+                Some(id) => id,
+                None => return Err(RegisterError::TooManyStates { block: block.name }),
+            },
+        };
+        if existing.is_some_and(|old_id| old_id != id) {
+            self.unregister(&block.name);
+        }
+        
+        block.id = id;
+        self.placeholders.remove(&id);
+        self.name_to_id.insert(block.name.clone(), id);
+        self.blocks.insert(id, block);
+        Ok(id)
+    }
+    
+    /// Lowest ID starting `count` unused state IDs, treating the IDs of the
+    /// block starting at `replacing` as free.
+    fn allocate(&self, count: usize, replacing: Option<BlockId>) -> Option<BlockId> {
+        let mut start: usize = 0;
+        for block in self.blocks.values().filter(|block| Some(block.id) != replacing) {
+            if block.id as usize >= start.saturating_add(count) {
+                break;
+            }
+            start = block.id as usize + block.state_count();
+        }
+        // The end of the range must fit in a `BlockId` too
+        (start.checked_add(count)? <= BlockId::MAX as usize).then_some(start as BlockId)
     }
     
+    /// Register a stand-in for a block this registry doesn't define, such
+    /// as one from a missing mod. It keeps the name and number of states so
+    /// the blocks survive being saved again, and is solid.
+    pub fn register_placeholder(&mut self, name: &str, states: usize) -> Result<BlockId, RegisterError> {
+        let mut block = BlockDef::new(name, BlockKind::Solid, 0);
+        match states {
+            0 => return Err(RegisterError::NoStates { block: qualified_name(name).into_owned() }),
+            1 => {}
+            _ => {
+                let max = i32::try_from(states - 1)
+                    .map_err(|_| RegisterError::TooManyStates { block: qualified_name(name).into_owned() })?;
+                block = block.with_property(BlockProperty::int("state", 0, max));
+            }
+        }
+        let id = self.try_register(block)?;
+        self.placeholders.insert(id);
+        Ok(id)
+    }
+    
+    /// Check if a block or state ID belongs to a placeholder block.
+    pub fn is_placeholder(&self, id: BlockId) -> bool {
+        self.get(id).is_some_and(|block| self.placeholders.contains(&block.id))
+    }
+    
+    /// Remove a block by name, returning its definition.
+    pub fn unregister(&mut self, name: &str) -> Option<BlockDef> {
+        let id = self.name_to_id.remove(qualified_name(name).as_ref())?;
+        self.placeholders.remove(&id);
+        self.blocks.remove(&id)
+    }
+    
+    /// Get the block owning a block or state ID.
     pub fn get(&self, id: BlockId) -> Option<&BlockDef> {
-        self.blocks.get(&id)
+        self.blocks.range(..=id)
+            .next_back()
+            .map(|(_, block)| block)
+            .filter(|block| block.states().contains(&id))
     }
     
+    /// Get a block by name; `stone` is short for `core:stone`.
     pub fn get_by_name(&self, name: &str) -> Option<&BlockDef> {
-        self.name_to_id.get(name)
+        self.name_to_id.get(qualified_name(name).as_ref())
             .and_then(|&id| self.blocks.get(&id))
     }
     
+    /// State ID of a named block with the given property values, e.g.
+    /// `registry.state("oak_log", [("axis", "x")])`.
+    pub fn state<'a>(&self, name: &str, values: impl IntoIterator<Item = (&'a str, &'a str)>) -> Option<BlockId> {
+        self.get_by_name(name)?.state(values)
+    }
+    
+    /// Property values of a state ID.
+    pub fn state_values(&self, state: BlockId) -> Option<Vec<(&str, String)>> {
+        self.get(state)?.state_values(state)
+    }
+    
     pub fn get_kind(&self, id: BlockId) -> BlockKind {
-        self.blocks.get(&id)
+        self.get(id)
             .map(|block| block.kind)
             .unwrap_or(BlockKind::Air)
     }
     
-    pub fn is_solid(&self, id: BlockId) -> bool {
-        self.get_kind(id).is_solid()
+    /// Check if a block hides the faces of blocks behind it.
+    pub fn is_opaque(&self, id: BlockId) -> bool {
+        self.get_kind(id).is_opaque()
+    }
+    
+    /// Check if a block has faces to draw; unknown IDs are invisible.
+    pub fn is_visible(&self, id: BlockId) -> bool {
+        self.get_kind(id).is_visible()
     }
     
-    pub fn is_air(&self, id: BlockId) -> bool {
-        id == AIR_BLOCK || self.get_kind(id).is_air()
+    /// Check if entities collide with a block.
+    pub fn is_collidable(&self, id: BlockId) -> bool {
+        self.get(id).is_some_and(BlockDef::is_collidable)
+    }
+    
+    pub fn is_liquid(&self, id: BlockId) -> bool {
+        self.get_kind(id) == BlockKind::Liquid
+    }
+    
+    /// Light level a block gives off.
+    pub fn light_emission(&self, id: BlockId) -> u8 {
+        self.get(id).map_or(0, |block| block.light_emission.min(MAX_LIGHT_LEVEL))
+    }
+    
+    pub fn hardness(&self, id: BlockId) -> f32 {
+        self.get(id).map_or(0.0, |block| block.hardness)
+    }
+    
+    /// Check if the face of `block` towards `neighbor` needs drawing.
+    ///
+    /// Opaque neighbours hide every face. Transparent blocks and liquids
+    /// also hide faces between blocks of the same type, so glass walls and
+    /// bodies of water are only drawn on the outside; cutout blocks keep
+    /// them since their holes show what's behind.
+    pub fn face_visible(&self, block: BlockId, neighbor: BlockId) -> bool {
+        let kind = self.get_kind(block);
+        if !kind.is_visible() || self.is_opaque(neighbor) {
+            return false;
+        }
+        
+        match kind {
+            BlockKind::Transparent | BlockKind::Liquid => {
+                let owner = |id| self.get(id).map(|block| block.id);
+                owner(block) != owner(neighbor)
+            }
+            _ => true,
+        }
     }
     
     pub fn iter(&self) -> impl Iterator<Item = &BlockDef> {
         self.blocks.values()
     }
+    
+    /// The name → ID table to store with saved chunks.
+    pub fn id_table(&self) -> BlockIdTable {
+        BlockIdTable {
+            blocks: self.iter()
+                .map(|block| BlockIdEntry {
+                    name: block.name.clone(),
+                    id: block.id,
+                    states: block.state_count(),
+                })
+                .collect(),
+        }
+    }
+    
+    /// Map the IDs of a world saved with `table` to this registry's IDs.
+    ///
+    /// Blocks this registry doesn't define get placeholders. A block whose
+    /// number of states changed loses its state and maps to its default
+    /// state. Fails if a placeholder can't be registered.
+    pub fn remap_from(&mut self, table: &BlockIdTable) -> Result<BlockRemap, RegisterError> {
+        let mut map = HashMap::new();
+        for entry in &table.blocks {
+            let id = match self.get_by_name(&entry.name) {
+                Some(block) => block.id,
+                None => self.register_placeholder(&entry.name, entry.states)?,
+            };
+            let same_states = self.blocks[&id].state_count() == entry.states;
+            
+            for offset in 0..entry.states {
+                // Saved IDs past the end of the ID space can't occur in chunks
+                let Ok(old_id) = BlockId::try_from(entry.id as usize + offset) else {
+                    break;
+                };
+                let new_id = if same_states { id + offset as BlockId } else { id };
+                map.insert(old_id, new_id);
+            }
+        }
+        Ok(BlockRemap { map })
+    }
 }
 
 impl Default for BlockRegistry {
@@ -106,13 +692,56 @@ impl Default for BlockRegistry {
     }
 }
 
-/// Palette for efficient chunk storage - maps local indices to block IDs.
+/// IDs a world was saved with, stored next to its chunks.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BlockIdTable {
+    pub blocks: Vec<BlockIdEntry>,
+}
+
+/// One block of a [`BlockIdTable`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockIdEntry {
+    /// Namespaced block name
+    pub name: String,
+    /// First state ID
+    pub id: BlockId,
+    /// Number of states
+    pub states: usize,
+}
+
+/// Translation from the IDs of a saved world to a registry's IDs.
 #[derive(Debug, Clone)]
+pub struct BlockRemap {
+    /// Saved state ID → current state ID
+    map: HashMap<BlockId, BlockId>,
+}
+
+impl BlockRemap {
+    /// Current ID for a saved ID. IDs missing from the saved table become
+    /// air.
+    pub fn get(&self, id: BlockId) -> BlockId {
+        self.map.get(&id).copied().unwrap_or(AIR_BLOCK)
+    }
+    
+    /// Check if every saved ID keeps its value.
+    pub fn is_identity(&self) -> bool {
+        self.map.iter().all(|(old, new)| old == new)
+    }
+}
+
+/// Palette for efficient chunk storage - maps local indices to block (state)
+/// IDs.
+///
+/// Indices are 16 bits wide, so a palette can hold every possible `BlockId`
+/// and adding a block never fails. Serialized as the ordered list of block
+/// IDs; the reverse map is rebuilt on load.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "Vec<BlockId>", try_from = "Vec<BlockId>")]
 pub struct Palette {
     /// Maps palette index to block ID
     id_to_block: Vec<BlockId>,
     /// Maps block ID to palette index
-    block_to_id: HashMap<BlockId, u8>,
+    block_to_id: HashMap<BlockId, u16>,
 }
 
 impl Palette {
@@ -128,30 +757,28 @@ impl Palette {
     }
     
     /// Add a block to the palette, returning its palette index.
-    pub fn add_block(&mut self, block_id: BlockId) -> u8 {
+    pub fn add_block(&mut self, block_id: BlockId) -> u16 {
         if let Some(&palette_id) = self.block_to_id.get(&block_id) {
             return palette_id;
         }
         
-        let palette_id = self.id_to_block.len() as u8;
-        if palette_id == 255 {
-            panic!("Palette overflow: too many unique blocks in chunk");
-        }
-        
+        // At most `BlockId::MAX + 1` distinct blocks exist, so every new
+        // entry fits in a 16-bit index.
+        let palette_id = self.id_to_block.len() as u16;
         self.id_to_block.push(block_id);
         self.block_to_id.insert(block_id, palette_id);
         palette_id
     }
     
     /// Get block ID from palette index.
-    pub fn get_block(&self, palette_id: u8) -> BlockId {
+    pub fn get_block(&self, palette_id: u16) -> BlockId {
         self.id_to_block.get(palette_id as usize)
             .copied()
             .unwrap_or(AIR_BLOCK)
     }
     
     /// Get palette index for block ID.
-    pub fn get_palette_id(&self, block_id: BlockId) -> Option<u8> {
+    pub fn get_palette_id(&self, block_id: BlockId) -> Option<u16> {
         self.block_to_id.get(&block_id).copied()
     }
     
@@ -165,9 +792,9 @@ impl Palette {
     }
     
     /// Iterate over (palette_id, block_id) pairs.
-    pub fn iter(&self) -> impl Iterator<Item = (u8, BlockId)> + '_ {
+    pub fn iter(&self) -> impl Iterator<Item = (u16, BlockId)> + '_ {
         self.id_to_block.iter().enumerate()
-            .map(|(i, &block_id)| (i as u8, block_id))
+            .map(|(i, &block_id)| (i as u16, block_id))
     }
 }
 
@@ -177,26 +804,64 @@ impl Default for Palette {
     }
 }
 
+impl PartialEq for Palette {
+    fn eq(&self, other: &Self) -> bool {
+        self.id_to_block == other.id_to_block
+    }
+}
+
+impl Eq for Palette {}
+
+impl From<Palette> for Vec<BlockId> {
+    fn from(palette: Palette) -> Self {
+        palette.id_to_block
+    }
+}
+
+impl TryFrom<Vec<BlockId>> for Palette {
+    type Error = String;
+
+    fn try_from(blocks: Vec<BlockId>) -> Result<Self, Self::Error> {
+        if blocks.first() != Some(&AIR_BLOCK) {
+            return Err("palette must start with air".to_string());
+        }
+
+        let mut palette = Palette::new();
+        for &block_id in &blocks[1..] {
+            if palette.get_palette_id(block_id).is_some() {
+                return Err(format!("duplicate palette entry for block {}", block_id));
+            }
+            palette.add_block(block_id);
+        }
+        Ok(palette)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// First ID after the built-in blocks.
+    fn first_free_id(registry: &BlockRegistry) -> BlockId {
+        registry.iter().map(|block| block.states().end).max().unwrap()
+    }
+
     #[test]
     fn test_block_registry() {
         let registry = BlockRegistry::new();
 
         // Test default blocks
         assert!(registry.get(AIR_BLOCK).is_some());
-        assert_eq!(registry.get(AIR_BLOCK).unwrap().name, "air");
+        assert_eq!(registry.get(AIR_BLOCK).unwrap().name, "core:air");
         assert_eq!(registry.get_kind(AIR_BLOCK), BlockKind::Air);
-        assert!(registry.is_air(AIR_BLOCK));
-        assert!(!registry.is_solid(AIR_BLOCK));
+        assert!(!registry.is_visible(AIR_BLOCK));
+        assert!(!registry.is_opaque(AIR_BLOCK));
 
         let stone = registry.get_by_name("stone").unwrap();
         assert_eq!(stone.id, 1);
         assert_eq!(stone.kind, BlockKind::Solid);
-        assert!(registry.is_solid(stone.id));
-        assert!(!registry.is_air(stone.id));
+        assert!(registry.is_opaque(stone.id));
+        assert!(registry.is_visible(stone.id));
 
         // Test unknown block
         assert!(registry.get(999).is_none());
@@ -229,4 +894,348 @@ mod tests {
         let blocks: Vec<_> = palette.iter().collect();
         assert_eq!(blocks, vec![(0, AIR_BLOCK), (1, stone_id)]);
     }
+
+    #[test]
+    fn test_palette_serde() {
+        let mut palette = Palette::new();
+        palette.add_block(3);
+        palette.add_block(1);
+
+        let json = serde_json::to_string(&palette).unwrap();
+        assert_eq!(json, "[0,3,1]");
+
+        let loaded: Palette = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, palette);
+        assert_eq!(loaded.get_palette_id(1), Some(2)); // Reverse map rebuilt
+
+        assert!(serde_json::from_str::<Palette>("[1,0]").is_err());
+        assert!(serde_json::from_str::<Palette>("[0,2,2]").is_err());
+        assert!(serde_json::from_str::<Palette>("[]").is_err());
+    }
+
+    #[test]
+    fn test_block_def_serde() {
+        let grass = BlockDef::grass();
+        let json = serde_json::to_string(&grass).unwrap();
+        let loaded: BlockDef = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, grass);
+        assert_eq!(serde_json::to_string(&BlockKind::Solid).unwrap(), "\"Solid\"");
+    }
+
+    #[test]
+    fn test_palette_beyond_255_entries() {
+        let mut palette = Palette::new();
+        for block_id in 1..=1000 {
+            assert_eq!(palette.add_block(block_id), block_id);
+        }
+        assert_eq!(palette.len(), 1001);
+        assert_eq!(palette.get_block(1000), 1000);
+        assert_eq!(palette.get_palette_id(777), Some(777));
+
+        // Every possible block ID fits
+        for block_id in 1001..=BlockId::MAX {
+            palette.add_block(block_id);
+        }
+        assert_eq!(palette.len(), BlockId::MAX as usize + 1);
+        assert_eq!(palette.add_block(BlockId::MAX), BlockId::MAX);
+    }
+
+    #[test]
+    fn test_state_enumeration() {
+        let mut registry = BlockRegistry::new();
+        let door = BlockDef::new("door", BlockKind::Solid, 7)
+            .with_property(BlockProperty::bool("open"))
+            .with_property(BlockProperty::enumeration("facing", ["north", "east", "south", "west"]))
+            .with_property(BlockProperty::int("height", 0, 2));
+        let id = first_free_id(&registry);
+        assert_eq!(registry.register(door), id); // Right after the built-in states
+
+        let door = registry.get_by_name("door").unwrap();
+        assert_eq!(door.state_count(), 2 * 4 * 3);
+        assert_eq!(door.states(), id..id + 24);
+
+        // The default state is the block ID; the last property varies fastest
+        assert_eq!(registry.state("door", []), Some(id));
+        assert_eq!(registry.state("door", [("height", "1")]), Some(id + 1));
+        assert_eq!(registry.state("door", [("facing", "east")]), Some(id + 3));
+        assert_eq!(registry.state("door", [("open", "true"), ("facing", "west"), ("height", "2")]), Some(id + 23));
+
+        // Every state ID resolves to the door
+        for state in door.states() {
+            assert_eq!(registry.get(state).unwrap().name, "core:door");
+            assert!(registry.is_opaque(state));
+        }
+        assert!(registry.get(id + 24).is_none());
+        assert_ne!(registry.get(id - 1).unwrap().name, "core:door");
+
+        // Unknown properties and values are rejected
+        assert_eq!(registry.state("door", [("color", "red")]), None);
+        assert_eq!(registry.state("door", [("height", "3")]), None);
+        assert_eq!(registry.state("door", [("open", "yes")]), None);
+        assert_eq!(registry.state("window", []), None);
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let registry = BlockRegistry::new();
+
+        let log_x = registry.state("oak_log", [("axis", "x")]).unwrap();
+        assert_eq!(registry.state_values(log_x), Some(vec![("axis", "x".to_string())]));
+        assert_eq!(registry.get_by_name("oak_log").unwrap().id, registry.state("oak_log", [("axis", "y")]).unwrap());
+
+        for block in registry.iter() {
+            for state in block.states() {
+                let values = registry.state_values(state).unwrap();
+                let pairs: Vec<(&str, &str)> = values.iter()
+                    .map(|(name, value)| (*name, value.as_str()))
+                    .collect();
+                assert_eq!(registry.state(&block.name, pairs), Some(state));
+            }
+        }
+
+        // Plain blocks have a single state without properties
+        assert_eq!(registry.state("stone", []), Some(1));
+        assert_eq!(registry.state_values(1), Some(vec![]));
+
+        // States are stored in palettes like any other ID
+        let mut palette = Palette::new();
+        let log_z = registry.state("oak_log", [("axis", "z")]).unwrap();
+        palette.add_block(log_x);
+        palette.add_block(log_z);
+        let loaded: Palette = serde_json::from_str(&serde_json::to_string(&palette).unwrap()).unwrap();
+        assert_eq!(registry.state_values(loaded.get_block(2)), Some(vec![("axis", "z".to_string())]));
+    }
+
+    #[test]
+    fn test_property_serde() {
+        let log = BlockDef::oak_log();
+        let json = serde_json::to_string(&log).unwrap();
+        assert!(json.contains(r#""properties":[{"name":"axis","kind":{"enum":["y","x","z"]}}]"#), "{}", json);
+        assert_eq!(serde_json::from_str::<BlockDef>(&json).unwrap(), log);
+
+        // Plain blocks don't need a properties field
+        let stone: BlockDef = serde_json::from_str(r#"{"name":"core:stone","kind":"Solid","texture_id":1,"hardness":1.5}"#).unwrap();
+        assert_eq!(stone, BlockDef::stone());
+
+        let slab = BlockProperty::int("layers", 1, 8);
+        assert_eq!(serde_json::to_string(&slab.kind).unwrap(), r#"{"int":{"min":1,"max":8}}"#);
+        assert_eq!(slab.value_count(), 8);
+        assert_eq!(slab.value_name(7), "8");
+    }
+
+    #[test]
+    fn test_automatic_ids() {
+        let mut registry = BlockRegistry::new();
+        let first = first_free_id(&registry);
+        assert_eq!(registry.get_by_name("core:stone").unwrap().id, 1);
+        assert_eq!(registry.get_by_name("stone").unwrap().id, 1);
+
+        // The ID in the definition is ignored and nothing is overwritten
+        let mut ore = BlockDef::new("mymod:ore", BlockKind::Solid, 9);
+        ore.id = 1;
+        let ore_id = registry.register(ore);
+        assert_eq!(ore_id, first);
+        assert_eq!(registry.get(1).unwrap().name, "core:stone");
+        assert!(registry.get_by_name("ore").is_none());
+
+        // Re-registering keeps the ID; more states need a new range
+        assert_eq!(registry.register(BlockDef::new("mymod:ore", BlockKind::Solid, 10)), ore_id);
+        assert_eq!(registry.get(ore_id).unwrap().texture_id, 10);
+        let lamp = BlockDef::new("mymod:lamp", BlockKind::Solid, 2);
+        assert_eq!(registry.register(lamp.clone()), first + 1);
+        assert_eq!(registry.register(BlockDef::new("mymod:gem", BlockKind::Solid, 3)), first + 2);
+        assert_eq!(registry.register(lamp.with_property(BlockProperty::bool("lit"))), first + 3);
+
+        // Freed ranges are reused
+        registry.unregister("mymod:ore");
+        assert_eq!(registry.register(BlockDef::new("mymod:slate", BlockKind::Solid, 3)), first);
+        assert_eq!(registry.register(BlockDef::new("mymod:marble", BlockKind::Solid, 3)), first + 1);
+    }
+
+    #[test]
+    fn test_register_errors() {
+        let mut registry = BlockRegistry::new();
+        let wide = BlockProperty::int("value", 0, i32::MAX - 1);
+        let dial = BlockDef::new("dial", BlockKind::Solid, 1)
+            .with_property(wide.clone())
+            .with_property(wide.clone())
+            .with_property(wide);
+        assert_eq!(dial.checked_state_count(), None);
+        assert_eq!(registry.try_register(dial), Err(RegisterError::TooManyStates { block: "core:dial".into() }));
+
+        let sign = BlockDef::new("sign", BlockKind::Solid, 1)
+            .with_property(BlockProperty { name: "text".into(), kind: PropertyKind::Enum(Vec::new()) });
+        assert_eq!(registry.try_register(sign), Err(RegisterError::NoStates { block: "core:sign".into() }));
+
+        // A failed override leaves the old definition in place
+        let gauge = |max| BlockDef::new("gauge", BlockKind::Solid, 1)
+            .with_property(BlockProperty::int("value", 0, max));
+        let id = registry.register(gauge(99));
+        assert!(registry.try_register(gauge(BlockId::MAX as i32)).is_err());
+        assert_eq!(registry.get_by_name("gauge").map(|block| (block.id, block.state_count())), Some((id, 100)));
+        assert_eq!(registry.iter().count(), BlockRegistry::new().iter().count() + 1);
+    }
+
+    #[test]
+    fn test_remap_from_table() {
+        let mut saved = BlockRegistry::new();
+        let ore = saved.register(BlockDef::new("mymod:ore", BlockKind::Solid, 9));
+        let gem = saved.register(BlockDef::new("mymod:gem", BlockKind::Solid, 9)
+            .with_property(BlockProperty::bool("polished")));
+        let table = saved.id_table();
+        assert_eq!(table.blocks.len(), BlockRegistry::new().iter().count() + 2);
+        assert_eq!(table.blocks.last().unwrap(), &BlockIdEntry { name: "mymod:gem".into(), id: gem, states: 2 });
+
+        // The same registry maps every ID to itself
+        assert!(saved.clone().remap_from(&table).unwrap().is_identity());
+
+        // A registry where the gem comes first and the ore mod is missing
+        let mut registry = BlockRegistry::new();
+        let new_gem = registry.register(BlockDef::new("mymod:gem", BlockKind::Solid, 9)
+            .with_property(BlockProperty::bool("polished")));
+        let remap = registry.remap_from(&table).unwrap();
+        assert!(!remap.is_identity());
+        assert_eq!(remap.get(1), 1);
+        assert_eq!(remap.get(gem + 1), new_gem + 1);
+
+        let placeholder = remap.get(ore);
+        assert!(registry.is_placeholder(placeholder));
+        assert!(!registry.is_placeholder(new_gem));
+        assert_eq!(registry.get(placeholder).unwrap().name, "mymod:ore");
+        assert!(registry.is_opaque(placeholder) && registry.is_collidable(placeholder));
+
+        // Placeholders are saved under the original name and replaced once
+        // the block is defined again
+        let reloaded = registry.id_table();
+        assert!(reloaded.blocks.iter().any(|entry| entry.name == "mymod:ore" && entry.id == placeholder));
+        registry.register(BlockDef::new("mymod:ore", BlockKind::Solid, 9));
+        assert!(!registry.is_placeholder(placeholder));
+
+        // IDs the table doesn't know become air
+        assert_eq!(remap.get(500), AIR_BLOCK);
+
+        // Placeholders that don't fit are errors, not panics
+        let huge = BlockIdTable {
+            blocks: vec![BlockIdEntry { name: "mymod:huge".into(), id: 0, states: BlockId::MAX as usize + 1 }],
+        };
+        assert_eq!(registry.remap_from(&huge).unwrap_err(), RegisterError::TooManyStates { block: "mymod:huge".into() });
+        assert_eq!(registry.register_placeholder("mymod:empty", 0), Err(RegisterError::NoStates { block: "mymod:empty".into() }));
+        assert!(registry.get_by_name("mymod:huge").is_none());
+    }
+
+    #[test]
+    fn test_id_table_serde() {
+        let table = BlockRegistry::new().id_table();
+        let json = serde_json::to_string(&table).unwrap();
+        assert!(json.contains(r#"{"name":"core:oak_log","id":5,"states":3}"#), "{}", json);
+        assert_eq!(serde_json::from_str::<BlockIdTable>(&json).unwrap(), table);
+    }
+
+    #[test]
+    fn test_default_blocks_keep_behavior() {
+        let registry = BlockRegistry::new();
+        assert!(!registry.is_collidable(AIR_BLOCK));
+        assert_eq!(registry.hardness(AIR_BLOCK), 0.0);
+
+        let leaves = registry.get_by_name("leaves").unwrap();
+        assert_eq!(leaves.kind, BlockKind::Cutout);
+        assert!(registry.is_collidable(leaves.id) && registry.face_visible(leaves.id, leaves.id));
+        let water = registry.get_by_name("water").unwrap().id;
+        assert!(registry.is_liquid(water) && !registry.is_collidable(water));
+
+        let solid: Vec<_> = registry.iter().filter(|block| block.kind == BlockKind::Solid).collect();
+        assert_eq!(solid.len(), registry.iter().count() - 3, "only air, leaves and water aren't solid");
+        for block in solid {
+            for state in block.states() {
+                assert!(registry.is_opaque(state) && registry.is_visible(state), "{}", block.name);
+                assert!(registry.is_collidable(state), "{}", block.name);
+                assert!(!registry.is_liquid(state), "{}", block.name);
+                assert_eq!(registry.light_emission(state), 0, "{}", block.name);
+                assert!(registry.hardness(state) > 0.0, "{}", block.name);
+
+                // Faces show against air and hide against any other block
+                assert!(registry.face_visible(state, AIR_BLOCK));
+                assert!(!registry.face_visible(AIR_BLOCK, state));
+                for other in registry.iter().filter(|other| other.kind == BlockKind::Solid) {
+                    assert!(!registry.face_visible(state, other.id));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_block_kinds() {
+        let mut registry = BlockRegistry::new();
+        let stone = registry.get_by_name("stone").unwrap().id;
+        let glass = registry.register(BlockDef::new("glass", BlockKind::Transparent, 5).with_hardness(0.3));
+        let tinted = registry.register(BlockDef::new("tinted_glass", BlockKind::Transparent, 6));
+        let leaves = registry.get_by_name("leaves").unwrap().id;
+        let tall_grass = registry.register(BlockDef::new("tall_grass", BlockKind::Cutout, 8).with_collidable(false));
+        let water = registry.register(BlockDef::new("water", BlockKind::Liquid, 9)
+            .with_property(BlockProperty::int("level", 0, 7)));
+        let torch = registry.register(BlockDef::new("torch", BlockKind::Cutout, 10)
+            .with_collidable(false)
+            .with_light_emission(14));
+
+        assert!(!registry.is_opaque(glass) && registry.is_visible(glass));
+        assert!(registry.is_collidable(glass) && registry.is_collidable(leaves));
+        assert!(!registry.is_collidable(tall_grass) && !registry.is_collidable(water));
+        assert!(registry.is_liquid(water + 3) && !registry.is_liquid(glass));
+        assert_eq!(registry.light_emission(torch), 14);
+        assert_eq!(registry.hardness(glass), 0.3);
+
+        // Neighbours seen through glass are drawn; glass hides itself only
+        assert!(registry.face_visible(stone, glass));
+        assert!(!registry.face_visible(glass, stone));
+        assert!(!registry.face_visible(glass, glass));
+        assert!(registry.face_visible(glass, tinted));
+        // Leaves show their inner faces
+        assert!(registry.face_visible(leaves, leaves));
+        assert!(registry.face_visible(stone, leaves));
+        // Water hides faces against other water of any level
+        assert!(!registry.face_visible(water, water + 5));
+        assert!(registry.face_visible(water, glass));
+        assert!(registry.face_visible(stone, water));
+    }
+
+    #[test]
+    fn test_block_attribute_serde() {
+        let torch: BlockDef = serde_json::from_str(
+            r#"{"name":"torch","kind":"Cutout","texture_id":10,"collidable":false,"light_emission":14}"#,
+        ).unwrap();
+        assert_eq!(torch, BlockDef::new("torch", BlockKind::Cutout, 10).with_collidable(false).with_light_emission(14));
+        assert_eq!(torch.hardness, 1.0);
+
+        let json = serde_json::to_string(&BlockDef::stone()).unwrap();
+        assert_eq!(json, r#"{"id":0,"name":"core:stone","kind":"Solid","texture_id":1,"hardness":1.5}"#);
+    }
+
+    #[test]
+    fn test_face_textures() {
+        let mut grass = BlockDef::new("grass", BlockKind::Solid, 3)
+            .with_texture("dirt")
+            .with_face_texture(BlockFace::Top, "grass_top");
+        grass.faces.side = Some("grass_side".into());
+
+        assert_eq!(grass.face_texture(BlockFace::Top), Some("grass_top"));
+        assert_eq!(grass.face_texture(BlockFace::Bottom), Some("dirt"));
+        for face in BlockFace::ALL.into_iter().filter(|face| face.is_side()) {
+            assert_eq!(grass.face_texture(face), Some("grass_side"));
+        }
+        assert_eq!(grass.texture_names(), vec!["dirt", "grass_top", "grass_side"]);
+
+        // A single face can override the sides
+        let furnace = BlockDef::new("furnace", BlockKind::Solid, 1)
+            .with_face_texture(BlockFace::North, "furnace_front");
+        assert_eq!(furnace.face_texture(BlockFace::North), Some("furnace_front"));
+        assert_eq!(furnace.face_texture(BlockFace::South), None);
+        assert!(BlockDef::stone().texture_names().is_empty());
+
+        assert_eq!(BlockFace::from_axis(1, true), BlockFace::Top);
+        assert_eq!(BlockFace::from_axis(2, false), BlockFace::North);
+
+        let json = serde_json::to_string(&grass).unwrap();
+        assert!(json.contains(r#""faces":{"top":"grass_top","side":"grass_side"}"#), "{}", json);
+        assert_eq!(serde_json::from_str::<BlockDef>(&json).unwrap(), grass);
+    }
 }
@@ -1,6 +1,27 @@
+use std::fmt::Display;
+use std::path::Path;
+
 use bevy::prelude::*;
+use voxel_core::BlockRegistry;
+use voxel_render::{ChunkViewer, VoxelWorldPlugin};
+use voxel_world::{StructureRule, TerrainConfig, WorldSettings};
 
 fn main() {
+    // Built-in blocks plus any defined in assets/blocks
+    let registry = or_exit_all(BlockRegistry::from_assets(Path::new("assets")));
+
+    // Terrain settings from assets/terrain.ron, if present
+    let config = or_exit(load_optional(Path::new("assets/terrain.ron"), TerrainConfig::load));
+
+    // Generator choice from assets/world.ron, if present
+    let settings = or_exit(load_optional(Path::new("assets/world.ron"), WorldSettings::load));
+
+    // Structures placed during generation, from assets/structures
+    let structures = or_exit_all(StructureRule::load_dir(Path::new("assets/structures"), &registry));
+
+    // Fails if the generator needs blocks the registry lacks
+    let generator = or_exit(settings.generator.build(config, registry, structures));
+
     App::new()
         .insert_resource(ClearColor(Color::rgb(0.06, 0.07, 0.09)))
         .add_plugins(
@@ -14,16 +35,48 @@ fn main() {
                 ..default()
             }),
         )
+        .add_plugins(VoxelWorldPlugin {
+            generator: generator.into(),
+            ..default()
+        })
         .add_systems(Startup, setup)
         .run();
 }
 
+/// Load a settings file if it exists, or use the defaults.
+fn load_optional<T: Default, E>(path: &Path, load: impl FnOnce(&Path) -> Result<T, E>) -> Result<T, E> {
+    if path.exists() {
+        load(path)
+    } else {
+        Ok(T::default())
+    }
+}
+
+/// Unwrap a loaded value, or print the error and quit.
+fn or_exit<T, E: Display>(result: Result<T, E>) -> T {
+    or_exit_all(result.map_err(std::iter::once))
+}
+
+/// Unwrap a loaded value, or print every error and quit.
+fn or_exit_all<T, E: Display>(result: Result<T, impl IntoIterator<Item = E>>) -> T {
+    result.unwrap_or_else(|errors| {
+        for error in errors {
+            eprintln!("error: {}", error);
+        }
+        std::process::exit(1);
+    })
+}
+
 fn setup(mut commands: Commands) {
-    // Camera
-    commands.spawn(Camera3dBundle {
-        transform: Transform::from_xyz(8.0, 8.0, 8.0).looking_at(Vec3::ZERO, Vec3::Y),
-        ..default()
-    });
+    // Camera (chunks stream around it)
+    commands.spawn((
+        Camera3dBundle {
+            transform: Transform::from_xyz(-48.0, 120.0, -48.0)
+                .looking_at(Vec3::new(8.0, 64.0, 8.0), Vec3::Y),
+            ..default()
+        },
+        ChunkViewer,
+    ));
 
     // Directional light
     commands.spawn(DirectionalLightBundle {
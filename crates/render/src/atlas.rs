@@ -0,0 +1,336 @@
+//! Packing block textures into a single atlas image.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use voxel_core::BlockRegistry;
+
+/// Texture coordinates of one texture in an atlas, from 0 to 1 with the
+/// origin at the top-left corner of the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+/// Why an atlas couldn't be built.
+#[derive(Debug)]
+pub enum AtlasError {
+    /// A texture file doesn't exist
+    MissingTexture { name: String, path: PathBuf },
+    /// A texture file couldn't be read
+    Io { path: PathBuf, error: io::Error },
+    /// A texture isn't a valid PNG
+    Decode { name: String, message: String },
+    /// A texture's size differs from the textures added before it
+    SizeMismatch { name: String, expected: (u32, u32), found: (u32, u32) },
+    /// The pixel data doesn't hold `width x height` RGBA pixels
+    InvalidSize { name: String, width: u32, height: u32, len: usize },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasError::MissingTexture { name, path } => {
+                write!(f, "texture \"{}\" not found at {}", name, path.display())
+            }
+            AtlasError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            AtlasError::Decode { name, message } => write!(f, "texture \"{}\": {}", name, message),
+            AtlasError::SizeMismatch { name, expected, found } => write!(
+                f,
+                "texture \"{}\" is {}x{} but the atlas uses {}x{} textures",
+                name, found.0, found.1, expected.0, expected.1
+            ),
+            AtlasError::InvalidSize { name, width, height, len } => write!(
+                f,
+                "texture \"{}\" has {} bytes of pixels, which aren't {}x{} RGBA pixels",
+                name, len, width, height
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AtlasError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// An RGBA8 image holding equally sized textures in a grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureAtlas {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major RGBA8 pixels
+    pub pixels: Vec<u8>,
+    rects: HashMap<String, UvRect>,
+}
+
+impl TextureAtlas {
+    /// Pack every texture used by the blocks in `registry`, loading
+    /// `<name>.png` files from `textures_dir`.
+    pub fn from_registry(registry: &BlockRegistry, textures_dir: &Path) -> Result<Self, AtlasError> {
+        let mut names: Vec<&str> = registry.iter()
+            .flat_map(|block| block.texture_names())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+
+        let mut builder = TextureAtlasBuilder::new();
+        for name in names {
+            builder.load_png(textures_dir, name)?;
+        }
+        Ok(builder.build())
+    }
+
+    /// Get where a texture lies in the atlas.
+    pub fn uv_rect(&self, name: &str) -> Option<UvRect> {
+        self.rects.get(name).copied()
+    }
+
+    /// Number of textures in the atlas.
+    pub fn len(&self) -> usize {
+        self.rects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Get the RGBA value of an atlas pixel.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let start = ((y * self.width + x) * 4) as usize;
+        self.pixels[start..start + 4].try_into().unwrap()
+    }
+}
+
+/// A decoded texture waiting to be packed.
+#[derive(Debug, Clone)]
+struct Texture {
+    name: String,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// Collects textures of one size and packs them into a `TextureAtlas`.
+#[derive(Debug, Clone, Default)]
+pub struct TextureAtlasBuilder {
+    textures: Vec<Texture>,
+}
+
+impl TextureAtlasBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a texture from RGBA8 pixels. Every texture must have the size of
+    /// the first one; adding a name again replaces its image.
+    pub fn add_rgba(&mut self, name: &str, width: u32, height: u32, pixels: Vec<u8>) -> Result<(), AtlasError> {
+        let expected_len = (width as usize).checked_mul(height as usize).and_then(|count| count.checked_mul(4));
+        if expected_len != Some(pixels.len()) {
+            return Err(AtlasError::InvalidSize { name: name.to_string(), width, height, len: pixels.len() });
+        }
+
+        if let Some(first) = self.textures.first() {
+            if (first.width, first.height) != (width, height) {
+                return Err(AtlasError::SizeMismatch {
+                    name: name.to_string(),
+                    expected: (first.width, first.height),
+                    found: (width, height),
+                });
+            }
+        }
+
+        let texture = Texture {
+            name: name.to_string(),
+            width,
+            height,
+            pixels,
+        };
+        match self.textures.iter_mut().find(|texture| texture.name == name) {
+            Some(existing) => *existing = texture,
+            None => self.textures.push(texture),
+        }
+        Ok(())
+    }
+
+    /// Add a texture from the bytes of a PNG file.
+    pub fn add_png(&mut self, name: &str, bytes: &[u8]) -> Result<(), AtlasError> {
+        let (width, height, pixels) = decode_png(name, bytes)?;
+        self.add_rgba(name, width, height, pixels)
+    }
+
+    /// Add the texture `<name>.png` from `textures_dir`.
+    pub fn load_png(&mut self, textures_dir: &Path, name: &str) -> Result<(), AtlasError> {
+        let path = textures_dir.join(format!("{}.png", name));
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(AtlasError::MissingTexture { name: name.to_string(), path });
+            }
+            Err(error) => return Err(AtlasError::Io { path, error }),
+        };
+        self.add_png(name, &bytes)
+    }
+
+    /// Lay the textures out in a near-square grid, in the order they were
+    /// added.
+    pub fn build(self) -> TextureAtlas {
+        let Some(first) = self.textures.first() else {
+            return TextureAtlas {
+                width: 0,
+                height: 0,
+                pixels: Vec::new(),
+                rects: HashMap::new(),
+            };
+        };
+
+        let (tile_width, tile_height) = (first.width, first.height);
+        let count = self.textures.len() as u32;
+        let columns = (count as f64).sqrt().ceil() as u32;
+        let rows = count.div_ceil(columns);
+        let (width, height) = (columns * tile_width, rows * tile_height);
+
+        let mut pixels = vec![0; (width * height * 4) as usize];
+        let mut rects = HashMap::new();
+        for (i, texture) in self.textures.into_iter().enumerate() {
+            let (x, y) = (i as u32 % columns * tile_width, i as u32 / columns * tile_height);
+            let row_len = (tile_width * 4) as usize;
+            for (row, src) in texture.pixels.chunks_exact(row_len).enumerate() {
+                let start = (((y + row as u32) * width + x) * 4) as usize;
+                pixels[start..start + row_len].copy_from_slice(src);
+            }
+
+            rects.insert(texture.name, UvRect {
+                min: [x as f32 / width as f32, y as f32 / height as f32],
+                max: [(x + tile_width) as f32 / width as f32, (y + tile_height) as f32 / height as f32],
+            });
+        }
+
+        TextureAtlas { width, height, pixels, rects }
+    }
+}
+
+/// Decode a PNG into its size and RGBA8 pixels.
+fn decode_png(name: &str, bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), AtlasError> {
+    let decode_error = |message: String| AtlasError::Decode { name: name.to_string(), message };
+
+    let mut decoder = png::Decoder::new(bytes);
+    // Expand palettes and bit depths so every image is 8-bit gray or RGB
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| decode_error(err.to_string()))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|err| decode_error(err.to_string()))?;
+    buf.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return Err(decode_error("unexpanded palette image".to_string())),
+    };
+    Ok((info.width, info.height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use voxel_core::{BlockDef, BlockFace, BlockKind};
+
+    /// Encode a solid-colour PNG.
+    fn png(width: u32, height: u32, color: png::ColorType, pixel: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&pixel.repeat((width * height) as usize)).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_pack_textures() {
+        let mut builder = TextureAtlasBuilder::new();
+        builder.add_png("red", &png(2, 2, png::ColorType::Rgba, &[255, 0, 0, 255])).unwrap();
+        builder.add_png("green", &png(2, 2, png::ColorType::Rgb, &[0, 255, 0])).unwrap();
+        builder.add_png("gray", &png(2, 2, png::ColorType::Grayscale, &[128])).unwrap();
+
+        let atlas = builder.build();
+        assert_eq!((atlas.width, atlas.height, atlas.len()), (4, 4, 3));
+        assert_eq!(atlas.pixels.len(), 4 * 4 * 4);
+
+        assert_eq!(atlas.uv_rect("red"), Some(UvRect { min: [0.0, 0.0], max: [0.5, 0.5] }));
+        assert_eq!(atlas.uv_rect("green"), Some(UvRect { min: [0.5, 0.0], max: [1.0, 0.5] }));
+        assert_eq!(atlas.uv_rect("gray"), Some(UvRect { min: [0.0, 0.5], max: [0.5, 1.0] }));
+        assert_eq!(atlas.uv_rect("blue"), None);
+
+        assert_eq!(atlas.pixel(1, 1), [255, 0, 0, 255]);
+        assert_eq!(atlas.pixel(2, 0), [0, 255, 0, 255]);
+        assert_eq!(atlas.pixel(3, 1), [0, 255, 0, 255]);
+        assert_eq!(atlas.pixel(0, 3), [128, 128, 128, 255]);
+        assert_eq!(atlas.pixel(3, 3), [0, 0, 0, 0]); // Unused tile
+
+        assert!(TextureAtlasBuilder::new().build().is_empty());
+    }
+
+    #[test]
+    fn test_size_mismatch() {
+        let mut builder = TextureAtlasBuilder::new();
+        builder.add_rgba("a", 2, 2, vec![0; 16]).unwrap();
+        let err = builder.add_png("b", &png(4, 2, png::ColorType::Rgb, &[1, 2, 3])).unwrap_err();
+        match err {
+            AtlasError::SizeMismatch { name, expected, found } => {
+                assert_eq!((name.as_str(), expected, found), ("b", (2, 2), (4, 2)));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        let err = builder.add_png("c", b"not a png").unwrap_err();
+        assert!(matches!(err, AtlasError::Decode { .. }));
+
+        // Pixel data of the wrong length, even for sizes overflowing `u32`
+        let err = builder.add_rgba("d", 2, 2, vec![0; 12]).unwrap_err();
+        assert!(matches!(err, AtlasError::InvalidSize { len: 12, .. }), "{:?}", err);
+        let err = builder.add_rgba("e", u32::MAX, u32::MAX, vec![0; 16]).unwrap_err();
+        assert!(matches!(err, AtlasError::InvalidSize { .. }), "{:?}", err);
+        assert_eq!(builder.build().len(), 1);
+    }
+
+    #[test]
+    fn test_from_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("dirt.png"), png(2, 2, png::ColorType::Rgb, &[90, 60, 30])).unwrap();
+        fs::write(dir.path().join("grass_top.png"), png(2, 2, png::ColorType::Rgb, &[40, 160, 40])).unwrap();
+
+        let mut registry = BlockRegistry::new();
+        registry.register(BlockDef::new("grass", BlockKind::Solid, 3)
+            .with_texture("dirt")
+            .with_face_texture(BlockFace::Top, "grass_top"));
+        registry.register(BlockDef::new("path", BlockKind::Solid, 3).with_texture("dirt"));
+
+        let atlas = TextureAtlas::from_registry(&registry, dir.path()).unwrap();
+        assert_eq!(atlas.len(), 2);
+        let grass = registry.get_by_name("grass").unwrap();
+        let top = atlas.uv_rect(grass.face_texture(BlockFace::Top).unwrap()).unwrap();
+        let side = atlas.uv_rect(grass.face_texture(BlockFace::East).unwrap()).unwrap();
+        assert_ne!(top, side);
+
+        registry.register(BlockDef::new("gravel", BlockKind::Solid, 3).with_face_texture(BlockFace::Bottom, "gravel"));
+        match TextureAtlas::from_registry(&registry, dir.path()).unwrap_err() {
+            AtlasError::MissingTexture { name, path } => {
+                assert_eq!(name, "gravel");
+                assert_eq!(path, dir.path().join("gravel.png"));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+}
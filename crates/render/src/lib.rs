@@ -1,6 +1,11 @@
-//! Rendering and meshing systems (to be implemented in later milestones).
+//! Rendering and meshing systems.
 
-pub fn placeholder() -> &'static str {
-    "render"
-}
+pub mod atlas;
+pub mod mesh;
+pub mod neighborhood;
+pub mod plugin;
 
+pub use atlas::*;
+pub use mesh::*;
+pub use neighborhood::*;
+pub use plugin::*;
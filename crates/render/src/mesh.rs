@@ -0,0 +1,319 @@
+//! Greedy meshing of chunk voxel data into vertex buffers.
+
+use voxel_core::{BlockId, BlockRegistry, IVec3, AIR_BLOCK, CHUNK_HEIGHT, CHUNK_SIZE};
+use voxel_world::Chunk;
+
+/// CPU-side mesh data ready to be uploaded to the GPU.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChunkMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+impl ChunkMesh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of quads (two triangles each) in the mesh.
+    pub fn quad_count(&self) -> usize {
+        self.indices.len() / 6
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Append a quad spanning `size_u` by `size_v` blocks.
+    ///
+    /// `axis` is the face normal axis (0 = x, 1 = y, 2 = z) and `positive`
+    /// selects which side of the block the face points to.
+    fn push_quad(&mut self, axis: usize, positive: bool, origin: [f32; 3], size_u: u32, size_v: u32) {
+        let u_axis = (axis + 1) % 3;
+        let v_axis = (axis + 2) % 3;
+
+        let mut du = [0.0; 3];
+        du[u_axis] = size_u as f32;
+        let mut dv = [0.0; 3];
+        dv[v_axis] = size_v as f32;
+
+        let mut normal = [0.0; 3];
+        normal[axis] = if positive { 1.0 } else { -1.0 };
+
+        let base = self.positions.len() as u32;
+        let corners = [
+            origin,
+            add(origin, du),
+            add(add(origin, du), dv),
+            add(origin, dv),
+        ];
+        let uvs = [
+            [0.0, 0.0],
+            [size_u as f32, 0.0],
+            [size_u as f32, size_v as f32],
+            [0.0, size_v as f32],
+        ];
+
+        self.positions.extend_from_slice(&corners);
+        self.normals.extend_from_slice(&[normal; 4]);
+        self.uvs.extend_from_slice(&uvs);
+
+        // u x v points along +axis, so keep counter-clockwise winding as seen
+        // from the side the face is pointing to.
+        if positive {
+            self.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        } else {
+            self.indices.extend_from_slice(&[base, base + 2, base + 1, base, base + 3, base + 2]);
+        }
+    }
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn from_components(c: [i32; 3]) -> IVec3 {
+    IVec3::new(c[0], c[1], c[2])
+}
+
+/// Build a mesh for a single chunk, treating everything outside it as air.
+pub fn mesh_chunk(chunk: &Chunk, registry: &BlockRegistry) -> ChunkMesh {
+    greedy_mesh(registry, |local_pos| chunk.get_block(local_pos))
+}
+
+/// Greedy mesher over a `CHUNK_SIZE x CHUNK_HEIGHT x CHUNK_SIZE` volume.
+///
+/// `sample` returns the block at a local position and may be queried one
+/// block outside the volume on every side to decide whether border faces are
+/// visible. Faces are emitted where `BlockRegistry::face_visible` says the
+/// neighbour doesn't hide them, and coplanar faces of the same block type
+/// are merged into larger quads.
+pub fn greedy_mesh<F>(registry: &BlockRegistry, sample: F) -> ChunkMesh
+where
+    F: Fn(IVec3) -> BlockId,
+{
+    let dims = [CHUNK_SIZE, CHUNK_HEIGHT, CHUNK_SIZE];
+    let mut mesh = ChunkMesh::new();
+
+    for axis in 0..3 {
+        let u_axis = (axis + 1) % 3;
+        let v_axis = (axis + 2) % 3;
+        let (size_u, size_v) = (dims[u_axis] as usize, dims[v_axis] as usize);
+        let mut mask = vec![AIR_BLOCK; size_u * size_v];
+
+        for positive in [true, false] {
+            let step = if positive { 1 } else { -1 };
+
+            for slice in 0..dims[axis] {
+                // Build the visibility mask for this slice.
+                for v in 0..size_v {
+                    for u in 0..size_u {
+                        let mut c = [0; 3];
+                        c[axis] = slice;
+                        c[u_axis] = u as i32;
+                        c[v_axis] = v as i32;
+                        let pos = from_components(c);
+
+                        let block = sample(pos);
+                        c[axis] += step;
+                        let visible = registry.face_visible(block, sample(from_components(c)));
+
+                        mask[v * size_u + u] = if visible { block } else { AIR_BLOCK };
+                    }
+                }
+
+                // Merge runs of identical faces into rectangles.
+                for v in 0..size_v {
+                    let mut u = 0;
+                    while u < size_u {
+                        let block = mask[v * size_u + u];
+                        if block == AIR_BLOCK {
+                            u += 1;
+                            continue;
+                        }
+
+                        let mut width = 1;
+                        while u + width < size_u && mask[v * size_u + u + width] == block {
+                            width += 1;
+                        }
+
+                        let mut height = 1;
+                        'grow: while v + height < size_v {
+                            let row = (v + height) * size_u;
+                            for k in u..u + width {
+                                if mask[row + k] != block {
+                                    break 'grow;
+                                }
+                            }
+                            height += 1;
+                        }
+
+                        for dv in 0..height {
+                            let row = (v + dv) * size_u;
+                            mask[row + u..row + u + width].fill(AIR_BLOCK);
+                        }
+
+                        let mut origin = [0.0; 3];
+                        origin[axis] = (slice + i32::from(positive)) as f32;
+                        origin[u_axis] = u as f32;
+                        origin[v_axis] = v as f32;
+                        mesh.push_quad(axis, positive, origin, width as u32, height as u32);
+
+                        u += width;
+                    }
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STONE: BlockId = 1;
+    const DIRT: BlockId = 2;
+
+    #[test]
+    fn test_empty_chunk() {
+        let registry = BlockRegistry::new();
+        let chunk = Chunk::new(IVec3::ZERO);
+
+        let mesh = mesh_chunk(&chunk, &registry);
+        assert!(mesh.is_empty());
+        assert_eq!(mesh.vertex_count(), 0);
+    }
+
+    #[test]
+    fn test_single_block() {
+        let registry = BlockRegistry::new();
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        chunk.set_block(IVec3::new(3, 10, 5), STONE);
+
+        let mesh = mesh_chunk(&chunk, &registry);
+        assert_eq!(mesh.quad_count(), 6);
+        assert_eq!(mesh.vertex_count(), 24);
+        assert_eq!(mesh.normals.len(), 24);
+        assert_eq!(mesh.uvs.len(), 24);
+        assert_eq!(mesh.indices.len(), 36);
+
+        // Every vertex lies on the unit cube around the block.
+        for p in &mesh.positions {
+            assert!(p[0] == 3.0 || p[0] == 4.0);
+            assert!(p[1] == 10.0 || p[1] == 11.0);
+            assert!(p[2] == 5.0 || p[2] == 6.0);
+        }
+    }
+
+    #[test]
+    fn test_winding_matches_normals() {
+        let registry = BlockRegistry::new();
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        chunk.set_block(IVec3::new(1, 1, 1), STONE);
+
+        let mesh = mesh_chunk(&chunk, &registry);
+        for tri in mesh.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[tri[i] as usize]);
+            let e1 = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let e2 = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+            let cross = [
+                e1[1] * e2[2] - e1[2] * e2[1],
+                e1[2] * e2[0] - e1[0] * e2[2],
+                e1[0] * e2[1] - e1[1] * e2[0],
+            ];
+            let n = mesh.normals[tri[0] as usize];
+            let dot = cross[0] * n[0] + cross[1] * n[1] + cross[2] * n[2];
+            assert!(dot > 0.0, "triangle faces away from its normal");
+        }
+    }
+
+    #[test]
+    fn test_adjacent_blocks_merge() {
+        let registry = BlockRegistry::new();
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        chunk.set_block(IVec3::new(4, 4, 4), STONE);
+        chunk.set_block(IVec3::new(5, 4, 4), STONE);
+
+        // Shared face is culled and the long sides merge into single quads.
+        let mesh = mesh_chunk(&chunk, &registry);
+        assert_eq!(mesh.quad_count(), 6);
+
+        // Different block types still cull the shared face but don't merge.
+        chunk.set_block(IVec3::new(5, 4, 4), DIRT);
+        let mesh = mesh_chunk(&chunk, &registry);
+        assert_eq!(mesh.quad_count(), 10);
+    }
+
+    #[test]
+    fn test_transparent_blocks() {
+        let mut registry = BlockRegistry::new();
+        let glass = registry.register(voxel_core::BlockDef::new("glass", voxel_core::BlockKind::Transparent, 5));
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        chunk.set_block(IVec3::new(4, 4, 4), glass);
+        chunk.set_block(IVec3::new(5, 4, 4), glass);
+
+        // Glass hides the faces between glass blocks
+        let mesh = mesh_chunk(&chunk, &registry);
+        assert_eq!(mesh.quad_count(), 6);
+
+        // Stone stays visible behind glass, glass isn't drawn against stone
+        chunk.set_block(IVec3::new(4, 4, 4), STONE);
+        let mesh = mesh_chunk(&chunk, &registry);
+        assert_eq!(mesh.quad_count(), 11);
+    }
+
+    #[test]
+    fn test_filled_chunk() {
+        let registry = BlockRegistry::new();
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        chunk.fill(STONE);
+
+        let mesh = mesh_chunk(&chunk, &registry);
+        assert_eq!(mesh.quad_count(), 6);
+
+        // Greedy merging covers the whole surface of the chunk.
+        let area: f32 = mesh
+            .indices
+            .chunks(6)
+            .map(|quad| {
+                // The third vertex of every quad carries its full extent.
+                let base = *quad.iter().min().unwrap();
+                let uv = mesh.uvs[base as usize + 2];
+                uv[0] * uv[1]
+            })
+            .sum();
+        let side = (CHUNK_SIZE * CHUNK_HEIGHT) as f32;
+        let top = (CHUNK_SIZE * CHUNK_SIZE) as f32;
+        assert_eq!(area, 4.0 * side + 2.0 * top);
+    }
+
+    #[test]
+    fn test_checkerboard() {
+        let registry = BlockRegistry::new();
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        let mut solid = 0;
+
+        for x in 0..4 {
+            for y in 0..4 {
+                for z in 0..4 {
+                    if (x + y + z) % 2 == 0 {
+                        chunk.set_block(IVec3::new(x, y, z), STONE);
+                        solid += 1;
+                    }
+                }
+            }
+        }
+
+        // No two solid blocks touch, so nothing can be culled or merged.
+        let mesh = mesh_chunk(&chunk, &registry);
+        assert_eq!(mesh.quad_count(), solid * 6);
+    }
+}
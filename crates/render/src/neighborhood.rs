@@ -0,0 +1,133 @@
+//! Padded chunk views so meshing can see across chunk borders.
+
+use voxel_core::{BlockId, BlockRegistry, IVec3, AIR_BLOCK, CHUNK_SIZE};
+use voxel_world::{Chunk, ChunkManager, HORIZONTAL_NEIGHBORS};
+
+use crate::mesh::{greedy_mesh, ChunkMesh};
+
+/// A chunk together with its four horizontal neighbours.
+///
+/// Missing neighbours are treated as air, so their border faces stay visible
+/// until the neighbour is loaded and the chunk is re-meshed.
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkNeighborhood<'a> {
+    pub center: &'a Chunk,
+    /// Neighbours in `HORIZONTAL_NEIGHBORS` order (-x, +x, -z, +z).
+    pub neighbors: [Option<&'a Chunk>; 4],
+}
+
+impl<'a> ChunkNeighborhood<'a> {
+    pub fn new(center: &'a Chunk, neighbors: [Option<&'a Chunk>; 4]) -> Self {
+        Self { center, neighbors }
+    }
+
+    /// Build the neighbourhood of a loaded chunk.
+    pub fn from_manager(manager: &'a ChunkManager, chunk_pos: IVec3) -> Option<Self> {
+        let center = manager.get_chunk(chunk_pos)?;
+        let neighbors = HORIZONTAL_NEIGHBORS.map(|offset| manager.get_chunk(chunk_pos + offset));
+        Some(Self::new(center, neighbors))
+    }
+
+    /// Get the block at coordinates local to the center chunk, reaching one
+    /// chunk into the horizontal neighbours.
+    pub fn get_block(&self, local_pos: IVec3) -> BlockId {
+        let (chunk, offset) = match (local_pos.x, local_pos.z) {
+            (x, _) if x < 0 => (self.neighbors[0], IVec3::new(CHUNK_SIZE, 0, 0)),
+            (x, _) if x >= CHUNK_SIZE => (self.neighbors[1], IVec3::new(-CHUNK_SIZE, 0, 0)),
+            (_, z) if z < 0 => (self.neighbors[2], IVec3::new(0, 0, CHUNK_SIZE)),
+            (_, z) if z >= CHUNK_SIZE => (self.neighbors[3], IVec3::new(0, 0, -CHUNK_SIZE)),
+            _ => (Some(self.center), IVec3::ZERO),
+        };
+
+        chunk
+            .map(|chunk| chunk.get_block(local_pos + offset))
+            .unwrap_or(AIR_BLOCK)
+    }
+}
+
+/// Build a mesh for a chunk, culling faces hidden by its neighbours.
+pub fn mesh_chunk_with_neighbors(neighborhood: &ChunkNeighborhood, registry: &BlockRegistry) -> ChunkMesh {
+    greedy_mesh(registry, |local_pos| neighborhood.get_block(local_pos))
+}
+
+/// Mesh a loaded chunk from a `ChunkManager`, or `None` if it isn't loaded.
+pub fn mesh_chunk_in_manager(
+    manager: &ChunkManager,
+    chunk_pos: IVec3,
+    registry: &BlockRegistry,
+) -> Option<ChunkMesh> {
+    ChunkNeighborhood::from_manager(manager, chunk_pos)
+        .map(|neighborhood| mesh_chunk_with_neighbors(&neighborhood, registry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::mesh_chunk;
+    use voxel_core::CHUNK_HEIGHT;
+
+    const STONE: BlockId = 1;
+
+    fn filled(pos: IVec3) -> Chunk {
+        let mut chunk = Chunk::new(pos);
+        chunk.fill(STONE);
+        chunk
+    }
+
+    #[test]
+    fn test_neighborhood_lookup() {
+        let mut manager = ChunkManager::new();
+        manager.insert_chunk(Chunk::new(IVec3::ZERO));
+        manager.set_block(IVec3::new(-1, 5, 3), STONE);
+        manager.set_block(IVec3::new(4, 5, 16), STONE);
+
+        let neighborhood = ChunkNeighborhood::from_manager(&manager, IVec3::ZERO).unwrap();
+        assert_eq!(neighborhood.get_block(IVec3::new(-1, 5, 3)), STONE);
+        assert_eq!(neighborhood.get_block(IVec3::new(4, 5, CHUNK_SIZE)), STONE);
+        assert_eq!(neighborhood.get_block(IVec3::new(CHUNK_SIZE, 5, 3)), AIR_BLOCK);
+        assert_eq!(neighborhood.get_block(IVec3::new(4, CHUNK_HEIGHT, 4)), AIR_BLOCK);
+
+        assert!(ChunkNeighborhood::from_manager(&manager, IVec3::new(5, 0, 5)).is_none());
+    }
+
+    #[test]
+    fn test_border_faces_culled() {
+        let registry = BlockRegistry::new();
+        let mut manager = ChunkManager::new();
+        manager.insert_chunk(filled(IVec3::ZERO));
+
+        // Alone, a filled chunk shows all six sides.
+        let mesh = mesh_chunk_in_manager(&manager, IVec3::ZERO, &registry).unwrap();
+        assert_eq!(mesh.quad_count(), 6);
+
+        for offset in HORIZONTAL_NEIGHBORS {
+            manager.insert_chunk(filled(offset));
+        }
+
+        // Surrounded, only top and bottom remain.
+        let mesh = mesh_chunk_in_manager(&manager, IVec3::ZERO, &registry).unwrap();
+        assert_eq!(mesh.quad_count(), 2);
+
+        // The single-chunk mesher still can't see the neighbours.
+        let chunk = manager.get_chunk(IVec3::ZERO).unwrap();
+        assert_eq!(mesh_chunk(chunk, &registry).quad_count(), 6);
+    }
+
+    #[test]
+    fn test_edge_edit_remeshes_neighbor() {
+        let registry = BlockRegistry::new();
+        let mut manager = ChunkManager::new();
+        manager.insert_chunk(filled(IVec3::ZERO));
+        manager.insert_chunk(filled(IVec3::new(1, 0, 0)));
+        manager.drain_remesh();
+
+        let before = mesh_chunk_in_manager(&manager, IVec3::new(1, 0, 0), &registry).unwrap();
+
+        // Digging out the +x edge of chunk 0 exposes a face in chunk 1.
+        manager.set_block(IVec3::new(15, 100, 8), AIR_BLOCK);
+        assert!(manager.needs_remesh(IVec3::new(1, 0, 0)));
+
+        let after = mesh_chunk_in_manager(&manager, IVec3::new(1, 0, 0), &registry).unwrap();
+        assert_ne!(before, after);
+    }
+}
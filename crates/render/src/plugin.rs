@@ -0,0 +1,306 @@
+//! Bevy integration: keeps chunk entities in sync with the `ChunkManager`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use voxel_core::{IVec3, CHUNK_SIZE};
+use voxel_world::{
+    BackgroundGenerator, ChunkManager, ChunkStreamer, StreamingConfig, TerrainConfig, TerrainGenerator,
+    WorldGenerator,
+};
+
+use crate::mesh::ChunkMesh;
+use crate::neighborhood::mesh_chunk_in_manager;
+
+/// Loaded chunks of the voxel world.
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct WorldChunks(pub ChunkManager);
+
+/// World generator used to fill in new chunks.
+#[derive(Resource, Clone, Deref)]
+pub struct WorldGen(pub Arc<dyn WorldGenerator>);
+
+/// Streams chunks around the `ChunkViewer` entity.
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct WorldStreamer(pub ChunkStreamer);
+
+/// Generates streamed chunks off the main thread.
+#[derive(Resource, Deref, DerefMut)]
+pub struct WorldBackground(pub BackgroundGenerator);
+
+/// Material shared by all chunk meshes.
+#[derive(Resource, Debug, Clone)]
+pub struct ChunkMaterial(pub Handle<StandardMaterial>);
+
+/// Maps loaded chunk positions to the entities rendering them.
+#[derive(Resource, Debug, Default)]
+pub struct ChunkEntities(pub HashMap<IVec3, Entity>);
+
+/// Marker for an entity that renders a chunk.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkEntity {
+    pub position: IVec3,
+}
+
+/// Marker for the entity (usually the camera) that chunks stream around.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct ChunkViewer;
+
+/// Plugin that owns the voxel world and spawns chunk meshes for it.
+#[derive(Clone)]
+pub struct VoxelWorldPlugin {
+    /// Fills in chunks; its registry is also used for meshing. Build one
+    /// from `GeneratorSettings`.
+    pub generator: Arc<dyn WorldGenerator>,
+    pub streaming: StreamingConfig,
+    /// Radius in chunks generated around the origin on startup
+    pub initial_radius: i32,
+}
+
+impl Default for VoxelWorldPlugin {
+    fn default() -> Self {
+        Self {
+            generator: Arc::new(TerrainGenerator::new(TerrainConfig::default())),
+            streaming: StreamingConfig::default(),
+            initial_radius: 4,
+        }
+    }
+}
+
+impl Plugin for VoxelWorldPlugin {
+    fn build(&self, app: &mut App) {
+        let generator = self.generator.clone();
+        let mut chunks = ChunkManager::new();
+        if self.initial_radius >= 0 {
+            generator.generate_chunks_around(IVec3::ZERO, self.initial_radius, &mut chunks);
+        }
+
+        app.insert_resource(WorldBackground(BackgroundGenerator::new(generator.clone())))
+            .insert_resource(WorldGen(generator))
+            .insert_resource(WorldChunks(chunks))
+            .insert_resource(WorldStreamer(ChunkStreamer::new(self.streaming)))
+            .init_resource::<ChunkEntities>()
+            .add_systems(Startup, setup_chunk_material)
+            .add_systems(Update, (stream_chunks, sync_chunk_entities).chain());
+    }
+}
+
+fn setup_chunk_material(mut commands: Commands, mut materials: ResMut<Assets<StandardMaterial>>) {
+    let material = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.45, 0.6, 0.35),
+        perceptual_roughness: 0.9,
+        ..default()
+    });
+    commands.insert_resource(ChunkMaterial(material));
+}
+
+/// Load and unload chunks around the `ChunkViewer`, if there is one.
+/// Generation runs in the background; finished chunks are picked up on
+/// later frames.
+pub fn stream_chunks(
+    mut streamer: ResMut<WorldStreamer>,
+    mut background: ResMut<WorldBackground>,
+    mut chunks: ResMut<WorldChunks>,
+    viewers: Query<&GlobalTransform, With<ChunkViewer>>,
+) {
+    let Ok(viewer) = viewers.get_single() else {
+        return;
+    };
+
+    let translation = viewer.translation().floor();
+    let viewer_pos = IVec3::new(translation.x as i32, translation.y as i32, translation.z as i32);
+    streamer.update_background(viewer_pos, &mut background, &mut chunks);
+}
+
+/// Despawn entities of unloaded chunks and (re)build meshes for chunks that
+/// were loaded or edited since the last frame.
+pub fn sync_chunk_entities(
+    mut commands: Commands,
+    mut chunks: ResMut<WorldChunks>,
+    generator: Res<WorldGen>,
+    material: Res<ChunkMaterial>,
+    mut entities: ResMut<ChunkEntities>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    handles: Query<&Handle<Mesh>, With<ChunkEntity>>,
+) {
+    entities.0.retain(|&chunk_pos, &mut entity| {
+        if chunks.get_chunk(chunk_pos).is_some() {
+            return true;
+        }
+        if let Ok(handle) = handles.get(entity) {
+            meshes.remove(handle);
+        }
+        commands.entity(entity).despawn_recursive();
+        false
+    });
+
+    for chunk_pos in chunks.drain_remesh() {
+        let Some(chunk_mesh) = mesh_chunk_in_manager(&chunks, chunk_pos, generator.registry()) else {
+            continue;
+        };
+        let mesh = to_bevy_mesh(&chunk_mesh);
+
+        if let Some(&entity) = entities.0.get(&chunk_pos) {
+            if let Some(existing) = handles.get(entity).ok().and_then(|h| meshes.get_mut(h)) {
+                *existing = mesh;
+                continue;
+            }
+        }
+
+        let entity = commands
+            .spawn((
+                PbrBundle {
+                    mesh: meshes.add(mesh),
+                    material: material.0.clone(),
+                    transform: Transform::from_xyz(
+                        (chunk_pos.x * CHUNK_SIZE) as f32,
+                        0.0,
+                        (chunk_pos.z * CHUNK_SIZE) as f32,
+                    ),
+                    ..default()
+                },
+                ChunkEntity { position: chunk_pos },
+            ))
+            .id();
+        entities.0.insert(chunk_pos, entity);
+    }
+}
+
+/// Convert CPU mesh data into a Bevy `Mesh` asset.
+pub fn to_bevy_mesh(chunk_mesh: &ChunkMesh) -> Mesh {
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, chunk_mesh.positions.clone())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, chunk_mesh.normals.clone())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, chunk_mesh.uvs.clone())
+        .with_inserted_indices(Indices::U32(chunk_mesh.indices.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::AssetPlugin;
+    use voxel_world::Chunk;
+
+    fn headless_app(initial_radius: i32) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_asset::<StandardMaterial>()
+            .add_plugins(VoxelWorldPlugin {
+                initial_radius,
+                streaming: StreamingConfig {
+                    load_radius: 1,
+                    unload_radius: 1,
+                    max_generated_per_tick: 2,
+                },
+                ..default()
+            });
+        app
+    }
+
+    fn chunk_entity_count(app: &mut App) -> usize {
+        app.world_mut()
+            .query::<&ChunkEntity>()
+            .iter(app.world())
+            .count()
+    }
+
+    #[test]
+    fn test_initial_chunks_spawned() {
+        let mut app = headless_app(0);
+        app.update();
+
+        assert_eq!(chunk_entity_count(&mut app), 1);
+        assert_eq!(app.world().resource::<Assets<Mesh>>().len(), 1);
+    }
+
+    #[test]
+    fn test_entities_follow_loaded_chunks() {
+        let mut app = headless_app(-1);
+        app.update();
+        assert_eq!(chunk_entity_count(&mut app), 0);
+
+        {
+            let mut chunks = app.world_mut().resource_mut::<WorldChunks>();
+            for x in 0..3 {
+                chunks.insert_chunk(Chunk::new(IVec3::new(x, 0, 0)));
+            }
+        }
+        app.update();
+        assert_eq!(chunk_entity_count(&mut app), 3);
+
+        app.world_mut()
+            .resource_mut::<WorldChunks>()
+            .remove_chunk(IVec3::new(2, 0, 0));
+        app.update();
+        assert_eq!(chunk_entity_count(&mut app), 2);
+        assert_eq!(app.world().resource::<Assets<Mesh>>().len(), 2);
+
+        let entities = app.world().resource::<ChunkEntities>();
+        assert!(!entities.0.contains_key(&IVec3::new(2, 0, 0)));
+    }
+
+    #[test]
+    fn test_edit_updates_mesh_in_place() {
+        let mut app = headless_app(-1);
+        app.world_mut()
+            .resource_mut::<WorldChunks>()
+            .insert_chunk(Chunk::new(IVec3::ZERO));
+        app.update();
+
+        app.world_mut()
+            .resource_mut::<WorldChunks>()
+            .set_block(IVec3::new(4, 4, 4), 1);
+        app.update();
+
+        assert_eq!(chunk_entity_count(&mut app), 1);
+        let meshes = app.world().resource::<Assets<Mesh>>();
+        assert_eq!(meshes.len(), 1);
+        let (_, mesh) = meshes.iter().next().unwrap();
+        assert_eq!(mesh.indices().unwrap().len(), 36);
+    }
+
+    #[test]
+    fn test_streaming_follows_viewer() {
+        let mut app = headless_app(-1);
+        let viewer = app
+            .world_mut()
+            .spawn((ChunkViewer, GlobalTransform::from_xyz(8.0, 80.0, 8.0)))
+            .id();
+
+        // The budget spreads the five chunks of the disc over several frames.
+        app.update();
+        assert_eq!(app.world().resource::<WorldStreamer>().stats().in_flight, 2);
+        update_until_loaded(&mut app, 5);
+        assert!(app.world().resource::<WorldStreamer>().is_idle());
+
+        // Moving far away unloads everything and starts over.
+        *app.world_mut().get_mut::<GlobalTransform>(viewer).unwrap() =
+            GlobalTransform::from_xyz(200.0, 80.0, 8.0);
+        app.update();
+        assert_eq!(chunk_entity_count(&mut app), 0);
+        let stats = app.world().resource::<WorldStreamer>().stats();
+        assert_eq!(stats.unloaded, 5);
+
+        update_until_loaded(&mut app, 5);
+        assert!(app.world().resource::<WorldChunks>().get_chunk(IVec3::new(12, 0, 0)).is_some());
+    }
+
+    /// Run frames until `count` chunk entities exist, finishing the
+    /// background jobs before each frame.
+    fn update_until_loaded(app: &mut App, count: usize) {
+        for _ in 0..10 {
+            app.world_mut().resource_scope(|world, mut background: Mut<WorldBackground>| {
+                background.finish(&mut world.resource_mut::<WorldChunks>().0);
+            });
+            app.update();
+            if chunk_entity_count(app) == count {
+                return;
+            }
+        }
+        panic!("chunks didn't finish loading");
+    }
+}
@@ -0,0 +1,41 @@
+//! Chunk generation throughput, comparing the column-based generator with
+//! the per-voxel sampling it replaced.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use voxel_core::{IVec3, CHUNK_HEIGHT, CHUNK_SIZE};
+use voxel_world::{Chunk, TerrainConfig, TerrainGenerator, WorldGenerator};
+
+/// The original algorithm: sample every voxel through `get_block_at`.
+fn generate_chunk_per_voxel(generator: &TerrainGenerator, chunk_pos: IVec3) -> Chunk {
+    let mut chunk = Chunk::new(chunk_pos);
+    for local_x in 0..CHUNK_SIZE {
+        for local_z in 0..CHUNK_SIZE {
+            for local_y in 0..CHUNK_HEIGHT {
+                let world_pos = IVec3::new(chunk_pos.x * CHUNK_SIZE + local_x, local_y, chunk_pos.z * CHUNK_SIZE + local_z);
+                chunk.set_block(IVec3::new(local_x, local_y, local_z), generator.get_block_at(world_pos));
+            }
+        }
+    }
+    chunk.mark_clean();
+    chunk
+}
+
+fn bench_generate_chunk(c: &mut Criterion) {
+    let generator = TerrainGenerator::new(TerrainConfig::default());
+    let chunk_pos = IVec3::new(3, 0, -7);
+
+    let mut group = c.benchmark_group("generate_chunk");
+    group.throughput(Throughput::Elements(1)); // Reported as chunks per second
+
+    group.bench_with_input(BenchmarkId::new("per_voxel", "default"), &chunk_pos, |b, &pos| {
+        b.iter(|| generate_chunk_per_voxel(&generator, black_box(pos)))
+    });
+    group.bench_with_input(BenchmarkId::new("column", "default"), &chunk_pos, |b, &pos| {
+        b.iter(|| generator.generate_chunk(black_box(pos)))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_generate_chunk);
+criterion_main!(benches);
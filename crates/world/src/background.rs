@@ -0,0 +1,229 @@
+//! Off-thread chunk generation with cancellation.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crossbeam_channel::{Receiver, Sender};
+
+use voxel_core::IVec3;
+use crate::chunk::{Chunk, ChunkManager};
+use crate::world_generator::WorldGenerator;
+
+/// Result of a background job; `None` if the job was cancelled before it ran.
+type JobResult = (IVec3, u64, Option<Chunk>);
+
+struct Job {
+    id: u64,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Generates chunks on the rayon thread pool and hands finished chunks back
+/// to a `ChunkManager` through a channel.
+pub struct BackgroundGenerator {
+    generator: Arc<dyn WorldGenerator>,
+    sender: Sender<JobResult>,
+    receiver: Receiver<JobResult>,
+    /// Requested chunks that haven't been delivered or cancelled yet
+    pending: HashMap<IVec3, Job>,
+    /// Jobs whose result hasn't been received, including cancelled ones
+    in_flight: usize,
+    next_job_id: u64,
+}
+
+impl BackgroundGenerator {
+    pub fn new(generator: Arc<dyn WorldGenerator>) -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        Self {
+            generator,
+            sender,
+            receiver,
+            pending: HashMap::new(),
+            in_flight: 0,
+            next_job_id: 0,
+        }
+    }
+
+    /// Queue a chunk for generation. Returns false if it's already pending.
+    pub fn request(&mut self, chunk_pos: IVec3) -> bool {
+        if self.pending.contains_key(&chunk_pos) {
+            return false;
+        }
+
+        let id = self.next_job_id;
+        self.next_job_id += 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.pending.insert(chunk_pos, Job { id, cancelled: cancelled.clone() });
+        self.in_flight += 1;
+
+        let generator = self.generator.clone();
+        let sender = self.sender.clone();
+        rayon::spawn(move || {
+            let chunk = if cancelled.load(Ordering::Relaxed) {
+                None
+            } else {
+                Some(generator.generate_chunk(chunk_pos))
+            };
+            // The receiver only goes away when the generator is dropped.
+            let _ = sender.send((chunk_pos, id, chunk));
+        });
+
+        true
+    }
+
+    /// Cancel a pending chunk. Jobs that already started still run, but
+    /// their result is discarded.
+    pub fn cancel(&mut self, chunk_pos: IVec3) -> bool {
+        match self.pending.remove(&chunk_pos) {
+            Some(job) => {
+                job.cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel all pending chunks farther than `max_distance` from `center`,
+    /// returning how many were cancelled.
+    pub fn cancel_outside(&mut self, center: IVec3, max_distance: i32) -> usize {
+        let max_distance_sq = max_distance * max_distance;
+        let distant: Vec<IVec3> = self.pending.keys()
+            .copied()
+            .filter(|&chunk_pos| {
+                let diff = chunk_pos - center;
+                diff.x * diff.x + diff.z * diff.z > max_distance_sq
+            })
+            .collect();
+
+        for chunk_pos in &distant {
+            self.cancel(*chunk_pos);
+        }
+        distant.len()
+    }
+
+    /// Check if a chunk has been requested but not delivered yet.
+    pub fn is_pending(&self, chunk_pos: IVec3) -> bool {
+        self.pending.contains_key(&chunk_pos)
+    }
+
+    /// Number of requested chunks not delivered yet.
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Move all finished chunks into `chunk_manager` without blocking,
+    /// returning how many were inserted.
+    pub fn poll(&mut self, chunk_manager: &mut ChunkManager) -> usize {
+        let mut inserted = 0;
+        while let Ok(result) = self.receiver.try_recv() {
+            inserted += usize::from(self.accept(result, chunk_manager));
+        }
+        inserted
+    }
+
+    /// Block until every outstanding job has finished, inserting the results
+    /// into `chunk_manager`. Returns how many chunks were inserted.
+    pub fn finish(&mut self, chunk_manager: &mut ChunkManager) -> usize {
+        let mut inserted = 0;
+        while self.in_flight > 0 {
+            match self.receiver.recv() {
+                Ok(result) => inserted += usize::from(self.accept(result, chunk_manager)),
+                Err(_) => break,
+            }
+        }
+        inserted
+    }
+
+    /// Get the generator used by background jobs.
+    pub fn generator(&self) -> &Arc<dyn WorldGenerator> {
+        &self.generator
+    }
+
+    fn accept(&mut self, (chunk_pos, id, chunk): JobResult, chunk_manager: &mut ChunkManager) -> bool {
+        self.in_flight -= 1;
+
+        // Ignore results of cancelled jobs, including ones superseded by a
+        // newer request for the same chunk.
+        if self.pending.get(&chunk_pos).map(|job| job.id) != Some(id) {
+            return false;
+        }
+        self.pending.remove(&chunk_pos);
+
+        match chunk {
+            // Don't clobber a chunk that was created (e.g. edited) meanwhile
+            Some(chunk) if chunk_manager.get_chunk(chunk_pos).is_none() => {
+                chunk_manager.insert_chunk(chunk);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::{TerrainConfig, TerrainGenerator};
+
+    fn background() -> BackgroundGenerator {
+        BackgroundGenerator::new(Arc::new(TerrainGenerator::new(TerrainConfig::default())))
+    }
+
+    #[test]
+    fn test_background_generation() {
+        let mut background = background();
+        let mut manager = ChunkManager::new();
+
+        assert!(background.request(IVec3::ZERO));
+        assert!(background.request(IVec3::new(1, 0, 0)));
+        assert!(!background.request(IVec3::ZERO)); // Already pending
+        assert_eq!(background.pending_count(), 2);
+
+        assert_eq!(background.finish(&mut manager), 2);
+        assert_eq!(background.pending_count(), 0);
+        assert_eq!(manager.chunk_count(), 2);
+        assert!(!manager.get_chunk(IVec3::ZERO).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let mut background = background();
+        let mut manager = ChunkManager::new();
+
+        background.request(IVec3::ZERO);
+        background.request(IVec3::new(5, 0, 0));
+        background.request(IVec3::new(-6, 0, 2));
+        assert_eq!(background.cancel_outside(IVec3::ZERO, 2), 2);
+        assert!(background.is_pending(IVec3::ZERO));
+        assert!(!background.is_pending(IVec3::new(5, 0, 0)));
+
+        background.finish(&mut manager);
+        assert_eq!(manager.chunk_count(), 1);
+        assert!(manager.get_chunk(IVec3::ZERO).is_some());
+    }
+
+    #[test]
+    fn test_rerequest_after_cancel() {
+        let mut background = background();
+        let mut manager = ChunkManager::new();
+
+        background.request(IVec3::ZERO);
+        background.cancel(IVec3::ZERO);
+        assert!(background.request(IVec3::ZERO));
+
+        assert_eq!(background.finish(&mut manager), 1);
+        assert_eq!(manager.chunk_count(), 1);
+    }
+
+    #[test]
+    fn test_existing_chunk_not_overwritten() {
+        let mut background = background();
+        let mut manager = ChunkManager::new();
+
+        background.request(IVec3::ZERO);
+        manager.set_block(IVec3::new(1, 200, 1), 4);
+
+        assert_eq!(background.finish(&mut manager), 0);
+        assert_eq!(manager.get_block(IVec3::new(1, 200, 1)), 4);
+    }
+}
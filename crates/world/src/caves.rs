@@ -0,0 +1,424 @@
+//! Cave carving.
+//!
+//! Spaghetti tunnels and caverns come straight from 3D noise and can be
+//! tested one position at a time. Worm tunnels and ravines are planned per
+//! region from the seed, like features, so a chunk gathers the ones that
+//! reach it once and then tests its positions against that short list.
+
+use std::f64::consts::{PI, TAU};
+use std::ops::RangeInclusive;
+
+use voxel_core::IVec3;
+use crate::features::FeatureRng;
+use noise::{NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
+
+/// Width of the regions worm tunnels (per cube) and ravines (per column)
+/// are planned in.
+pub const CAVE_REGION_SIZE: i32 = 64;
+
+/// Distance between the points of a worm tunnel.
+const WORM_STEP: f64 = 6.0;
+/// Blocks over which a ravine's floor rises towards its ends.
+const RAVINE_TAPER: f64 = 16.0;
+
+const RAVINE_SALT: u64 = 16;
+/// Worm `i` of a region uses salt `WORM_SALT + i`
+const WORM_SALT: u64 = 1 << 16;
+
+/// Cave carving settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaveConfig {
+    /// Solid blocks kept between caves and the surface. Ravines ignore it.
+    pub surface_margin: i32,
+    /// Solid blocks kept above `TerrainConfig::min_height`; nothing is
+    /// carved into them.
+    pub floor_margin: i32,
+    pub spaghetti_frequency: f64,
+    /// Larger values give wider spaghetti tunnels
+    pub spaghetti_thickness: f64,
+    pub cavern_frequency: f64,
+    /// Noise level above which caverns open; higher values give fewer
+    pub cavern_threshold: f64,
+    /// Worm tunnels started in each cube of regions
+    pub worms_per_region: u32,
+    /// Points along a worm tunnel, `WORM_STEP` blocks apart
+    pub worm_length: u32,
+    /// Average worm tunnel radius
+    pub worm_radius: f64,
+    /// Chance of a ravine in each column of regions
+    pub ravine_chance: f64,
+}
+
+impl Default for CaveConfig {
+    fn default() -> Self {
+        Self {
+            surface_margin: 4,
+            floor_margin: 5,
+            spaghetti_frequency: 0.025,
+            spaghetti_thickness: 0.09,
+            cavern_frequency: 0.012,
+            cavern_threshold: 0.6,
+            worms_per_region: 3,
+            worm_length: 8,
+            worm_radius: 2.0,
+            ravine_chance: 0.15,
+        }
+    }
+}
+
+/// A winding tunnel through points of varying radius.
+#[derive(Debug, Clone, PartialEq)]
+struct Worm {
+    points: Vec<([f64; 3], f64)>,
+    min: IVec3,
+    max: IVec3,
+}
+
+impl Worm {
+    fn carves(&self, pos: [f64; 3]) -> bool {
+        self.points.windows(2).any(|pair| {
+            let ((a, radius_a), (b, radius_b)) = (pair[0], pair[1]);
+            let t = project(pos, a, b);
+            let radius = radius_a + (radius_b - radius_a) * t;
+            let closest = [0, 1, 2].map(|axis| a[axis] + (b[axis] - a[axis]) * t);
+            distance_squared(pos, closest) <= radius * radius
+        })
+    }
+}
+
+/// A straight, steep-walled cut that is widest and deepest in the middle
+/// and open to the sky.
+#[derive(Debug, Clone, PartialEq)]
+struct Ravine {
+    start: [f64; 3],
+    end: [f64; 3],
+    width: f64,
+    bottom: f64,
+    min: IVec3,
+    max: IVec3,
+}
+
+impl Ravine {
+    fn carves(&self, pos: [f64; 3]) -> bool {
+        let flat = [pos[0], 0.0, pos[2]];
+        let t = project(flat, self.start, self.end);
+        let profile = (PI * t).sin();
+        let floor = self.bottom + (1.0 - profile) * RAVINE_TAPER;
+        if pos[1] < floor {
+            return false;
+        }
+
+        // Narrower towards the floor
+        let closest = [0, 1, 2].map(|axis| self.start[axis] + (self.end[axis] - self.start[axis]) * t);
+        let width = self.width * profile * ((pos[1] - floor) / 8.0 + 0.4).min(1.0);
+        distance_squared(flat, closest) <= width * width
+    }
+}
+
+/// Worm tunnels and ravines that can reach some bounds.
+#[derive(Debug, Clone, Default)]
+pub struct Tunnels {
+    worms: Vec<Worm>,
+    ravines: Vec<Ravine>,
+}
+
+impl Tunnels {
+    /// Whether a worm tunnel or ravine passes through the block at `pos`.
+    pub fn carves(&self, pos: IVec3) -> bool {
+        self.worm_carves(pos) || self.ravine_carves(pos)
+    }
+
+    fn worm_carves(&self, pos: IVec3) -> bool {
+        let center = block_center(pos);
+        self.worms.iter().any(|worm| pos.clamp(worm.min, worm.max) == pos && worm.carves(center))
+    }
+
+    fn ravine_carves(&self, pos: IVec3) -> bool {
+        let center = block_center(pos);
+        self.ravines.iter().any(|ravine| pos.clamp(ravine.min, ravine.max) == pos && ravine.carves(center))
+    }
+}
+
+/// Carves caves for one seed.
+#[derive(Debug, Clone)]
+pub struct CaveCarver {
+    config: CaveConfig,
+    seed: u32,
+    /// Lowest position that may be carved
+    floor: i32,
+    sea_level: i32,
+    spaghetti: [Perlin; 2],
+    cavern: Perlin,
+}
+
+impl CaveCarver {
+    pub fn new(config: &CaveConfig, seed: u32, min_height: i32, sea_level: i32) -> Self {
+        Self {
+            config: config.clone(),
+            seed,
+            floor: min_height + config.floor_margin + 1,
+            sea_level,
+            spaghetti: [Perlin::new(seed.wrapping_add(1)), Perlin::new(seed.wrapping_add(4))],
+            cavern: Perlin::new(seed.wrapping_add(5)),
+        }
+    }
+
+    /// Whether the block at `pos`, in a column whose surface is at
+    /// `surface_y`, is carved out. `tunnels` must come from `tunnels_near`
+    /// with bounds containing `pos`.
+    pub fn carves(&self, pos: IVec3, surface_y: i32, tunnels: &Tunnels) -> bool {
+        if pos.y < self.floor || pos.y > surface_y {
+            return false;
+        }
+        let below_margin = pos.y < surface_y - self.config.surface_margin;
+        (below_margin && (self.noise_cave(pos) || tunnels.worm_carves(pos))) || tunnels.ravine_carves(pos)
+    }
+
+    /// Spaghetti tunnels and caverns, ignoring the surface and floor.
+    fn noise_cave(&self, pos: IVec3) -> bool {
+        let frequency = self.config.spaghetti_frequency;
+        let point = [pos.x as f64 * frequency, pos.y as f64 * frequency * 1.5, pos.z as f64 * frequency];
+
+        // Tunnels run where both noise fields are near zero, along the
+        // intersection of their zero surfaces
+        let first = self.spaghetti[0].get(point);
+        let thickness = self.config.spaghetti_thickness;
+        if first.abs() < thickness {
+            let second = self.spaghetti[1].get(point);
+            if first * first + second * second < thickness * thickness {
+                return true;
+            }
+        }
+
+        // Caverns are squashed vertically into wide chambers
+        let frequency = self.config.cavern_frequency;
+        let point = [pos.x as f64 * frequency, pos.y as f64 * frequency * 2.0, pos.z as f64 * frequency];
+        self.cavern.get(point) > self.config.cavern_threshold
+    }
+
+    /// Worm tunnels and ravines that can reach the inclusive bounds.
+    pub fn tunnels_near(&self, min: IVec3, max: IVec3) -> Tunnels {
+        let mut tunnels = Tunnels::default();
+        let overlaps = |low: IVec3, high: IVec3| {
+            low.x <= max.x && high.x >= min.x && low.y <= max.y && high.y >= min.y && low.z <= max.z && high.z >= min.z
+        };
+
+        let worm_reach = (self.config.worm_length as f64 * WORM_STEP + self.config.worm_radius * 2.0).ceil() as i32;
+        let reach = IVec3::new(worm_reach, worm_reach, worm_reach);
+        for region_x in regions(min.x, max.x, worm_reach) {
+            for region_z in regions(min.z, max.z, worm_reach) {
+                for region_y in regions(min.y, max.y, worm_reach) {
+                    let region = IVec3::new(region_x, region_y, region_z);
+                    for index in 0..self.config.worms_per_region {
+                        let mut rng = FeatureRng::new(self.seed, region, WORM_SALT + index as u64);
+                        let start = [region.x, region.y, region.z]
+                            .map(|cell| (cell * CAVE_REGION_SIZE) as f64 + rng.next_f64() * CAVE_REGION_SIZE as f64);
+
+                        // Only walk worms starting close enough to reach the bounds
+                        let start_block = IVec3::new(start[0] as i32, start[1] as i32, start[2] as i32);
+                        if !overlaps(start_block - reach, start_block + reach) {
+                            continue;
+                        }
+                        let worm = self.walk_worm(start, &mut rng);
+                        if overlaps(worm.min, worm.max) {
+                            tunnels.worms.push(worm);
+                        }
+                    }
+                }
+            }
+        }
+
+        for region_x in regions(min.x, max.x, CAVE_REGION_SIZE) {
+            for region_z in regions(min.z, max.z, CAVE_REGION_SIZE) {
+                if let Some(ravine) = self.plan_ravine(region_x, region_z) {
+                    if overlaps(ravine.min, ravine.max) {
+                        tunnels.ravines.push(ravine);
+                    }
+                }
+            }
+        }
+        tunnels
+    }
+
+    fn walk_worm(&self, start: [f64; 3], rng: &mut FeatureRng) -> Worm {
+        let mut point = start;
+        let mut yaw = rng.next_f64() * TAU;
+        let mut pitch = (rng.next_f64() - 0.5) * 0.6;
+        let mut turn = 0.0;
+
+        // The heading changes gradually, so the tunnel winds instead of
+        // zigzagging
+        let mut points = Vec::with_capacity(self.config.worm_length as usize);
+        for _ in 0..self.config.worm_length {
+            let radius = self.config.worm_radius * (0.75 + rng.next_f64() * 0.5);
+            points.push((point, radius));
+            turn = turn * 0.7 + (rng.next_f64() - 0.5) * 0.8;
+            yaw += turn;
+            pitch = pitch * 0.7 + (rng.next_f64() - 0.5) * 0.4;
+            point = [
+                point[0] + yaw.cos() * pitch.cos() * WORM_STEP,
+                point[1] + pitch.sin() * WORM_STEP,
+                point[2] + yaw.sin() * pitch.cos() * WORM_STEP,
+            ];
+        }
+
+        let (min, max) = bounds(points.iter().copied());
+        Worm { points, min, max }
+    }
+
+    fn plan_ravine(&self, region_x: i32, region_z: i32) -> Option<Ravine> {
+        let mut rng = FeatureRng::new(self.seed, IVec3::new(region_x, 0, region_z), RAVINE_SALT);
+        if rng.next_f64() >= self.config.ravine_chance {
+            return None;
+        }
+
+        let center = [
+            (region_x * CAVE_REGION_SIZE) as f64 + rng.next_f64() * CAVE_REGION_SIZE as f64,
+            0.0,
+            (region_z * CAVE_REGION_SIZE) as f64 + rng.next_f64() * CAVE_REGION_SIZE as f64,
+        ];
+        let yaw = rng.next_f64() * TAU;
+        let half_length = 20.0 + rng.next_f64() * 12.0;
+        let offset = [yaw.cos() * half_length, 0.0, yaw.sin() * half_length];
+        let start = [center[0] - offset[0], 0.0, center[2] - offset[2]];
+        let end = [center[0] + offset[0], 0.0, center[2] + offset[2]];
+        let width = 2.0 + rng.next_f64() * 2.0;
+        let bottom = (self.sea_level - rng.range(20, 40)).max(self.floor) as f64;
+
+        let (mut min, mut max) = bounds([(start, width), (end, width)].into_iter());
+        min.y = bottom as i32;
+        max.y = i32::MAX;
+        Some(Ravine { start, end, width, bottom, min, max })
+    }
+}
+
+/// Regions whose contents, reaching at most `reach` blocks outside the
+/// region, can touch `min..=max` along one axis.
+fn regions(min: i32, max: i32, reach: i32) -> RangeInclusive<i32> {
+    (min - reach).div_euclid(CAVE_REGION_SIZE)..=(max + reach).div_euclid(CAVE_REGION_SIZE)
+}
+
+/// Block bounds of spheres.
+fn bounds(spheres: impl Iterator<Item = ([f64; 3], f64)>) -> (IVec3, IVec3) {
+    let mut min = IVec3::new(i32::MAX, i32::MAX, i32::MAX);
+    let mut max = IVec3::new(i32::MIN, i32::MIN, i32::MIN);
+    for (point, radius) in spheres {
+        let low = point.map(|value| (value - radius).floor() as i32);
+        let high = point.map(|value| (value + radius).ceil() as i32);
+        min = min.min(IVec3::new(low[0], low[1], low[2]));
+        max = max.max(IVec3::new(high[0], high[1], high[2]));
+    }
+    (min, max)
+}
+
+/// Position along the segment `a..b` closest to `pos`, from 0 to 1.
+fn project(pos: [f64; 3], a: [f64; 3], b: [f64; 3]) -> f64 {
+    let direction = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let length_squared = distance_squared(a, b);
+    if length_squared == 0.0 {
+        return 0.0;
+    }
+    let along = (0..3).map(|axis| (pos[axis] - a[axis]) * direction[axis]).sum::<f64>();
+    (along / length_squared).clamp(0.0, 1.0)
+}
+
+fn block_center(pos: IVec3) -> [f64; 3] {
+    [pos.x as f64 + 0.5, pos.y as f64 + 0.5, pos.z as f64 + 0.5]
+}
+
+fn distance_squared(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (0..3).map(|axis| (a[axis] - b[axis]).powi(2)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn carver(config: CaveConfig) -> CaveCarver {
+        CaveCarver::new(&config, 12345, 0, 64)
+    }
+
+    #[test]
+    fn test_worm_tunnels_connected() {
+        let carver = carver(CaveConfig::default());
+        let tunnels = carver.tunnels_near(IVec3::new(0, 0, 0), IVec3::new(127, 127, 127));
+        assert!(tunnels.worms.len() > 10);
+
+        for worm in &tunnels.worms {
+            let mut blocks = HashSet::new();
+            for x in worm.min.x..=worm.max.x {
+                for y in worm.min.y..=worm.max.y {
+                    for z in worm.min.z..=worm.max.z {
+                        if worm.carves(block_center(IVec3::new(x, y, z))) {
+                            blocks.insert(IVec3::new(x, y, z));
+                        }
+                    }
+                }
+            }
+
+            // Every carved block is reachable from every other
+            let start = *blocks.iter().next().unwrap();
+            let mut seen = HashSet::from([start]);
+            let mut queue = vec![start];
+            while let Some(pos) = queue.pop() {
+                for step in [IVec3::X, IVec3::Y, IVec3::Z] {
+                    for next in [pos + step, pos - step] {
+                        if blocks.contains(&next) && seen.insert(next) {
+                            queue.push(next);
+                        }
+                    }
+                }
+            }
+            assert_eq!(seen.len(), blocks.len());
+        }
+    }
+
+    #[test]
+    fn test_floor_and_surface_kept() {
+        // Caverns everywhere
+        let carver = carver(CaveConfig { cavern_threshold: -2.0, ..CaveConfig::default() });
+        let (min, max) = (IVec3::new(-40, -10, -40), IVec3::new(40, 100, 40));
+        let tunnels = carver.tunnels_near(min, max);
+        let surface_y = 80;
+        for x in min.x..=max.x {
+            for z in min.z..=max.z {
+                for y in min.y..=max.y {
+                    let pos = IVec3::new(x, y, z);
+                    let carved = carver.carves(pos, surface_y, &tunnels);
+                    if y <= 5 || y > surface_y {
+                        assert!(!carved, "{:?}", pos);
+                    } else if y < surface_y - 4 {
+                        assert!(carved, "{:?}", pos);
+                    } else if carved {
+                        // Only ravines break through the surface margin
+                        assert!(tunnels.ravine_carves(pos), "{:?}", pos);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ravines_breach_surface() {
+        let carver = carver(CaveConfig { ravine_chance: 1.0, ..CaveConfig::default() });
+        let (min, max) = (IVec3::new(0, 0, 0), IVec3::new(63, 127, 63));
+        let tunnels = carver.tunnels_near(min, max);
+        assert!(!tunnels.ravines.is_empty());
+
+        let surface_y = 70;
+        let breaches = (min.x..=max.x)
+            .flat_map(|x| (min.z..=max.z).map(move |z| IVec3::new(x, surface_y, z)))
+            .filter(|&pos| carver.carves(pos, surface_y, &tunnels))
+            .count();
+        assert!(breaches > 20, "{} surface blocks carved", breaches);
+
+        // Deep in the middle, shallow at the ends
+        let ravine = &tunnels.ravines[0];
+        let middle = [0, 1, 2].map(|axis| (ravine.start[axis] + ravine.end[axis]) / 2.0);
+        assert!(ravine.carves([middle[0], ravine.bottom + 1.0, middle[2]]));
+        assert!(!ravine.carves([ravine.start[0], ravine.bottom + 1.0, ravine.start[2]]));
+    }
+}
@@ -1,104 +1,273 @@
 //! Chunk data structures and management.
 
 use voxel_core::{
-    BlockId, Palette, IVec3, AIR_BLOCK,
-    CHUNK_SIZE, CHUNK_HEIGHT, CHUNK_VOLUME,
-    local_to_index, index_to_local, world_to_chunk, world_to_local,
+    BlockId, BlockRemap, ChunkLayout, Palette, IVec3, AIR_BLOCK,
+    CHUNK_SIZE, CHUNK_VOLUME,
+    local_to_index, index_to_local,
 };
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{HashMap, HashSet};
+use crate::section::{Section, SECTIONS_PER_CHUNK, SECTION_SIZE, SECTION_VOLUME};
 
-/// A chunk of voxel data with palette compression.
+/// Offsets to the four horizontally adjacent chunks (-x, +x, -z, +z).
+pub const HORIZONTAL_NEIGHBORS: [IVec3; 4] = [
+    IVec3::new(-1, 0, 0),
+    IVec3::new(1, 0, 0),
+    IVec3::new(0, 0, -1),
+    IVec3::new(0, 0, 1),
+];
+
+/// Offsets to the six face-adjacent chunks in the cubic layout.
+pub const FACE_NEIGHBORS: [IVec3; 6] = [
+    IVec3::new(-1, 0, 0),
+    IVec3::new(1, 0, 0),
+    IVec3::new(0, -1, 0),
+    IVec3::new(0, 1, 0),
+    IVec3::new(0, 0, -1),
+    IVec3::new(0, 0, 1),
+];
+
+/// A chunk of voxel data, stored as a stack of sections.
+///
+/// Column chunks hold `SECTIONS_PER_CHUNK` sections; cubic chunks hold a
+/// single one.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chunk {
     /// Chunk position in chunk coordinates
     pub position: IVec3,
-    /// Palette for block ID compression
-    pub palette: Palette,
-    /// Voxel data as palette indices
-    pub voxels: Vec<u8>,
-    /// Whether this chunk has been modified since last save
-    pub dirty: bool,
+    /// Sections from bottom to top; `None` for sections that are all air
+    #[serde(deserialize_with = "deserialize_sections")]
+    sections: Vec<Option<Section>>,
+    /// Bit `i` is set if section `i` has been modified since last save
+    #[serde(skip)]
+    dirty_sections: u32,
+}
+
+const _: () = assert!(SECTIONS_PER_CHUNK <= u32::BITS as usize);
+
+fn deserialize_sections<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Option<Section>>, D::Error> {
+    let sections = Vec::<Option<Section>>::deserialize(deserializer)?;
+    if layout_for_sections(sections.len()).is_none() {
+        return Err(D::Error::custom(format!(
+            "chunk has {} sections, expected 1 or {}",
+            sections.len(),
+            SECTIONS_PER_CHUNK
+        )));
+    }
+    Ok(sections)
+}
+
+/// The chunk layout with `count` sections per chunk.
+fn layout_for_sections(count: usize) -> Option<ChunkLayout> {
+    match count {
+        SECTIONS_PER_CHUNK => Some(ChunkLayout::Column),
+        1 => Some(ChunkLayout::Cubic),
+        _ => None,
+    }
+}
+
+/// Number of sections in a chunk of the given layout.
+fn section_count(layout: ChunkLayout) -> usize {
+    (layout.chunk_height() / SECTION_SIZE) as usize
+}
+
+/// Split a chunk voxel index into a section index and an index within it.
+fn section_index(index: usize) -> (usize, usize) {
+    (index / SECTION_VOLUME, index % SECTION_VOLUME)
 }
 
 impl Chunk {
-    /// Create a new empty chunk filled with air.
+    /// Create a new empty column chunk filled with air.
     pub fn new(position: IVec3) -> Self {
+        Self::with_layout(position, ChunkLayout::Column)
+    }
+    
+    /// Create a new empty chunk of the given layout filled with air.
+    pub fn with_layout(position: IVec3, layout: ChunkLayout) -> Self {
+        Self {
+            position,
+            sections: vec![None; section_count(layout)], // All air
+            dirty_sections: 0,
+        }
+    }
+    
+    /// Create a clean chunk from explicit sections, bottom to top. Panics
+    /// unless there is one section per chunk layer of a layout.
+    pub fn from_sections(position: IVec3, sections: Vec<Option<Section>>) -> Self {
+        assert!(
+            layout_for_sections(sections.len()).is_some(),
+            "chunk needs 1 or {} sections, got {}",
+            SECTIONS_PER_CHUNK,
+            sections.len()
+        );
         Self {
             position,
-            palette: Palette::new(),
-            voxels: vec![0; CHUNK_VOLUME], // All air (palette index 0)
-            dirty: false,
+            sections,
+            dirty_sections: 0,
         }
     }
     
+    /// Create a clean chunk from one index into `palette` per voxel
+    /// (`CHUNK_VOLUME` for a column, `SECTION_VOLUME` for a cube).
+    ///
+    /// Each section keeps only the palette entries it uses, and all-air
+    /// sections are not stored.
+    pub fn from_palette_indices(position: IVec3, palette: &Palette, indices: &[u16]) -> Self {
+        assert!(
+            indices.len() == CHUNK_VOLUME || indices.len() == SECTION_VOLUME,
+            "chunk needs {} or {} voxels, got {}",
+            CHUNK_VOLUME,
+            SECTION_VOLUME,
+            indices.len()
+        );
+        let sections = indices.chunks_exact(SECTION_VOLUME)
+            .map(|indices| Section::from_indices(palette, indices))
+            .collect();
+        Self::from_sections(position, sections)
+    }
+    
     /// Get block ID at local coordinates.
     pub fn get_block(&self, local_pos: IVec3) -> BlockId {
-        if let Some(index) = local_to_index(local_pos) {
-            let palette_id = self.voxels[index];
-            self.palette.get_block(palette_id)
-        } else {
-            AIR_BLOCK
+        let Some(index) = local_to_index(local_pos) else {
+            return AIR_BLOCK;
+        };
+        
+        let (section, index) = section_index(index);
+        match self.sections.get(section) {
+            Some(Some(section)) => section.get_block(index),
+            _ => AIR_BLOCK,
         }
     }
     
     /// Set block ID at local coordinates.
     pub fn set_block(&mut self, local_pos: IVec3, block_id: BlockId) {
-        if let Some(index) = local_to_index(local_pos) {
-            let palette_id = self.palette.add_block(block_id);
-            self.voxels[index] = palette_id;
-            self.dirty = true;
+        let Some(index) = local_to_index(local_pos) else {
+            return;
+        };
+        
+        let (section_y, index) = section_index(index);
+        let Some(slot) = self.sections.get_mut(section_y) else {
+            return; // Above the top of a cubic chunk
+        };
+        match slot {
+            Some(section) => section.set_block(index, block_id),
+            None if block_id == AIR_BLOCK => {} // Already air
+            None => slot.insert(Section::new()).set_block(index, block_id),
         }
+        self.dirty_sections |= 1 << section_y;
     }
     
     /// Fill the entire chunk with a single block type.
     pub fn fill(&mut self, block_id: BlockId) {
-        self.palette = Palette::new();
-        let palette_id = self.palette.add_block(block_id);
-        self.voxels.fill(palette_id);
-        self.dirty = true;
+        let section = (block_id != AIR_BLOCK).then(|| Section::filled(block_id));
+        self.sections = vec![section; self.sections.len()];
+        self.mark_all_dirty();
     }
     
     /// Check if chunk is entirely air.
+    ///
+    /// Looks at the voxels rather than the palettes, which may still hold
+    /// entries for blocks that have since been removed.
     pub fn is_empty(&self) -> bool {
-        self.palette.len() == 1 && self.palette.get_block(0) == AIR_BLOCK
+        self.sections.iter().flatten().all(Section::is_empty)
     }
     
     /// Get the number of non-air blocks in this chunk.
     pub fn count_solid_blocks(&self) -> usize {
-        if self.is_empty() {
-            return 0;
+        self.sections.iter().flatten()
+            .map(Section::count_solid_blocks)
+            .sum()
+    }
+    
+    /// Drop palette entries no longer referenced by any voxel and release
+    /// sections that are all air, returning how many palette entries were
+    /// removed.
+    ///
+    /// Palettes only grow as blocks are placed, so chunks that were heavily
+    /// edited should be compacted before saving.
+    pub fn compact(&mut self) -> usize {
+        let mut removed = 0;
+        for slot in &mut self.sections {
+            if let Some(section) = slot {
+                removed += section.compact();
+                if section.is_empty() {
+                    *slot = None;
+                }
+            }
         }
-        
-        self.voxels.iter()
-            .filter(|&&palette_id| {
-                let block_id = self.palette.get_block(palette_id);
-                block_id != AIR_BLOCK
-            })
-            .count()
+        removed
+    }
+    
+    /// The layout this chunk belongs to.
+    pub fn layout(&self) -> ChunkLayout {
+        layout_for_sections(self.sections.len()).unwrap()
+    }
+    
+    /// Sections from bottom to top; `None` for sections that are all air.
+    pub fn sections(&self) -> &[Option<Section>] {
+        &self.sections
+    }
+    
+    /// Get the section at the given index, or `None` if it is all air.
+    pub fn section(&self, section_y: usize) -> Option<&Section> {
+        self.sections.get(section_y)?.as_ref()
+    }
+    
+    /// Replace block IDs saved with another registry by this one's IDs.
+    pub fn remap_blocks(&mut self, remap: &BlockRemap) {
+        for section in self.sections.iter_mut().flatten() {
+            section.remap_blocks(remap);
+        }
+    }
+    
+    /// Heap memory used by the packed voxels of all sections, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.sections.iter().flatten()
+            .map(Section::memory_usage)
+            .sum()
     }
     
     /// Iterate over all non-air blocks with their positions and block IDs.
     pub fn iter_blocks(&self) -> impl Iterator<Item = (IVec3, BlockId)> + '_ {
-        self.voxels.iter().enumerate()
-            .filter_map(|(index, &palette_id)| {
-                let block_id = self.palette.get_block(palette_id);
-                if block_id != AIR_BLOCK {
-                    index_to_local(index).map(|pos| (pos, block_id))
-                } else {
-                    None
-                }
+        self.sections.iter().enumerate()
+            .filter_map(|(section_y, section)| section.as_ref().map(|section| (section_y, section)))
+            .flat_map(|(section_y, section)| {
+                section.voxels.iter().enumerate()
+                    .filter_map(move |(index, palette_id)| {
+                        let block_id = section.palette.get_block(palette_id);
+                        if block_id != AIR_BLOCK {
+                            index_to_local(section_y * SECTION_VOLUME + index).map(|pos| (pos, block_id))
+                        } else {
+                            None
+                        }
+                    })
             })
     }
     
     /// Mark chunk as clean (saved).
     pub fn mark_clean(&mut self) {
-        self.dirty = false;
+        self.dirty_sections = 0;
+    }
+    
+    /// Mark every section as modified.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty_sections = u32::MAX >> (u32::BITS as usize - self.sections.len());
     }
     
     /// Check if chunk needs saving.
     pub fn is_dirty(&self) -> bool {
-        self.dirty
+        self.dirty_sections != 0
+    }
+    
+    /// Check if a section has been modified since last save.
+    pub fn is_section_dirty(&self, section_y: usize) -> bool {
+        self.dirty_sections & (1 << section_y) != 0
+    }
+    
+    /// Indices of the sections modified since last save, bottom to top.
+    pub fn dirty_sections(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.sections.len()).filter(|&section_y| self.is_section_dirty(section_y))
     }
 }
 
@@ -106,19 +275,35 @@ impl Chunk {
 #[derive(Debug, Default)]
 pub struct ChunkManager {
     chunks: HashMap<IVec3, Chunk>,
+    /// Loaded chunks whose mesh no longer matches their contents
+    remesh: HashSet<IVec3>,
+    layout: ChunkLayout,
 }
 
 impl ChunkManager {
     pub fn new() -> Self {
+        Self::with_layout(ChunkLayout::Column)
+    }
+    
+    /// Create a manager for chunks of the given layout.
+    pub fn with_layout(layout: ChunkLayout) -> Self {
         Self {
             chunks: HashMap::new(),
+            remesh: HashSet::new(),
+            layout,
         }
     }
     
+    /// The layout of the chunks in this manager.
+    pub fn layout(&self) -> ChunkLayout {
+        self.layout
+    }
+    
     /// Get a chunk at the given chunk coordinates, creating if necessary.
     pub fn get_or_create_chunk(&mut self, chunk_pos: IVec3) -> &mut Chunk {
+        let layout = self.layout;
         self.chunks.entry(chunk_pos)
-            .or_insert_with(|| Chunk::new(chunk_pos))
+            .or_insert_with(|| Chunk::with_layout(chunk_pos, layout))
     }
     
     /// Get a chunk at the given chunk coordinates.
@@ -132,19 +317,29 @@ impl ChunkManager {
     }
     
     /// Insert a chunk.
+    ///
+    /// The chunk and its loaded neighbours are queued for re-meshing, since
+    /// the faces along their shared borders change.
     pub fn insert_chunk(&mut self, chunk: Chunk) {
-        self.chunks.insert(chunk.position, chunk);
+        debug_assert_eq!(chunk.layout(), self.layout, "chunk layout doesn't match manager");
+        let chunk_pos = chunk.position;
+        self.chunks.insert(chunk_pos, chunk);
+        self.remesh.insert(chunk_pos);
+        self.queue_neighbor_remesh(chunk_pos);
     }
     
     /// Remove a chunk.
     pub fn remove_chunk(&mut self, chunk_pos: IVec3) -> Option<Chunk> {
-        self.chunks.remove(&chunk_pos)
+        let chunk = self.chunks.remove(&chunk_pos)?;
+        self.remesh.remove(&chunk_pos);
+        self.queue_neighbor_remesh(chunk_pos);
+        Some(chunk)
     }
     
     /// Get block at world coordinates.
     pub fn get_block(&self, world_pos: IVec3) -> BlockId {
-        let chunk_pos = world_to_chunk(world_pos);
-        let local_pos = world_to_local(world_pos);
+        let chunk_pos = self.layout.world_to_chunk(world_pos);
+        let local_pos = self.layout.world_to_local(world_pos);
         
         self.get_chunk(chunk_pos)
             .map(|chunk| chunk.get_block(local_pos))
@@ -152,12 +347,59 @@ impl ChunkManager {
     }
     
     /// Set block at world coordinates.
+    ///
+    /// Edits on a chunk border also queue the adjacent chunk for re-meshing.
     pub fn set_block(&mut self, world_pos: IVec3, block_id: BlockId) {
-        let chunk_pos = world_to_chunk(world_pos);
-        let local_pos = world_to_local(world_pos);
+        let chunk_pos = self.layout.world_to_chunk(world_pos);
+        let local_pos = self.layout.world_to_local(world_pos);
         
         let chunk = self.get_or_create_chunk(chunk_pos);
         chunk.set_block(local_pos, block_id);
+        
+        // Cubes also share their top and bottom faces with loaded chunks
+        let vertical = match (self.layout, local_pos.y) {
+            (ChunkLayout::Cubic, 0) => Some(IVec3::new(0, -1, 0)),
+            (ChunkLayout::Cubic, y) if y == CHUNK_SIZE - 1 => Some(IVec3::new(0, 1, 0)),
+            _ => None,
+        };
+        
+        self.remesh.insert(chunk_pos);
+        for neighbor_pos in border_neighbors(local_pos).chain(vertical) {
+            let neighbor_pos = chunk_pos + neighbor_pos;
+            if self.chunks.contains_key(&neighbor_pos) {
+                self.remesh.insert(neighbor_pos);
+            }
+        }
+    }
+    
+    /// Queue a loaded chunk for re-meshing.
+    pub fn mark_for_remesh(&mut self, chunk_pos: IVec3) {
+        if self.chunks.contains_key(&chunk_pos) {
+            self.remesh.insert(chunk_pos);
+        }
+    }
+    
+    /// Check if a chunk is waiting to be re-meshed.
+    pub fn needs_remesh(&self, chunk_pos: IVec3) -> bool {
+        self.remesh.contains(&chunk_pos)
+    }
+    
+    /// Take all chunk positions waiting to be re-meshed, clearing the queue.
+    pub fn drain_remesh(&mut self) -> Vec<IVec3> {
+        self.remesh.drain().collect()
+    }
+    
+    fn queue_neighbor_remesh(&mut self, chunk_pos: IVec3) {
+        let offsets: &[IVec3] = match self.layout {
+            ChunkLayout::Column => &HORIZONTAL_NEIGHBORS,
+            ChunkLayout::Cubic => &FACE_NEIGHBORS,
+        };
+        for &offset in offsets {
+            let neighbor_pos = chunk_pos + offset;
+            if self.chunks.contains_key(&neighbor_pos) {
+                self.remesh.insert(neighbor_pos);
+            }
+        }
     }
     
     /// Get all loaded chunk positions.
@@ -175,21 +417,47 @@ impl ChunkManager {
         self.chunks.len()
     }
     
-    /// Unload chunks outside a given radius from center.
+    /// Unload chunks outside a given horizontal radius from center.
+    ///
+    /// Only x and z are compared, so in the cubic layout whole stacks of
+    /// chunks are unloaded together.
     pub fn unload_distant_chunks(&mut self, center: IVec3, max_distance: i32) {
         let max_distance_sq = max_distance * max_distance;
         
-        self.chunks.retain(|&chunk_pos, _| {
-            let diff = chunk_pos - center;
-            let distance_sq = diff.x * diff.x + diff.z * diff.z;
-            distance_sq <= max_distance_sq
-        });
+        let distant: Vec<IVec3> = self.chunks.keys()
+            .copied()
+            .filter(|&chunk_pos| {
+                let diff = chunk_pos - center;
+                diff.x * diff.x + diff.z * diff.z > max_distance_sq
+            })
+            .collect();
+        
+        for chunk_pos in distant {
+            self.remove_chunk(chunk_pos);
+        }
     }
 }
 
+/// Offsets of the horizontal neighbours that share a face with the block at
+/// `local_pos`, i.e. the chunks whose border meshes depend on it.
+pub fn border_neighbors(local_pos: IVec3) -> impl Iterator<Item = IVec3> {
+    let x = match local_pos.x {
+        0 => Some(IVec3::new(-1, 0, 0)),
+        x if x == CHUNK_SIZE - 1 => Some(IVec3::new(1, 0, 0)),
+        _ => None,
+    };
+    let z = match local_pos.z {
+        0 => Some(IVec3::new(0, 0, -1)),
+        z if z == CHUNK_SIZE - 1 => Some(IVec3::new(0, 0, 1)),
+        _ => None,
+    };
+    x.into_iter().chain(z)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use voxel_core::CHUNK_HEIGHT;
 
     #[test]
     fn test_chunk_creation() {
@@ -229,6 +497,7 @@ mod tests {
         chunk.set_block(pos, AIR_BLOCK);
         assert_eq!(chunk.get_block(pos), AIR_BLOCK);
         assert_eq!(chunk.count_solid_blocks(), 0);
+        assert!(chunk.is_empty());
 
         // Test out of bounds
         let oob_pos = IVec3::new(-1, 0, 0);
@@ -281,4 +550,305 @@ mod tests {
         assert!(manager.get_chunk(chunk_pos).is_some()); // Near chunk should remain
         assert!(manager.get_chunk(far_pos).is_none()); // Far chunk should be gone
     }
+
+    #[test]
+    fn test_border_neighbors() {
+        assert_eq!(border_neighbors(IVec3::new(5, 10, 5)).count(), 0);
+        assert_eq!(
+            border_neighbors(IVec3::new(0, 10, 5)).collect::<Vec<_>>(),
+            vec![IVec3::new(-1, 0, 0)]
+        );
+        assert_eq!(
+            border_neighbors(IVec3::new(15, 10, 0)).collect::<Vec<_>>(),
+            vec![IVec3::new(1, 0, 0), IVec3::new(0, 0, -1)]
+        );
+    }
+
+    #[test]
+    fn test_remesh_queue() {
+        let mut manager = ChunkManager::new();
+        manager.insert_chunk(Chunk::new(IVec3::ZERO));
+        assert_eq!(manager.drain_remesh(), vec![IVec3::ZERO]);
+
+        // Loading a neighbour re-meshes both chunks along the shared border
+        manager.insert_chunk(Chunk::new(IVec3::new(1, 0, 0)));
+        let mut queued = manager.drain_remesh();
+        queued.sort_by_key(|pos| pos.x);
+        assert_eq!(queued, vec![IVec3::ZERO, IVec3::new(1, 0, 0)]);
+
+        // Interior edits only touch their own chunk
+        manager.set_block(IVec3::new(5, 10, 5), 1);
+        assert_eq!(manager.drain_remesh(), vec![IVec3::ZERO]);
+
+        // Edge edits also re-mesh the loaded neighbour across that edge
+        manager.set_block(IVec3::new(15, 10, 5), 1);
+        assert!(manager.needs_remesh(IVec3::ZERO));
+        assert!(manager.needs_remesh(IVec3::new(1, 0, 0)));
+        manager.drain_remesh();
+
+        // Edges facing unloaded chunks don't queue anything extra
+        manager.set_block(IVec3::new(0, 10, 5), 1);
+        assert_eq!(manager.drain_remesh(), vec![IVec3::ZERO]);
+
+        // Unloading re-meshes the remaining neighbour
+        manager.remove_chunk(IVec3::new(1, 0, 0));
+        assert_eq!(manager.drain_remesh(), vec![IVec3::ZERO]);
+    }
+
+    fn sample_chunk() -> Chunk {
+        let mut chunk = Chunk::new(IVec3::new(-4, 0, 9));
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for y in 0..60 {
+                    chunk.set_block(IVec3::new(x, y, z), if y < 55 { 1 } else { 2 });
+                }
+            }
+        }
+        chunk.set_block(IVec3::new(3, 60, 7), 3);
+        chunk
+    }
+
+    fn assert_same_chunk(a: &Chunk, b: &Chunk) {
+        assert_eq!(a.position, b.position);
+        assert_eq!(a.sections(), b.sections());
+    }
+
+    #[test]
+    fn test_chunk_serde_json() {
+        let chunk = sample_chunk();
+        let json = serde_json::to_string(&chunk).unwrap();
+
+        // Run-length encoding keeps layered terrain far below one entry per voxel
+        assert!(json.len() < 1024, "json is {} bytes", json.len());
+
+        let loaded: Chunk = serde_json::from_str(&json).unwrap();
+        assert_same_chunk(&loaded, &chunk);
+        assert!(!loaded.is_dirty());
+        assert_eq!(loaded.get_block(IVec3::new(3, 60, 7)), 3);
+    }
+
+    #[test]
+    fn test_chunk_serde_binary() {
+        let chunk = sample_chunk();
+        let bytes = bincode::serialize(&chunk).unwrap();
+        assert!(bytes.len() < 1024, "binary is {} bytes", bytes.len());
+
+        let loaded: Chunk = bincode::deserialize(&bytes).unwrap();
+        assert_same_chunk(&loaded, &chunk);
+
+        let empty = Chunk::new(IVec3::ZERO);
+        let loaded: Chunk = bincode::deserialize(&bincode::serialize(&empty).unwrap()).unwrap();
+        assert!(loaded.is_empty());
+    }
+
+    #[test]
+    fn test_chunk_serde_rejects_bad_runs() {
+        let json = r#"{"position":{"x":0,"y":0,"z":0},"sections":[{"palette":[0],"voxels":[[0,10]]}]}"#;
+        assert!(serde_json::from_str::<Chunk>(json).is_err());
+
+        // Every section of a column or cube must be present, even if only as null
+        let json = r#"{"position":{"x":0,"y":0,"z":0},"sections":[null,null]}"#;
+        assert!(serde_json::from_str::<Chunk>(json).is_err());
+    }
+
+    #[test]
+    fn test_voxel_memory_usage() {
+        // Empty and uniform chunks store no voxel words at all
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        assert_eq!(chunk.memory_usage(), 0);
+        assert!(chunk.sections().iter().all(Option::is_none));
+        chunk.fill(1);
+        assert_eq!(chunk.memory_usage(), 0);
+        assert_eq!(chunk.get_block(IVec3::new(3, 200, 9)), 1);
+
+        // Two blocks need one bit per voxel, only in the edited section
+        chunk.set_block(IVec3::new(0, 0, 0), AIR_BLOCK);
+        assert_eq!(chunk.section(0).unwrap().voxels.bits_per_value(), 1);
+        assert_eq!(chunk.section(1).unwrap().voxels.bits_per_value(), 0);
+        assert_eq!(chunk.memory_usage(), SECTION_VOLUME / 8);
+        assert_eq!(chunk.get_block(IVec3::ZERO), AIR_BLOCK);
+        assert_eq!(chunk.count_solid_blocks(), CHUNK_VOLUME - 1);
+
+        // Noisy chunks grow to 4 and then 8 bits, keeping every block intact
+        let mut noisy = Chunk::new(IVec3::ZERO);
+        let block_at = |index: usize, kinds: usize| (index * 2654435761 % kinds) as BlockId;
+        for index in 0..CHUNK_VOLUME {
+            noisy.set_block(index_to_local(index).unwrap(), block_at(index, 12));
+        }
+        assert_eq!(noisy.section(3).unwrap().voxels.bits_per_value(), 4);
+        assert_eq!(noisy.memory_usage(), CHUNK_VOLUME / 2);
+
+        for index in 0..CHUNK_VOLUME {
+            noisy.set_block(index_to_local(index).unwrap(), 100 + block_at(index, 40));
+        }
+        assert_eq!(noisy.section(3).unwrap().voxels.bits_per_value(), 8);
+        assert_eq!(noisy.memory_usage(), CHUNK_VOLUME);
+        for index in (0..CHUNK_VOLUME).step_by(97) {
+            assert_eq!(noisy.get_block(index_to_local(index).unwrap()), 100 + block_at(index, 40));
+        }
+    }
+
+    #[test]
+    fn test_many_distinct_blocks() {
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        for block_id in 1..=1000 {
+            chunk.set_block(index_to_local(block_id as usize * 4).unwrap(), block_id);
+        }
+
+        let section = chunk.section(0).unwrap();
+        assert_eq!(section.palette.len(), 1001);
+        assert_eq!(section.voxels.bits_per_value(), 16);
+        assert_eq!(chunk.count_solid_blocks(), 1000);
+        for block_id in 1..=1000 {
+            assert_eq!(chunk.get_block(index_to_local(block_id as usize * 4).unwrap()), block_id);
+        }
+
+        let json = serde_json::to_string(&chunk).unwrap();
+        let loaded: Chunk = serde_json::from_str(&json).unwrap();
+        assert_same_chunk(&loaded, &chunk);
+    }
+
+    #[test]
+    fn test_compact_after_place_remove_cycles() {
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        for cycle in 0..3u16 {
+            for i in 0..50u16 {
+                chunk.set_block(IVec3::new(i as i32 % 16, 10 + cycle as i32, i as i32 / 16), 1 + cycle * 50 + i);
+            }
+            assert!(!chunk.is_empty());
+            assert_eq!(chunk.count_solid_blocks(), 50);
+            for i in 0..50u16 {
+                chunk.set_block(IVec3::new(i as i32 % 16, 10 + cycle as i32, i as i32 / 16), AIR_BLOCK);
+            }
+            assert!(chunk.is_empty());
+            assert_eq!(chunk.count_solid_blocks(), 0);
+        }
+        assert_eq!(chunk.section(0).unwrap().palette.len(), 151);
+
+        // The mined-out section is released entirely
+        assert_eq!(chunk.compact(), 150);
+        assert!(chunk.section(0).is_none());
+        assert_eq!(chunk.memory_usage(), 0);
+        assert!(chunk.is_empty());
+        assert_eq!(chunk.compact(), 0);
+    }
+
+    #[test]
+    fn test_compact_remaps_voxels() {
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        for block_id in 1..=20 {
+            chunk.set_block(IVec3::new(block_id as i32 % 16, 0, block_id as i32 / 16), block_id);
+        }
+        // Keep only the odd blocks
+        for block_id in (2..=20u16).step_by(2) {
+            chunk.set_block(IVec3::new(block_id as i32 % 16, 0, block_id as i32 / 16), AIR_BLOCK);
+        }
+        let before: Vec<_> = chunk.iter_blocks().collect();
+
+        assert_eq!(chunk.compact(), 10);
+        let section = chunk.section(0).unwrap();
+        assert_eq!(section.palette.len(), 11);
+        assert_eq!(section.palette.get_block(0), AIR_BLOCK);
+        assert_eq!(section.voxels.bits_per_value(), 4);
+        assert_eq!(chunk.iter_blocks().collect::<Vec<_>>(), before);
+        assert_eq!(chunk.count_solid_blocks(), 10);
+
+        // A section without air keeps air at index 0
+        let mut full = Chunk::new(IVec3::ZERO);
+        full.fill(1);
+        assert_eq!(full.compact(), 0);
+        full.set_block(IVec3::ZERO, 2);
+        full.set_block(IVec3::ZERO, 1);
+        assert_eq!(full.compact(), 1);
+        assert_eq!(full.section(0).unwrap().palette.get_block(0), AIR_BLOCK);
+        assert_eq!(full.count_solid_blocks(), CHUNK_VOLUME);
+    }
+
+    #[test]
+    fn test_sections() {
+        let mut chunk = Chunk::new(IVec3::ZERO);
+
+        // Writing air into an empty section doesn't allocate it
+        chunk.set_block(IVec3::new(1, 40, 1), AIR_BLOCK);
+        assert!(chunk.section(2).is_none());
+
+        chunk.set_block(IVec3::new(1, 40, 1), 1);
+        chunk.set_block(IVec3::new(2, 47, 3), 2);
+        chunk.set_block(IVec3::new(0, 255, 0), 3);
+        assert_eq!(chunk.sections().iter().flatten().count(), 2);
+        assert_eq!(chunk.section(2).unwrap().get_block(15 * 256 + 3 * 16 + 2), 2);
+        assert_eq!(chunk.get_block(IVec3::new(0, 255, 0)), 3);
+        assert_eq!(chunk.iter_blocks().count(), 3);
+
+        // Edits only dirty the sections they touch
+        assert_eq!(chunk.dirty_sections().collect::<Vec<_>>(), vec![2, 15]);
+        assert!(chunk.is_section_dirty(2));
+        assert!(!chunk.is_section_dirty(3));
+        chunk.mark_clean();
+        assert!(!chunk.is_dirty());
+
+        chunk.fill(AIR_BLOCK);
+        assert!(chunk.sections().iter().all(Option::is_none));
+        assert_eq!(chunk.dirty_sections().count(), SECTIONS_PER_CHUNK);
+    }
+
+    #[test]
+    fn test_cubic_chunk_manager() {
+        let mut manager = ChunkManager::with_layout(ChunkLayout::Cubic);
+        manager.set_block(IVec3::new(3, -1, 4), 1);
+        manager.set_block(IVec3::new(3, -300, 4), 2);
+        manager.set_block(IVec3::new(3, 1000, 4), 3);
+
+        assert_eq!(manager.chunk_count(), 3);
+        assert_eq!(manager.get_block(IVec3::new(3, -1, 4)), 1);
+        assert_eq!(manager.get_block(IVec3::new(3, -300, 4)), 2);
+        assert_eq!(manager.get_block(IVec3::new(3, 1000, 4)), 3);
+        assert_eq!(manager.get_block(IVec3::new(3, 0, 4)), AIR_BLOCK);
+
+        let chunk = manager.get_chunk(IVec3::new(0, -1, 0)).unwrap();
+        assert_eq!(chunk.layout(), ChunkLayout::Cubic);
+        assert_eq!(chunk.sections().len(), 1);
+        assert_eq!(chunk.get_block(IVec3::new(3, 15, 4)), 1);
+        assert_eq!(manager.get_chunk(IVec3::new(0, -19, 0)).unwrap().get_block(IVec3::new(3, 4, 4)), 2);
+
+        // Cubic chunks end at local y = 15
+        let mut cube = Chunk::with_layout(IVec3::ZERO, ChunkLayout::Cubic);
+        cube.set_block(IVec3::new(0, 16, 0), 1);
+        assert!(cube.is_empty());
+        assert!(!cube.is_dirty());
+        cube.fill(1);
+        assert_eq!(cube.count_solid_blocks(), 16 * 16 * 16);
+    }
+
+    #[test]
+    fn test_cubic_remesh_queue() {
+        let mut manager = ChunkManager::with_layout(ChunkLayout::Cubic);
+        manager.insert_chunk(Chunk::with_layout(IVec3::ZERO, ChunkLayout::Cubic));
+        manager.drain_remesh();
+
+        // Loading the cube below re-meshes the one above it
+        manager.insert_chunk(Chunk::with_layout(IVec3::new(0, -1, 0), ChunkLayout::Cubic));
+        let mut queued = manager.drain_remesh();
+        queued.sort_by_key(|pos| pos.y);
+        assert_eq!(queued, vec![IVec3::new(0, -1, 0), IVec3::ZERO]);
+
+        // Edits on the bottom face also re-mesh the cube below
+        manager.set_block(IVec3::new(5, 0, 5), 1);
+        assert!(manager.needs_remesh(IVec3::new(0, -1, 0)));
+        manager.drain_remesh();
+
+        manager.set_block(IVec3::new(5, 7, 5), 1);
+        assert_eq!(manager.drain_remesh(), vec![IVec3::ZERO]);
+    }
+
+    #[test]
+    fn test_cubic_chunk_serde() {
+        let mut cube = Chunk::with_layout(IVec3::new(1, -6, 2), ChunkLayout::Cubic);
+        cube.set_block(IVec3::new(2, 3, 4), 5);
+
+        let json = serde_json::to_string(&cube).unwrap();
+        let loaded: Chunk = serde_json::from_str(&json).unwrap();
+        assert_same_chunk(&loaded, &cube);
+        assert_eq!(loaded.layout(), ChunkLayout::Cubic);
+    }
 }
@@ -0,0 +1,332 @@
+//! Decoration features placed after the terrain: trees, boulders and ore
+//! veins.
+//!
+//! Features are planned per cell from the seed and the cell position alone,
+//! so every chunk a feature overlaps plans it identically and draws its own
+//! part of it. Chunks apply the features around them in one global order,
+//! which keeps overlapping features the same whatever order chunks are
+//! generated in.
+
+use voxel_core::{IVec3, CHUNK_SIZE};
+use serde::{Deserialize, Serialize};
+
+/// Width of a feature cell. Surface features are planned per column of
+/// cells, ore veins per cube.
+pub const FEATURE_CELL_SIZE: i32 = CHUNK_SIZE;
+
+/// Furthest any block of a feature lies from its anchor, horizontally and
+/// (for ore veins) vertically. Features only reach into neighbouring cells.
+pub const MAX_FEATURE_REACH: i32 = 3;
+
+const TREE_SALT: u64 = 1;
+const BOULDER_SALT: u64 = 2;
+const ORE_SALT: u64 = 3;
+
+/// Decoration settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeatureConfig {
+    /// Tree attempts per cell; each succeeds with its biome's tree chance
+    pub tree_attempts: u32,
+    /// Chance of a boulder per cell in biomes with boulders
+    pub boulder_chance: f64,
+    pub ores: Vec<OreConfig>,
+}
+
+impl Default for FeatureConfig {
+    fn default() -> Self {
+        Self {
+            tree_attempts: 3,
+            boulder_chance: 0.1,
+            ores: vec![
+                OreConfig {
+                    block: "coal_ore".into(),
+                    veins_per_cell: 2,
+                    size: 10,
+                    min_y: 8,
+                    max_y: 96,
+                },
+                OreConfig {
+                    block: "iron_ore".into(),
+                    veins_per_cell: 1,
+                    size: 6,
+                    min_y: 8,
+                    max_y: 56,
+                },
+            ],
+        }
+    }
+}
+
+/// An ore generated as veins inside stone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OreConfig {
+    /// Block name, resolved in the generator's registry
+    pub block: String,
+    /// Veins per cube of cells within the height range
+    pub veins_per_cell: u32,
+    /// Blocks per vein; veins that fold back on themselves end up smaller
+    pub size: u32,
+    /// Lowest vein centre
+    pub min_y: i32,
+    /// Highest vein centre
+    pub max_y: i32,
+}
+
+/// What a block of a feature is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FeaturePart {
+    Log,
+    Leaves,
+    Rock,
+    /// Index into `FeatureConfig::ores`
+    Ore(usize),
+}
+
+/// A planned feature.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Feature {
+    /// A tree rooted on the surface. Placed if `roll` is below the tree
+    /// chance of its biome.
+    Tree {
+        x: i32,
+        z: i32,
+        roll: f64,
+        trunk_height: i32,
+    },
+    /// A lump of rock half sunk into the surface.
+    Boulder { x: i32, z: i32, radius: i32 },
+    OreVein {
+        ore: usize,
+        center: IVec3,
+        size: u32,
+        seed: u64,
+    },
+}
+
+impl Feature {
+    /// Horizontal position the feature is built around.
+    pub fn anchor(&self) -> (i32, i32) {
+        match *self {
+            Feature::Tree { x, z, .. } | Feature::Boulder { x, z, .. } => (x, z),
+            Feature::OreVein { center, .. } => (center.x, center.z),
+        }
+    }
+
+    /// Whether any block of the feature can fall inside the inclusive
+    /// bounds. Only the horizontal extent is checked for surface features,
+    /// whose height isn't known yet.
+    pub fn may_touch(&self, min: IVec3, max: IVec3) -> bool {
+        let (x, z) = self.anchor();
+        let reach = MAX_FEATURE_REACH;
+        let horizontal = x + reach >= min.x && x - reach <= max.x && z + reach >= min.z && z - reach <= max.z;
+        match *self {
+            Feature::OreVein { center, .. } => {
+                horizontal && center.y + reach >= min.y && center.y - reach <= max.y
+            }
+            _ => horizontal,
+        }
+    }
+
+    /// Blocks of the feature in placement order. Surface features stand on
+    /// the block at `surface_y`; ore veins ignore it.
+    pub fn blocks(&self, surface_y: i32) -> Vec<(IVec3, FeaturePart)> {
+        let mut blocks = Vec::new();
+        match *self {
+            Feature::Tree { x, z, trunk_height, .. } => {
+                let top = surface_y + trunk_height;
+                // Two wide layers under the top and two narrow ones above,
+                // without corners
+                for dy in -2..=1 {
+                    let radius: i32 = if dy < 0 { 2 } else { 1 };
+                    for dx in -radius..=radius {
+                        for dz in -radius..=radius {
+                            if dx.abs() == radius && dz.abs() == radius {
+                                continue;
+                            }
+                            blocks.push((IVec3::new(x + dx, top + dy, z + dz), FeaturePart::Leaves));
+                        }
+                    }
+                }
+                // The trunk replaces the leaves in its way
+                for y in surface_y + 1..=top {
+                    blocks.push((IVec3::new(x, y, z), FeaturePart::Log));
+                }
+            }
+            Feature::Boulder { x, z, radius } => {
+                let center = IVec3::new(x, surface_y + 1, z);
+                for dx in -radius..=radius {
+                    for dy in -radius..=radius {
+                        for dz in -radius..=radius {
+                            let offset = IVec3::new(dx, dy, dz);
+                            if offset.length_squared() <= radius * radius + radius {
+                                blocks.push((center + offset, FeaturePart::Rock));
+                            }
+                        }
+                    }
+                }
+            }
+            Feature::OreVein { ore, center, size, seed } => {
+                // A random walk that stays within reach of the centre
+                let mut rng = FeatureRng(seed);
+                let limit = IVec3::new(MAX_FEATURE_REACH, MAX_FEATURE_REACH, MAX_FEATURE_REACH);
+                let mut offset = IVec3::ZERO;
+                for _ in 0..size {
+                    blocks.push((center + offset, FeaturePart::Ore(ore)));
+                    let step = IVec3::new(rng.range(-1, 1), rng.range(-1, 1), rng.range(-1, 1));
+                    offset = (offset + step).clamp(limit * -1, limit);
+                }
+            }
+        }
+        blocks
+    }
+}
+
+/// Trees and boulders of the cell column at `(cell_x, cell_z)`.
+pub(crate) fn plan_surface_features(config: &FeatureConfig, seed: u32, cell_x: i32, cell_z: i32) -> Vec<Feature> {
+    let cell = IVec3::new(cell_x, 0, cell_z);
+    let mut features = Vec::new();
+
+    let mut rng = FeatureRng::new(seed, cell, BOULDER_SALT);
+    if rng.next_f64() < config.boulder_chance {
+        let (x, z) = rng.position_in(cell);
+        features.push(Feature::Boulder { x, z, radius: rng.range(1, 2) });
+    }
+
+    let mut rng = FeatureRng::new(seed, cell, TREE_SALT);
+    for _ in 0..config.tree_attempts {
+        let (x, z) = rng.position_in(cell);
+        features.push(Feature::Tree {
+            x,
+            z,
+            roll: rng.next_f64(),
+            trunk_height: rng.range(4, 6),
+        });
+    }
+    features
+}
+
+/// Ore veins of the cell cube at `cell`.
+pub(crate) fn plan_ore_veins(config: &FeatureConfig, seed: u32, cell: IVec3) -> Vec<Feature> {
+    let cell_min_y = cell.y * FEATURE_CELL_SIZE;
+    let cell_max_y = cell_min_y + FEATURE_CELL_SIZE - 1;
+    let mut features = Vec::new();
+
+    for (ore, ore_config) in config.ores.iter().enumerate() {
+        let min_y = ore_config.min_y.max(cell_min_y);
+        let max_y = ore_config.max_y.min(cell_max_y);
+        if min_y > max_y {
+            continue;
+        }
+
+        let mut rng = FeatureRng::new(seed, cell, ORE_SALT + ore as u64);
+        for _ in 0..ore_config.veins_per_cell {
+            let (x, z) = rng.position_in(cell);
+            let center = IVec3::new(x, rng.range(min_y, max_y), z);
+            features.push(Feature::OreVein {
+                ore,
+                center,
+                size: ore_config.size,
+                seed: rng.next_u64(),
+            });
+        }
+    }
+    features
+}
+
+/// Small deterministic random number generator (SplitMix64).
+#[derive(Debug, Clone)]
+pub(crate) struct FeatureRng(u64);
+
+impl FeatureRng {
+    /// A generator for one kind of feature in one cell.
+    pub fn new(seed: u32, cell: IVec3, salt: u64) -> Self {
+        let mut rng = Self(seed as u64 ^ salt.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        for coordinate in [cell.x, cell.y, cell.z] {
+            rng.0 = rng.next_u64() ^ coordinate as u32 as u64;
+        }
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0.0..1.0`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `min..=max`.
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        let span = (max as i64 - min as i64 + 1) as u64;
+        (min as i64 + (self.next_u64() % span) as i64) as i32
+    }
+
+    /// A random column inside a cell.
+    fn position_in(&mut self, cell: IVec3) -> (i32, i32) {
+        let x = cell.x * FEATURE_CELL_SIZE + self.range(0, FEATURE_CELL_SIZE - 1);
+        let z = cell.z * FEATURE_CELL_SIZE + self.range(0, FEATURE_CELL_SIZE - 1);
+        (x, z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plans_deterministic() {
+        let config = FeatureConfig::default();
+        for cell in [(0, 0), (-1, 5), (1000, -1000)] {
+            let plan = plan_surface_features(&config, 42, cell.0, cell.1);
+            assert_eq!(plan, plan_surface_features(&config, 42, cell.0, cell.1));
+            assert_ne!(plan, plan_surface_features(&config, 43, cell.0, cell.1));
+        }
+        assert_ne!(plan_surface_features(&config, 42, 0, 1), plan_surface_features(&config, 42, 1, 0));
+
+        let cell = IVec3::new(3, 2, -4);
+        let veins = plan_ore_veins(&config, 42, cell);
+        assert_eq!(veins.len(), 3);
+        assert_eq!(veins, plan_ore_veins(&config, 42, cell));
+        // Nothing outside the ore height ranges
+        assert!(plan_ore_veins(&config, 42, IVec3::new(0, 10, 0)).is_empty());
+        assert!(plan_ore_veins(&config, 42, IVec3::new(0, -1, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_features_stay_within_reach() {
+        let config = FeatureConfig { boulder_chance: 1.0, ..FeatureConfig::default() };
+        for cell_x in -8..8 {
+            let mut features = plan_surface_features(&config, 7, cell_x, 3);
+            features.extend(plan_ore_veins(&config, 7, IVec3::new(cell_x, 3, 3)));
+            for feature in features {
+                let (x, z) = feature.anchor();
+                for (pos, _) in feature.blocks(60) {
+                    assert!((pos.x - x).abs() <= MAX_FEATURE_REACH, "{:?}", feature);
+                    assert!((pos.z - z).abs() <= MAX_FEATURE_REACH, "{:?}", feature);
+                    if let Feature::OreVein { center, .. } = feature {
+                        assert!((pos.y - center.y).abs() <= MAX_FEATURE_REACH, "{:?}", feature);
+                    }
+                    assert!(feature.may_touch(pos, pos));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rng_range() {
+        let mut rng = FeatureRng::new(1, IVec3::ZERO, 0);
+        let mut seen = [false; 5];
+        for _ in 0..200 {
+            let value = rng.range(-2, 2);
+            seen[(value + 2) as usize] = true;
+            assert!((0.0..1.0).contains(&rng.next_f64()));
+        }
+        assert!(seen.iter().all(|&seen| seen));
+    }
+}
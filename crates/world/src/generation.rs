@@ -1,19 +1,44 @@
 //! Terrain generation using layered noise.
 
-use voxel_core::{BlockId, BlockRegistry, IVec3, CHUNK_SIZE, CHUNK_HEIGHT};
-use crate::chunk::{Chunk, ChunkManager};
-use noise::{NoiseFn, Perlin, Seedable};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::Path;
 
-/// Terrain generator configuration.
-#[derive(Debug, Clone)]
+use voxel_core::{load_config, local_to_index, BlockId, BlockRegistry, ChunkLayout, ConfigError, IVec3, Palette, CHUNK_SIZE};
+use crate::caves::{CaveCarver, CaveConfig, Tunnels};
+use crate::chunk::Chunk;
+use crate::lakes::{lake_sites_near, Lake, LakeConfig, LakeSite};
+use crate::structure::{Placement, StructureHeight, StructureRule};
+use crate::features::{plan_ore_veins, plan_surface_features, Feature, FeatureConfig, FeaturePart, FEATURE_CELL_SIZE, MAX_FEATURE_REACH};
+use crate::terrain_noise::{HeightNoise, TerrainNoise};
+use crate::world_generator::WorldGenerator;
+use noise::{NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
+
+/// Terrain generator configuration. Fields missing from a config file keep
+/// their default values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TerrainConfig {
     pub seed: u32,
     pub sea_level: i32,
+    /// Lowest terrain height; everything at or below it is solid stone
+    pub min_height: i32,
     pub max_height: i32,
     pub height_scale: f64,
-    pub height_frequency: f64,
-    pub cave_frequency: f64,
-    pub cave_threshold: f64,
+    /// Noise stack shaping the surface
+    pub noise: TerrainNoise,
+    /// Caves, tunnels and ravines carved out of the terrain
+    pub caves: CaveConfig,
+    /// Lakes filling inland depressions
+    pub lakes: LakeConfig,
+    /// Frequency of the temperature and humidity noise that picks biomes
+    pub biome_frequency: f64,
+    /// Trees, boulders and ores placed on the terrain
+    pub features: FeatureConfig,
+    /// Chunk layout of generated chunks. `ChunkStreamer` and the renderer
+    /// still expect columns, so configs loaded from files must use them.
+    pub layout: ChunkLayout,
 }
 
 impl Default for TerrainConfig {
@@ -21,138 +46,577 @@ impl Default for TerrainConfig {
         Self {
             seed: 12345,
             sea_level: 64,
+            min_height: 0,
             max_height: 128,
             height_scale: 32.0,
-            height_frequency: 0.01,
-            cave_frequency: 0.05,
-            cave_threshold: 0.3,
+            noise: TerrainNoise::default(),
+            caves: CaveConfig::default(),
+            lakes: LakeConfig::default(),
+            biome_frequency: 0.002,
+            features: FeatureConfig::default(),
+            layout: ChunkLayout::Column,
         }
     }
 }
 
+impl TerrainConfig {
+    /// Cubic-chunk terrain reaching far below 0 and far above the column
+    /// height limit.
+    pub fn cubic() -> Self {
+        Self {
+            min_height: -512,
+            max_height: 1024,
+            height_scale: 256.0,
+            noise: TerrainNoise::default().scaled(0.4),
+            layout: ChunkLayout::Cubic,
+            ..Self::default()
+        }
+    }
+    
+    /// Load a config from a RON, TOML or JSON file and validate it.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let config: Self = load_config(path)?;
+        config.validate()
+            .map_err(|message| ConfigError::Invalid { path: path.to_path_buf(), message })?;
+        Ok(config)
+    }
+    
+    /// Check that the height range isn't empty, the noise can be sampled
+    /// and the chunks can be streamed and rendered.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.layout != ChunkLayout::Column {
+            return Err("cubic chunks can't be streamed or rendered yet; use the Column layout".to_string());
+        }
+        if self.min_height > self.max_height {
+            return Err(format!("min_height {} is above max_height {}", self.min_height, self.max_height));
+        }
+        if !(self.biome_frequency > 0.0 && self.biome_frequency.is_finite()) {
+            return Err("biome_frequency must be positive".to_string());
+        }
+        self.noise.validate()
+    }
+}
+
+/// `TerrainConfig::height_scale` is the height scale of hills; other biomes
+/// scale their terrain relative to it.
+const REFERENCE_HEIGHT_SCALE: f64 = 32.0;
+/// How quickly biome weights fall off with distance in climate space.
+/// Lower values blend heights over wider borders.
+const BIOME_BLEND_SHARPNESS: f64 = 40.0;
+/// Depth of the subsurface layer below the surface block.
+const SUBSURFACE_DEPTH: i32 = 3;
+/// Columns up to this far above sea level are sandy beaches.
+const BEACH_HEIGHT: i32 = 1;
+/// Water deeper than this has a gravel floor instead of sand.
+const SHALLOW_WATER_DEPTH: i32 = 4;
+/// Ground up to this far above a lake's water level is dug out for the
+/// lake; higher ground is left as islands.
+const LAKE_BANK_HEIGHT: i32 = 3;
+
+/// Surface of one column.
+#[derive(Debug, Clone, Copy)]
+struct Column {
+    height: i32,
+    biome: Biome,
+    /// Water fills the column from above `height` up to this level
+    water_level: i32,
+}
+
+impl Column {
+    fn is_underwater(&self) -> bool {
+        self.height < self.water_level
+    }
+}
+
+/// Block IDs used by the generator, resolved from the registry once.
+#[derive(Debug, Clone)]
+struct TerrainBlocks {
+    air: BlockId,
+    stone: BlockId,
+    water: BlockId,
+    sand: BlockId,
+    gravel: BlockId,
+    wood: BlockId,
+    leaves: BlockId,
+    /// Surface blocks in `Biome::ALL` order
+    surface: Vec<BlockId>,
+    /// Subsurface blocks in `Biome::ALL` order
+    subsurface: Vec<BlockId>,
+    /// Ore blocks in `FeatureConfig::ores` order
+    ores: Vec<BlockId>,
+}
+
+impl TerrainBlocks {
+    fn resolve(registry: &BlockRegistry, features: &FeatureConfig) -> Result<Self, UnknownBlockError> {
+        let id = |name: &str| registered_block(registry, name);
+        Ok(Self {
+            air: id("air")?,
+            stone: id("stone")?,
+            water: id("water")?,
+            sand: id("sand")?,
+            gravel: id("gravel")?,
+            wood: id("wood")?,
+            leaves: id("leaves")?,
+            surface: Biome::ALL.iter().map(|biome| biome.get_surface_block(registry)).collect::<Result<_, _>>()?,
+            subsurface: Biome::ALL.iter().map(|biome| biome.get_subsurface_block(registry)).collect::<Result<_, _>>()?,
+            ores: features.ores.iter().map(|ore| id(&ore.block)).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// ID of a named block, or an error if it isn't registered.
+fn registered_block(registry: &BlockRegistry, name: &str) -> Result<BlockId, UnknownBlockError> {
+    match registry.get_by_name(name) {
+        Some(block) => Ok(block.id),
+        None => Err(UnknownBlockError { block: name.to_string() }),
+    }
+}
+
+/// A generator needs a block the registry doesn't define.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownBlockError {
+    pub block: String,
+}
+
+impl fmt::Display for UnknownBlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block {} isn't registered", self.block)
+    }
+}
+
+impl std::error::Error for UnknownBlockError {}
+
+/// Blocks that decoration features write into.
+trait FeatureTarget {
+    /// Inclusive world-space bounds of the blocks held.
+    fn bounds(&self) -> (IVec3, IVec3);
+    /// Block at a position within the bounds.
+    fn get(&self, pos: IVec3) -> BlockId;
+    /// Replace the block at a position within the bounds.
+    fn set(&mut self, pos: IVec3, block: BlockId);
+}
+
+/// A single block, for sampling one position with its features.
+struct BlockSample {
+    pos: IVec3,
+    block: BlockId,
+}
+
+impl FeatureTarget for BlockSample {
+    fn bounds(&self) -> (IVec3, IVec3) {
+        (self.pos, self.pos)
+    }
+    
+    fn get(&self, _pos: IVec3) -> BlockId {
+        self.block
+    }
+    
+    fn set(&mut self, _pos: IVec3, block: BlockId) {
+        self.block = block;
+    }
+}
+
+/// Palette indices of a chunk being generated.
+struct ChunkBuffer {
+    /// World position of the chunk's lowest corner
+    origin: IVec3,
+    height: i32,
+    palette: Palette,
+    /// Recently used palette entries; faster than the palette's map for the
+    /// handful of blocks in a chunk
+    palette_ids: Vec<(BlockId, u16)>,
+    voxels: Vec<u16>,
+}
+
+impl ChunkBuffer {
+    fn palette_index(&mut self, block_id: BlockId) -> u16 {
+        match self.palette_ids.iter().find(|(id, _)| *id == block_id) {
+            Some(&(_, palette_id)) => palette_id,
+            None => {
+                let palette_id = self.palette.add_block(block_id);
+                self.palette_ids.push((block_id, palette_id));
+                palette_id
+            }
+        }
+    }
+    
+    fn index(&self, pos: IVec3) -> usize {
+        local_to_index(pos - self.origin).unwrap()
+    }
+}
+
+impl FeatureTarget for ChunkBuffer {
+    fn bounds(&self) -> (IVec3, IVec3) {
+        let size = IVec3::new(CHUNK_SIZE, self.height, CHUNK_SIZE);
+        (self.origin, self.origin + size - IVec3::new(1, 1, 1))
+    }
+    
+    fn get(&self, pos: IVec3) -> BlockId {
+        self.palette.get_block(self.voxels[self.index(pos)])
+    }
+    
+    fn set(&mut self, pos: IVec3, block: BlockId) {
+        let palette_id = self.palette_index(block);
+        let index = self.index(pos);
+        self.voxels[index] = palette_id;
+    }
+}
+
 /// Procedural terrain generator.
 pub struct TerrainGenerator {
     config: TerrainConfig,
-    height_noise: Perlin,
-    cave_noise: Perlin,
+    height_noise: HeightNoise,
+    caves: CaveCarver,
+    temperature_noise: Perlin,
+    humidity_noise: Perlin,
     registry: BlockRegistry,
+    blocks: TerrainBlocks,
+    structures: Vec<StructureRule>,
 }
 
 impl TerrainGenerator {
+    /// Create a generator using the built-in blocks.
+    ///
+    /// Panics if the config names ores that aren't built in.
     pub fn new(config: TerrainConfig) -> Self {
-        let mut height_noise = Perlin::new();
-        height_noise = height_noise.set_seed(config.seed);
-        
-        let mut cave_noise = Perlin::new();
-        cave_noise = cave_noise.set_seed(config.seed.wrapping_add(1));
+        Self::with_registry(config, BlockRegistry::new()).unwrap_or_else(|error| panic!("{}", error))
+    }
+    
+    /// Create a generator using a custom block registry, e.g. one loaded
+    /// from asset files. Fails unless the registry defines air, stone, dirt,
+    /// grass, sand, gravel, water, wood, leaves and the configured ores.
+    pub fn with_registry(config: TerrainConfig, registry: BlockRegistry) -> Result<Self, UnknownBlockError> {
+        let blocks = TerrainBlocks::resolve(&registry, &config.features)?;
+        let height_noise = HeightNoise::new(&config.noise, config.seed);
+        let caves = CaveCarver::new(&config.caves, config.seed, config.min_height, config.sea_level);
+        let temperature_noise = Perlin::new(config.seed.wrapping_add(2));
+        let humidity_noise = Perlin::new(config.seed.wrapping_add(3));
         
-        Self {
+        Ok(Self {
             config,
             height_noise,
-            cave_noise,
-            registry: BlockRegistry::new(),
+            caves,
+            temperature_noise,
+            humidity_noise,
+            registry,
+            blocks,
+            structures: Vec::new(),
+        })
+    }
+    
+    /// Place structures by these rules after the other features. Later
+    /// rules are placed over earlier ones.
+    pub fn with_structures(mut self, structures: Vec<StructureRule>) -> Self {
+        self.structures = structures;
+        self
+    }
+    
+    /// Temperature and humidity of a column, each roughly in `-1.0..1.0`.
+    pub fn climate_at(&self, x: i32, z: i32) -> (f64, f64) {
+        let point = [
+            x as f64 * self.config.biome_frequency,
+            z as f64 * self.config.biome_frequency,
+        ];
+        (self.temperature_noise.get(point), self.humidity_noise.get(point))
+    }
+    
+    /// Biome of a column.
+    pub fn biome_at(&self, x: i32, z: i32) -> Biome {
+        let (temperature, humidity) = self.climate_at(x, z);
+        Biome::from_climate(temperature, humidity)
+    }
+    
+    /// Height scale of a column, blended between the biomes whose climates
+    /// are close so terrain stays continuous across biome borders.
+    pub fn height_scale_at(&self, x: i32, z: i32) -> f64 {
+        let (temperature, humidity) = self.climate_at(x, z);
+        self.blended_height_scale(temperature, humidity)
+    }
+    
+    fn blended_height_scale(&self, temperature: f64, humidity: f64) -> f64 {
+        let distances = Biome::ALL.map(|biome| biome.climate_distance(temperature, humidity));
+        let nearest = distances.iter().copied().fold(f64::INFINITY, f64::min);
+        
+        let mut total_weight = 0.0;
+        let mut scale = 0.0;
+        for (biome, distance) in Biome::ALL.iter().zip(distances) {
+            // Relative to the nearest biome, which keeps the weights finite
+            let weight = (-BIOME_BLEND_SHARPNESS * (distance - nearest)).exp();
+            total_weight += weight;
+            scale += weight * biome.get_height_scale();
         }
+        
+        scale / total_weight / REFERENCE_HEIGHT_SCALE * self.config.height_scale
     }
     
-    /// Generate terrain height at given x, z coordinates.
-    pub fn get_height(&self, x: i32, z: i32) -> i32 {
-        let height = self.height_noise.get([
-            x as f64 * self.config.height_frequency,
-            z as f64 * self.config.height_frequency,
-        ]);
+    /// Surface height and biome of a column before lakes are dug.
+    fn base_column(&self, x: i32, z: i32) -> (i32, Biome) {
+        let (temperature, humidity) = self.climate_at(x, z);
+        let height = self.height_noise.sample(x as f64, z as f64);
         
-        let scaled_height = height * self.config.height_scale;
+        let scaled_height = height * self.blended_height_scale(temperature, humidity);
         let final_height = self.config.sea_level + scaled_height as i32;
         
-        final_height.clamp(0, self.config.max_height)
+        (
+            final_height.clamp(self.config.min_height, self.config.max_height),
+            Biome::from_climate(temperature, humidity),
+        )
     }
     
-    /// Check if position should be a cave.
-    pub fn is_cave(&self, x: i32, y: i32, z: i32) -> bool {
-        if y <= 5 || y >= self.config.max_height - 5 {
-            return false; // No caves near bedrock or surface
+    /// Lakes that can cover a column in the inclusive bounds.
+    fn lakes_near(&self, min_x: i32, min_z: i32, max_x: i32, max_z: i32) -> Vec<Lake> {
+        lake_sites_near(&self.config.lakes, self.config.seed, min_x, min_z, max_x, max_z).into_iter()
+            .filter_map(|site| self.resolve_lake(site))
+            .collect()
+    }
+    
+    /// The lake at a site, if the site is a depression above sea level. The
+    /// water stops a block under the lowest point of the rim, and the centre
+    /// must be low enough to be dug out.
+    fn resolve_lake(&self, site: LakeSite) -> Option<Lake> {
+        let rim = site.rim().into_iter()
+            .map(|(x, z)| self.base_column(x, z).0)
+            .min()?;
+        let level = rim - 1;
+        let (x, z) = site.center();
+        let inside = self.base_column(x, z).0;
+        (level > self.config.sea_level && inside <= level + LAKE_BANK_HEIGHT).then_some(Lake { site, level })
+    }
+    
+    /// Surface and water of a column. `lakes` must include every lake
+    /// covering it.
+    fn column_in(&self, x: i32, z: i32, lakes: &[Lake]) -> Column {
+        let (mut height, biome) = self.base_column(x, z);
+        let mut water_level = self.config.sea_level;
+        for lake in lakes {
+            match lake.bowl_depth(x, z) {
+                Some(depth) if height <= lake.level + LAKE_BANK_HEIGHT => {
+                    height = height.min(lake.level - depth);
+                    water_level = water_level.max(lake.level);
+                }
+                _ => {}
+            }
         }
-        
-        let cave_value = self.cave_noise.get([
-            x as f64 * self.config.cave_frequency,
-            y as f64 * self.config.cave_frequency * 0.5, // Stretch caves vertically
-            z as f64 * self.config.cave_frequency,
-        ]);
-        
-        cave_value.abs() < self.config.cave_threshold
+        Column { height, biome, water_level }
+    }
+    
+    fn column(&self, x: i32, z: i32) -> Column {
+        self.column_in(x, z, &self.lakes_near(x, z, x, z))
     }
     
-    /// Get block type for given world position.
+    /// Generate terrain height at given x, z coordinates.
+    pub fn get_height(&self, x: i32, z: i32) -> i32 {
+        self.column(x, z).height
+    }
+    
+    /// Top of the water in a column, if the column is under the sea or a
+    /// lake.
+    pub fn get_water_level(&self, x: i32, z: i32) -> Option<i32> {
+        let column = self.column(x, z);
+        column.is_underwater().then_some(column.water_level)
+    }
+    
+    /// Check if a position is carved out by caves, tunnels or ravines.
+    /// Only positions between the world floor and the surface are.
+    pub fn is_cave(&self, x: i32, y: i32, z: i32) -> bool {
+        let pos = IVec3::new(x, y, z);
+        let height = self.get_height(x, z);
+        y <= height && self.caves.carves(pos, height, &self.caves.tunnels_near(pos, pos))
+    }
+    
+    /// Get block type for given world position, including features.
     pub fn get_block_at(&self, world_pos: IVec3) -> BlockId {
-        let height = self.get_height(world_pos.x, world_pos.z);
+        let column = self.column(world_pos.x, world_pos.z);
+        // Tunnels only matter below the surface
+        let tunnels = if world_pos.y <= column.height {
+            self.caves.tunnels_near(world_pos, world_pos)
+        } else {
+            Tunnels::default()
+        };
+        let mut sample = BlockSample {
+            pos: world_pos,
+            block: self.column_block_at(world_pos, column, &tunnels),
+        };
+        self.decorate(&mut sample);
+        sample.block
+    }
+    
+    /// Get block type for a position in a column. `tunnels` must reach the
+    /// position.
+    fn column_block_at(&self, world_pos: IVec3, column: Column, tunnels: &Tunnels) -> BlockId {
+        let Column { height, biome, water_level } = column;
         
         // Above terrain
         if world_pos.y > height {
-            return self.registry.get_by_name("air").unwrap().id;
+            return if world_pos.y <= water_level { self.blocks.water } else { self.blocks.air };
         }
         
-        // Check for caves
-        if self.is_cave(world_pos.x, world_pos.y, world_pos.z) {
-            return self.registry.get_by_name("air").unwrap().id;
+        // Check for caves, flooded under water
+        if self.caves.carves(world_pos, height, tunnels) {
+            return if column.is_underwater() { self.blocks.water } else { self.blocks.air };
         }
         
         // Bedrock layer
-        if world_pos.y <= 0 {
-            return self.registry.get_by_name("stone").unwrap().id;
+        if world_pos.y <= self.config.min_height {
+            return self.blocks.stone;
         }
         
+        // Sand on beaches and shallow floors, gravel deeper down
+        let sandy = column.is_underwater() || height <= self.config.sea_level + BEACH_HEIGHT;
+        
         // Surface layer
         if world_pos.y == height {
-            return self.registry.get_by_name("grass").unwrap().id;
+            return match () {
+                _ if water_level - height > SHALLOW_WATER_DEPTH => self.blocks.gravel,
+                _ if sandy => self.blocks.sand,
+                _ => self.blocks.surface[biome as usize],
+            };
         }
         
         // Subsurface layers
-        if world_pos.y >= height - 3 {
-            return self.registry.get_by_name("dirt").unwrap().id;
+        if world_pos.y >= height - SUBSURFACE_DEPTH {
+            return if sandy { self.blocks.sand } else { self.blocks.subsurface[biome as usize] };
         }
         
         // Deep stone
-        self.registry.get_by_name("stone").unwrap().id
+        self.blocks.stone
     }
     
-    /// Generate a single chunk.
-    pub fn generate_chunk(&self, chunk_pos: IVec3) -> Chunk {
-        let mut chunk = Chunk::new(chunk_pos);
-        
-        // Calculate world coordinates for this chunk
-        let world_x_start = chunk_pos.x * CHUNK_SIZE;
-        let world_z_start = chunk_pos.z * CHUNK_SIZE;
+    /// Add the parts of the features that reach into `target`.
+    ///
+    /// Features are applied in one global order (ore veins, then boulders,
+    /// then trees, each by cell, then structures by rule) so every target
+    /// sees the same sequence of writes for a position, wherever its bounds
+    /// are.
+    fn decorate(&self, target: &mut impl FeatureTarget) {
+        let (min, max) = target.bounds();
+        let features = &self.config.features;
+        let seed = self.config.seed;
+        let cells = |min: i32, max: i32| {
+            (min - MAX_FEATURE_REACH).div_euclid(FEATURE_CELL_SIZE)..=(max + MAX_FEATURE_REACH).div_euclid(FEATURE_CELL_SIZE)
+        };
         
-        // Generate blocks for the entire chunk
-        for local_x in 0..CHUNK_SIZE {
-            for local_z in 0..CHUNK_SIZE {
-                let world_x = world_x_start + local_x;
-                let world_z = world_z_start + local_z;
-                
-                for local_y in 0..CHUNK_HEIGHT {
-                    let world_pos = IVec3::new(world_x, local_y, world_z);
-                    let local_pos = IVec3::new(local_x, local_y, local_z);
-                    
-                    let block_id = self.get_block_at(world_pos);
-                    chunk.set_block(local_pos, block_id);
+        for cell_x in cells(min.x, max.x) {
+            for cell_z in cells(min.z, max.z) {
+                for cell_y in cells(min.y, max.y) {
+                    for vein in plan_ore_veins(features, seed, IVec3::new(cell_x, cell_y, cell_z)) {
+                        if vein.may_touch(min, max) {
+                            self.place_feature(&vein, 0, target);
+                        }
+                    }
                 }
             }
         }
         
-        chunk.mark_clean(); // Newly generated chunks are clean
-        chunk
+        let mut surface_features = Vec::new();
+        for cell_x in cells(min.x, max.x) {
+            for cell_z in cells(min.z, max.z) {
+                surface_features.extend(
+                    plan_surface_features(features, seed, cell_x, cell_z).into_iter()
+                        .filter(|feature| feature.may_touch(min, max)),
+                );
+            }
+        }
+        let (boulders, trees): (Vec<_>, Vec<_>) = surface_features.into_iter()
+            .partition(|feature| matches!(feature, Feature::Boulder { .. }));
+        
+        for feature in boulders.iter().chain(&trees) {
+            let (x, z) = feature.anchor();
+            let column = self.column(x, z);
+            let (height, biome) = (column.height, column.biome);
+            
+            // Only on intact dry ground, not over a ravine or on a beach
+            let ground_pos = IVec3::new(x, height, z);
+            let tunnels = self.caves.tunnels_near(ground_pos, ground_pos);
+            let ground = self.column_block_at(ground_pos, column, &tunnels);
+            if column.is_underwater() || ground != self.blocks.surface[biome as usize] {
+                continue;
+            }
+            let fits = match *feature {
+                Feature::Tree { roll, .. } => roll < biome.get_tree_chance(),
+                _ => biome.has_boulders(),
+            };
+            if fits {
+                self.place_feature(feature, height, target);
+            }
+        }
+        
+        for (rule, placement) in self.structures_in(min, max) {
+            self.place_structure(rule, placement, target);
+        }
+    }
+    
+    /// Write the blocks of a feature that fall inside `target`. Logs grow
+    /// through leaves, leaves and rocks only fill air, and ores only replace
+    /// stone above the bedrock layer.
+    fn place_feature(&self, feature: &Feature, surface_y: i32, target: &mut impl FeatureTarget) {
+        let (min, max) = target.bounds();
+        let air = self.blocks.air;
+        for (pos, part) in feature.blocks(surface_y) {
+            if pos.clamp(min, max) != pos {
+                continue;
+            }
+            
+            let current = target.get(pos);
+            let block = match part {
+                FeaturePart::Log if current == air || current == self.blocks.leaves => self.blocks.wood,
+                FeaturePart::Leaves if current == air => self.blocks.leaves,
+                FeaturePart::Rock if current == air => self.blocks.stone,
+                FeaturePart::Ore(ore) if current == self.blocks.stone && pos.y > self.config.min_height => {
+                    self.blocks.ores[ore]
+                }
+                _ => continue,
+            };
+            target.set(pos, block);
+        }
     }
     
-    /// Generate multiple chunks in a radius around center.
-    pub fn generate_chunks_around(&self, center: IVec3, radius: i32, chunk_manager: &mut ChunkManager) {
-        for x in (center.x - radius)..=(center.x + radius) {
-            for z in (center.z - radius)..=(center.z + radius) {
-                let chunk_pos = IVec3::new(x, 0, z);
+    /// Structures placed by the generator's rules that reach into the
+    /// inclusive bounds, in the order they're placed.
+    pub fn structures_in(&self, min: IVec3, max: IVec3) -> Vec<(&StructureRule, Placement)> {
+        let seed = self.config.seed;
+        let mut placements = Vec::new();
+        for rule in &self.structures {
+            let reach = rule.structure.reach();
+            for (cell_x, cell_z) in rule.cells(min.x - reach, min.z - reach, max.x + reach, max.z + reach) {
+                let Some(mut placement) = rule.plan(seed, cell_x, cell_z) else {
+                    continue;
+                };
+                let (x, z) = (placement.position.x, placement.position.z);
+                if x + reach < min.x || x - reach > max.x || z + reach < min.z || z - reach > max.z {
+                    continue;
+                }
                 
-                // Only generate if chunk doesn't exist
-                if chunk_manager.get_chunk(chunk_pos).is_none() {
-                    let chunk = self.generate_chunk(chunk_pos);
-                    chunk_manager.insert_chunk(chunk);
+                if rule.placement.height == StructureHeight::Surface {
+                    let column = self.column(x, z);
+                    if column.is_underwater() {
+                        continue;
+                    }
+                    placement.position.y = column.height;
+                }
+                // Keep it if the overlap with the bounds isn't empty
+                let (low, high) = rule.structure.bounds(placement);
+                if low.max(min) == low.max(min).min(high.min(max)) {
+                    placements.push((rule, placement));
+                }
+            }
+        }
+        placements
+    }
+    
+    /// Write the blocks of a structure that fall inside `target`, over
+    /// whatever is there except the bedrock layer.
+    fn place_structure(&self, rule: &StructureRule, placement: Placement, target: &mut impl FeatureTarget) {
+        let (min, max) = target.bounds();
+        let (low, high) = rule.structure.bounds(placement);
+        let (low, high) = (low.max(min), high.min(max));
+        for y in low.y.max(self.config.min_height + 1)..=high.y {
+            for z in low.z..=high.z {
+                for x in low.x..=high.x {
+                    let pos = IVec3::new(x, y, z);
+                    if let Some(block) = rule.structure.block_at(placement, pos) {
+                        target.set(pos, block);
+                    }
                 }
             }
         }
@@ -162,15 +626,78 @@ impl TerrainGenerator {
     pub fn config(&self) -> &TerrainConfig {
         &self.config
     }
+}
+
+impl WorldGenerator for TerrainGenerator {
+    /// Generate a single chunk.
+    ///
+    /// Works column by column: the height is sampled once per column, only
+    /// blocks up to the surface are evaluated (so cave noise is never sampled
+    /// in open sky), and palette indices are collected in a flat buffer that
+    /// is split into sections once at the end. Features are added on top.
+    /// The result matches `get_block_at` for every position.
+    fn generate_chunk(&self, chunk_pos: IVec3) -> Chunk {
+        let layout = self.config.layout;
+        let chunk_height = layout.chunk_height();
+        
+        // Calculate world coordinates for this chunk
+        let world_x_start = chunk_pos.x * CHUNK_SIZE;
+        let world_y_start = chunk_pos.y * chunk_height;
+        let world_z_start = chunk_pos.z * CHUNK_SIZE;
+        
+        let mut buffer = ChunkBuffer {
+            origin: IVec3::new(world_x_start, world_y_start, world_z_start),
+            height: chunk_height,
+            palette: Palette::new(),
+            palette_ids: Vec::with_capacity(4),
+            voxels: vec![0u16; layout.chunk_volume()],
+        };
+        let (min, max) = buffer.bounds();
+        let tunnels = self.caves.tunnels_near(min, max);
+        let lakes = self.lakes_near(min.x, min.z, max.x, max.z);
+        
+        for local_z in 0..CHUNK_SIZE {
+            for local_x in 0..CHUNK_SIZE {
+                let world_x = world_x_start + local_x;
+                let world_z = world_z_start + local_z;
+                let column = self.column_in(world_x, world_z, &lakes);
+                let top = column.height.max(column.water_level);
+                
+                // Everything above the surface and water stays air (palette
+                // index 0)
+                for local_y in 0..=(top - world_y_start).min(chunk_height - 1) {
+                    let world_y = world_y_start + local_y;
+                    let block_id = self.column_block_at(IVec3::new(world_x, world_y, world_z), column, &tunnels);
+                    if block_id == self.blocks.air {
+                        continue;
+                    }
+                    
+                    let palette_id = buffer.palette_index(block_id);
+                    let index = local_to_index(IVec3::new(local_x, local_y, local_z)).unwrap();
+                    buffer.voxels[index] = palette_id;
+                }
+            }
+        }
+        
+        self.decorate(&mut buffer);
+        
+        // Newly generated chunks are clean
+        Chunk::from_palette_indices(chunk_pos, &buffer.palette, &buffer.voxels)
+    }
     
-    /// Get the block registry.
-    pub fn registry(&self) -> &BlockRegistry {
+    fn registry(&self) -> &BlockRegistry {
         &self.registry
     }
+    
+    /// In the cubic layout every chunk layer between `min_height` and
+    /// `max_height` is generated.
+    fn chunk_layers(&self) -> RangeInclusive<i32> {
+        self.config.layout.chunk_y_range(self.config.min_height, self.config.max_height)
+    }
 }
 
-/// Biome-based terrain generation (future expansion).
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Terrain biomes, picked from temperature and humidity noise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Biome {
     Plains,
     Hills,
@@ -179,14 +706,67 @@ pub enum Biome {
 }
 
 impl Biome {
-    pub fn get_surface_block(&self, registry: &BlockRegistry) -> BlockId {
+    pub const ALL: [Biome; 4] = [Biome::Plains, Biome::Hills, Biome::Mountains, Biome::Desert];
+    
+    /// The `(temperature, humidity)` this biome fits best.
+    pub fn climate(&self) -> (f64, f64) {
         match self {
-            Biome::Plains | Biome::Hills => registry.get_by_name("grass").unwrap().id,
-            Biome::Mountains => registry.get_by_name("stone").unwrap().id,
-            Biome::Desert => registry.get_by_name("dirt").unwrap().id, // Would be sand in full implementation
+            Biome::Plains => (0.0, -0.1),
+            Biome::Hills => (0.0, 0.3),
+            Biome::Mountains => (-0.35, 0.0),
+            Biome::Desert => (0.35, -0.3),
         }
     }
     
+    /// Distance from this biome's climate to the given one.
+    fn climate_distance(&self, temperature: f64, humidity: f64) -> f64 {
+        let (t, h) = self.climate();
+        ((temperature - t).powi(2) + (humidity - h).powi(2)).sqrt()
+    }
+    
+    /// The biome whose climate is closest to the given one.
+    pub fn from_climate(temperature: f64, humidity: f64) -> Self {
+        Self::ALL.into_iter()
+            .min_by(|a, b| {
+                a.climate_distance(temperature, humidity)
+                    .total_cmp(&b.climate_distance(temperature, humidity))
+            })
+            .unwrap()
+    }
+    
+    pub fn get_surface_block(&self, registry: &BlockRegistry) -> Result<BlockId, UnknownBlockError> {
+        match self {
+            Biome::Plains | Biome::Hills => registered_block(registry, "grass"),
+            Biome::Mountains => registered_block(registry, "stone"),
+            Biome::Desert => registered_block(registry, "sand"),
+        }
+    }
+    
+    /// Block filling the few layers under the surface block.
+    pub fn get_subsurface_block(&self, registry: &BlockRegistry) -> Result<BlockId, UnknownBlockError> {
+        match self {
+            Biome::Plains | Biome::Hills => registered_block(registry, "dirt"),
+            Biome::Mountains => registered_block(registry, "stone"),
+            Biome::Desert => registered_block(registry, "sand"),
+        }
+    }
+    
+    /// Chance of each tree attempt growing a tree.
+    pub fn get_tree_chance(&self) -> f64 {
+        match self {
+            Biome::Plains => 0.08,
+            Biome::Hills => 0.4,
+            Biome::Mountains | Biome::Desert => 0.0,
+        }
+    }
+    
+    /// Whether boulders lie on the surface.
+    pub fn has_boulders(&self) -> bool {
+        matches!(self, Biome::Plains | Biome::Mountains)
+    }
+    
+    /// Terrain amplitude. Hills get the configured `height_scale` and the
+    /// other biomes scale with it.
     pub fn get_height_scale(&self) -> f64 {
         match self {
             Biome::Plains => 16.0,
@@ -200,6 +780,10 @@ impl Biome {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::chunk::ChunkManager;
+    use crate::structure::{Structure, StructurePlacement};
+    use crate::terrain_noise::NoiseLayer;
+    use voxel_core::{AIR_BLOCK, CHUNK_HEIGHT};
 
     #[test]
     fn test_terrain_config() {
@@ -207,7 +791,73 @@ mod tests {
         assert_eq!(config.seed, 12345);
         assert_eq!(config.sea_level, 64);
         assert!(config.height_scale > 0.0);
-        assert!(config.height_frequency > 0.0);
+        assert!(!config.noise.layers.is_empty());
+    }
+
+    #[test]
+    fn test_terrain_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("terrain.ron");
+        std::fs::write(&path, r#"(
+            seed: 7,
+            noise: (
+                layers: [(kind: ridged, octaves: 3, frequency: 0.01, lacunarity: 2.0, persistence: 0.5, weight: 1.0)],
+                warp: None,
+            ),
+        )"#).unwrap();
+
+        let config = TerrainConfig::load(&path).unwrap();
+        assert_eq!(config.seed, 7);
+        assert_eq!(config.noise.layers, vec![NoiseLayer::ridged(3, 0.01, 1.0)]);
+        assert_eq!(config.noise.warp, None);
+        // Missing fields keep their defaults
+        assert_eq!(config.noise.spline, TerrainNoise::default().spline);
+        assert_eq!(config.sea_level, 64);
+
+        let path = dir.path().join("terrain.toml");
+        std::fs::write(&path, "seed = \"oops\"").unwrap();
+        assert!(matches!(TerrainConfig::load(&path), Err(ConfigError::Parse { .. })));
+
+        // Values that parse but can't generate terrain
+        for contents in [
+            "min_height = 200",
+            "biome_frequency = 0.0",
+            "[noise]\nlayers = [{ kind = \"fbm\", octaves = 0, frequency = 0.01, lacunarity = 2.0, persistence = 0.5, weight = 1.0 }]",
+            "[noise]\nwarp = { octaves = 2, frequency = -0.01, amplitude = 8.0 }",
+            "[noise]\nspline = [[0.0, 0.0], [0.5, 0.2], [0.5, 0.4]]",
+            "layout = \"Cubic\"",
+        ] {
+            std::fs::write(&path, contents).unwrap();
+            assert!(matches!(TerrainConfig::load(&path), Err(ConfigError::Invalid { .. })), "{}", contents);
+        }
+    }
+
+    #[test]
+    fn test_bundled_terrain_config() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../assets/terrain.ron");
+        assert_eq!(TerrainConfig::load(&path).unwrap(), TerrainConfig::default());
+    }
+
+    /// Statistics of the heightmap for the default seed. A change here
+    /// changes every generated world, so update the numbers deliberately.
+    #[test]
+    fn test_heightmap_snapshot() {
+        fn stats(config: TerrainConfig) -> (i32, i32, f64, f64) {
+            let generator = TerrainGenerator::new(config);
+            let heights: Vec<i32> = (-64..64)
+                .flat_map(|x| (-64..64).map(move |z| (x * 16, z * 16)))
+                .map(|(x, z)| generator.get_height(x, z))
+                .collect();
+            let count = heights.len() as f64;
+            let mean = heights.iter().sum::<i32>() as f64 / count;
+            let variance = heights.iter().map(|&h| (h as f64 - mean).powi(2)).sum::<f64>() / count;
+            let min = *heights.iter().min().unwrap();
+            let max = *heights.iter().max().unwrap();
+            (min, max, (mean * 10.0).round() / 10.0, (variance.sqrt() * 10.0).round() / 10.0)
+        }
+
+        assert_eq!(stats(TerrainConfig::default()), (39, 116, 64.8, 5.4));
+        assert_eq!(stats(TerrainConfig::cubic()), (-138, 423, 74.9, 51.6));
     }
 
     #[test]
@@ -228,10 +878,11 @@ mod tests {
         // Same coordinates should give same height
         assert_eq!(height1, generator.get_height(0, 0));
 
-        // Test block generation
-        let surface_pos = IVec3::new(0, height1, 0);
+        // Test block generation, away from caves breaking the surface
+        let x = (0..).find(|&x| !generator.is_cave(x, generator.get_height(x, 0), 0)).unwrap();
+        let surface_pos = IVec3::new(x, generator.get_height(x, 0), 0);
         let surface_block = generator.get_block_at(surface_pos);
-        assert_eq!(surface_block, generator.registry.get_by_name("grass").unwrap().id);
+        assert_eq!(surface_block, generator.biome_at(x, 0).get_surface_block(&generator.registry).unwrap());
 
         let above_surface = IVec3::new(0, height1 + 10, 0);
         let air_block = generator.get_block_at(above_surface);
@@ -260,8 +911,9 @@ mod tests {
         let mut found_surface = false;
         let mut found_air = false;
 
-        for (local_pos, block_id) in chunk.iter_blocks() {
-            if block_id == generator.registry.get_by_name("grass").unwrap().id {
+        let surface = generator.biome_at(8, 8).get_surface_block(&generator.registry).unwrap();
+        for (_, block_id) in chunk.iter_blocks() {
+            if block_id == surface {
                 found_surface = true;
             }
         }
@@ -277,6 +929,9 @@ mod tests {
 
         assert!(found_surface, "Should have surface blocks");
         assert!(found_air, "Should have air blocks above terrain");
+
+        // Open sky isn't stored at all
+        assert!(chunk.section(crate::section::SECTIONS_PER_CHUNK - 1).is_none());
     }
 
     #[test]
@@ -286,9 +941,478 @@ mod tests {
         let plains = Biome::Plains;
         let mountains = Biome::Mountains;
 
-        assert_eq!(plains.get_surface_block(&registry), registry.get_by_name("grass").unwrap().id);
-        assert_eq!(mountains.get_surface_block(&registry), registry.get_by_name("stone").unwrap().id);
+        assert_eq!(plains.get_surface_block(&registry).unwrap(), registry.get_by_name("grass").unwrap().id);
+        assert_eq!(mountains.get_surface_block(&registry).unwrap(), registry.get_by_name("stone").unwrap().id);
 
         assert!(mountains.get_height_scale() > plains.get_height_scale());
+        assert_eq!(Biome::Desert.get_surface_block(&registry).unwrap(), registry.get_by_name("sand").unwrap().id);
+        assert_eq!(plains.get_subsurface_block(&registry).unwrap(), registry.get_by_name("dirt").unwrap().id);
+
+        // A registry without a biome's blocks can't generate terrain
+        let mut without_grass = registry.clone();
+        without_grass.unregister("grass");
+        assert_eq!(plains.get_surface_block(&without_grass), Err(UnknownBlockError { block: "grass".into() }));
+        let error = TerrainGenerator::with_registry(TerrainConfig::default(), without_grass).err().unwrap();
+        assert_eq!(error, UnknownBlockError { block: "grass".into() });
+
+        for biome in Biome::ALL {
+            let (temperature, humidity) = biome.climate();
+            assert_eq!(Biome::from_climate(temperature, humidity), biome);
+        }
+    }
+
+    #[test]
+    fn test_biomes_deterministic() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let same_seed = TerrainGenerator::new(TerrainConfig::default());
+        let other_seed = TerrainGenerator::new(TerrainConfig { seed: 999, ..TerrainConfig::default() });
+
+        let mut seen = std::collections::HashSet::new();
+        let mut differs = false;
+        for x in -32..32 {
+            for z in -32..32 {
+                let (x, z) = (x * 128, z * 128);
+                let biome = generator.biome_at(x, z);
+                assert_eq!(biome, same_seed.biome_at(x, z));
+                differs |= biome != other_seed.biome_at(x, z);
+                seen.insert(biome);
+            }
+        }
+        assert!(differs, "biomes don't depend on the seed");
+        assert_eq!(seen.len(), Biome::ALL.len(), "only saw {:?}", seen);
+    }
+
+    #[test]
+    fn test_biome_borders_continuous() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let mut borders = 0;
+        for z in [0, 777, -3000] {
+            for x in -6000..6000 {
+                let (a, b) = (generator.biome_at(x, z), generator.biome_at(x + 1, z));
+                if a != b {
+                    borders += 1;
+                    let step = (generator.get_height(x + 1, z) - generator.get_height(x, z)).abs();
+                    assert!(step <= 2, "height jumps by {} at ({}, {})", step, x, z);
+
+                    // Unblended, neighbouring biomes differ by at least 8
+                    let scale_step = (generator.height_scale_at(x + 1, z) - generator.height_scale_at(x, z)).abs();
+                    assert!(scale_step < 5.0, "{:?} -> {:?} at ({}, {}) changes scale by {}", a, b, x, z, scale_step);
+                }
+            }
+        }
+        assert!(borders > 20, "only {} biome borders crossed", borders);
+    }
+
+    #[test]
+    fn test_biome_surface_blocks() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let registry = &generator.registry;
+        let mut checked = std::collections::HashSet::new();
+        for x in -64..64 {
+            let (x, z) = (x * 97, 13);
+            let biome = generator.biome_at(x, z);
+            let height = generator.get_height(x, z);
+            if generator.is_cave(x, height, z) || generator.is_cave(x, height - 1, z) {
+                continue;
+            }
+            // Beaches and sea floors are sandy in every biome
+            if height <= generator.config.sea_level + BEACH_HEIGHT {
+                continue;
+            }
+            assert_eq!(generator.get_block_at(IVec3::new(x, height, z)), biome.get_surface_block(registry).unwrap());
+            assert_eq!(generator.get_block_at(IVec3::new(x, height - 1, z)), biome.get_subsurface_block(registry).unwrap());
+            checked.insert(biome);
+        }
+        assert!(checked.contains(&Biome::Desert) && checked.contains(&Biome::Plains), "{:?}", checked);
+    }
+
+    #[test]
+    fn test_parallel_generation_matches_serial() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let positions = [
+            IVec3::new(0, 0, 0),
+            IVec3::new(-3, 0, 2),
+            IVec3::new(7, 0, -5),
+            IVec3::new(1, 0, 1),
+        ];
+
+        let parallel = generator.generate_chunks_parallel(&positions);
+        assert_eq!(parallel.len(), positions.len());
+
+        for (&chunk_pos, chunk) in positions.iter().zip(&parallel) {
+            let serial = generator.generate_chunk(chunk_pos);
+            assert_eq!(chunk.position, serial.position);
+            assert_eq!(chunk.sections(), serial.sections());
+        }
+    }
+
+    #[test]
+    fn test_chunk_matches_block_sampling() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let chunk_pos = IVec3::new(-2, 0, 3);
+        let chunk = generator.generate_chunk(chunk_pos);
+
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for y in 0..CHUNK_HEIGHT {
+                    let local_pos = IVec3::new(x, y, z);
+                    let world_pos = IVec3::new(chunk_pos.x * CHUNK_SIZE + x, y, chunk_pos.z * CHUNK_SIZE + z);
+                    assert_eq!(chunk.get_block(local_pos), generator.get_block_at(world_pos));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_features_placed() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let registry = &generator.registry;
+        let id = |name: &str| registry.get_by_name(name).unwrap().id;
+        let (wood, leaves, stone) = (id("wood"), id("leaves"), id("stone"));
+        let ores = [id("coal_ore"), id("iron_ore")];
+
+        let mut counts = std::collections::HashMap::new();
+        for x in -4..4 {
+            for z in -4..4 {
+                let chunk = generator.generate_chunk(IVec3::new(x * 4, 0, z * 4));
+                for (pos, block) in chunk.iter_blocks() {
+                    *counts.entry(block).or_insert(0) += 1;
+                    if ores.contains(&block) {
+                        assert!(pos.y > generator.config.min_height && pos.y <= 96 + MAX_FEATURE_REACH);
+                    }
+                }
+                // Bedrock is never replaced
+                for local in [IVec3::new(0, 0, 0), IVec3::new(9, 0, 4)] {
+                    assert_eq!(chunk.get_block(local), stone);
+                }
+            }
+        }
+        for block in [wood, leaves, ores[0], ores[1]] {
+            assert!(counts.get(&block).copied().unwrap_or(0) > 0, "no {}", registry.get(block).unwrap().name);
+        }
+
+        // Ores missing from the registry are reported
+        let mut config = TerrainConfig::default();
+        config.features.ores[0].block = "mymod:mithril".into();
+        let error = TerrainGenerator::with_registry(config, BlockRegistry::new()).err().unwrap();
+        assert_eq!(error, UnknownBlockError { block: "mymod:mithril".into() });
+    }
+
+    #[test]
+    fn test_features_independent_of_generation_order() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let leaves = generator.registry.get_by_name("leaves").unwrap().id;
+        let positions: Vec<IVec3> = (-3..3)
+            .flat_map(|x| (-3..3).map(move |z| IVec3::new(x, 0, z)))
+            .collect();
+
+        let mut forward = ChunkManager::new();
+        for &pos in &positions {
+            forward.insert_chunk(generator.generate_chunk(pos));
+        }
+        let mut backward = ChunkManager::new();
+        for &pos in positions.iter().rev() {
+            backward.insert_chunk(generator.generate_chunk(pos));
+        }
+        let mut parallel = ChunkManager::new();
+        for chunk in generator.generate_chunks_parallel(&positions) {
+            parallel.insert_chunk(chunk);
+        }
+
+        for &pos in &positions {
+            let chunk = forward.get_chunk(pos).unwrap();
+            assert_eq!(chunk.sections(), backward.get_chunk(pos).unwrap().sections());
+            assert_eq!(chunk.sections(), parallel.get_chunk(pos).unwrap().sections());
+        }
+
+        // Some canopies cross chunk borders, and both sides agree with
+        // sampling the blocks one at a time
+        let mut crossings = 0;
+        for x in -3 * CHUNK_SIZE..3 * CHUNK_SIZE - 1 {
+            if (x + 1).rem_euclid(CHUNK_SIZE) != 0 {
+                continue;
+            }
+            for z in -3 * CHUNK_SIZE..3 * CHUNK_SIZE {
+                for y in 40..CHUNK_HEIGHT.min(140) {
+                    let (a, b) = (IVec3::new(x, y, z), IVec3::new(x + 1, y, z));
+                    if forward.get_block(a) == leaves && forward.get_block(b) == leaves {
+                        crossings += 1;
+                        assert_eq!(generator.get_block_at(a), leaves);
+                        assert_eq!(generator.get_block_at(b), leaves);
+                    }
+                }
+            }
+        }
+        assert!(crossings > 0, "no tree crosses a chunk border");
+    }
+
+    #[test]
+    fn test_structures_generated() {
+        let registry = BlockRegistry::new();
+        let marker = registry.get_by_name("coal_ore").unwrap().id;
+        let mut tower = Structure::new(IVec3::new(5, 8, 3)).with_origin(IVec3::new(2, 0, 1));
+        for y in 0..8 {
+            for z in 0..3 {
+                for x in 0..5 {
+                    tower.set(IVec3::new(x, y, z), Some(if y == 0 { marker } else { AIR_BLOCK }));
+                }
+            }
+        }
+        let placement = StructurePlacement { spacing: 2, separation: 0, ..StructurePlacement::default() };
+        let generator = TerrainGenerator::with_registry(TerrainConfig::default(), registry)
+            .unwrap()
+            .with_structures(vec![StructureRule::new("tower", tower, placement)]);
+
+        let min = IVec3::new(-4 * CHUNK_SIZE, 0, -4 * CHUNK_SIZE);
+        let max = IVec3::new(4 * CHUNK_SIZE - 1, CHUNK_HEIGHT - 1, 4 * CHUNK_SIZE - 1);
+        let placements = generator.structures_in(min, max);
+        assert!(placements.len() > 4);
+
+        // Chunks agree with sampling blocks one at a time, also where a
+        // structure crosses a chunk border
+        let mut crossings = 0;
+        for (rule, placement) in placements {
+            let (low, high) = rule.structure.bounds(placement);
+            assert_eq!(placement.position.y, generator.get_height(placement.position.x, placement.position.z));
+            if low.x.div_euclid(CHUNK_SIZE) != high.x.div_euclid(CHUNK_SIZE)
+                || low.z.div_euclid(CHUNK_SIZE) != high.z.div_euclid(CHUNK_SIZE)
+            {
+                crossings += 1;
+            }
+
+            let mut chunks = ChunkManager::new();
+            for chunk_x in low.x.div_euclid(CHUNK_SIZE)..=high.x.div_euclid(CHUNK_SIZE) {
+                for chunk_z in low.z.div_euclid(CHUNK_SIZE)..=high.z.div_euclid(CHUNK_SIZE) {
+                    chunks.insert_chunk(generator.generate_chunk(IVec3::new(chunk_x, 0, chunk_z)));
+                }
+            }
+            for (pos, block) in rule.structure.blocks(placement) {
+                assert_eq!(chunks.get_block(pos), block, "{:?}", pos);
+                assert_eq!(generator.get_block_at(pos), block, "{:?}", pos);
+            }
+        }
+        assert!(crossings > 0, "no structure crosses a chunk border");
+
+        // A generator without rules places nothing
+        let plain = TerrainGenerator::new(TerrainConfig::default());
+        assert!(plain.structures_in(min, max).is_empty());
+    }
+
+    #[test]
+    fn test_caves_connected() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let air = generator.blocks.air;
+        let stone = generator.blocks.stone;
+        let chunks = 4;
+        let size = chunks * CHUNK_SIZE;
+        let mut manager = ChunkManager::new();
+        for x in 0..chunks {
+            for z in 0..chunks {
+                manager.insert_chunk(generator.generate_chunk(IVec3::new(x, 0, z)));
+            }
+        }
+
+        // Air below the surface, and the bedrock layer under it intact
+        let floor = generator.config.min_height + generator.config.caves.floor_margin;
+        let mut cave = std::collections::HashSet::new();
+        for x in 0..size {
+            for z in 0..size {
+                for y in 0..=floor {
+                    assert_eq!(manager.get_block(IVec3::new(x, y, z)), stone);
+                }
+                for y in floor + 1..generator.get_height(x, z) {
+                    let pos = IVec3::new(x, y, z);
+                    if manager.get_block(pos) == air {
+                        cave.insert(pos);
+                    }
+                }
+            }
+        }
+        assert!(cave.len() > 5000, "only {} cave blocks", cave.len());
+
+        // Flood fill the caves. Pockets cut off by the edge of the area may
+        // connect outside it; caves closed off inside it should be rare
+        let mut seen = std::collections::HashSet::new();
+        let mut largest = 0;
+        let mut enclosed = 0;
+        for &start in &cave {
+            if !seen.insert(start) {
+                continue;
+            }
+            let mut queue = vec![start];
+            let mut count = 0;
+            let mut reaches_edge = false;
+            while let Some(pos) = queue.pop() {
+                count += 1;
+                reaches_edge |= pos.x == 0 || pos.z == 0 || pos.x == size - 1 || pos.z == size - 1;
+                for step in [IVec3::X, IVec3::Y, IVec3::Z] {
+                    for next in [pos + step, pos - step] {
+                        if cave.contains(&next) && seen.insert(next) {
+                            queue.push(next);
+                        }
+                    }
+                }
+            }
+            largest = largest.max(count);
+            if !reaches_edge {
+                enclosed += count;
+            }
+        }
+        let total = cave.len() as f64;
+        assert!(largest as f64 / total > 0.6, "largest cave holds {} of {} blocks", largest, total);
+        assert!(enclosed as f64 / total < 0.05, "{} of {} cave blocks are enclosed", enclosed, total);
+    }
+
+    #[test]
+    fn test_oceans_filled() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let sea_level = generator.config.sea_level;
+        let blocks = &generator.blocks;
+
+        // Chunks over deep water
+        let ocean_chunks: Vec<IVec3> = (-64..64)
+            .flat_map(|x| (-64..64).map(move |z| IVec3::new(x * 3, 0, z * 3)))
+            .filter(|chunk| generator.get_height(chunk.x * CHUNK_SIZE + 8, chunk.z * CHUNK_SIZE + 8) < sea_level - 8)
+            .take(4)
+            .collect();
+        assert_eq!(ocean_chunks.len(), 4);
+
+        let mut ocean_columns = 0;
+        for chunk_pos in ocean_chunks {
+            let chunk = generator.generate_chunk(chunk_pos);
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let (world_x, world_z) = (chunk_pos.x * CHUNK_SIZE + x, chunk_pos.z * CHUNK_SIZE + z);
+                    let height = generator.get_height(world_x, world_z);
+                    if height >= sea_level {
+                        continue;
+                    }
+                    ocean_columns += 1;
+                    assert_eq!(generator.get_water_level(world_x, world_z), Some(sea_level));
+
+                    // Flooded all the way down, caves included
+                    for y in 0..=sea_level {
+                        assert_ne!(chunk.get_block(IVec3::new(x, y, z)), blocks.air, "air at {} {} {}", world_x, y, world_z);
+                    }
+                    assert_eq!(chunk.get_block(IVec3::new(x, sea_level, z)), blocks.water);
+                    assert_eq!(chunk.get_block(IVec3::new(x, sea_level + 1, z)), blocks.air);
+                    let floor = chunk.get_block(IVec3::new(x, height, z));
+                    assert!(floor == blocks.sand || floor == blocks.gravel || floor == blocks.water);
+                }
+            }
+        }
+        assert!(ocean_columns > 500);
+    }
+
+    #[test]
+    fn test_beaches() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let sea_level = generator.config.sea_level;
+        let mut beaches = 0;
+        let mut deep_floors = 0;
+        for x in -200..200 {
+            let (x, z) = (x * 7, 31);
+            let height = generator.get_height(x, z);
+            if generator.is_cave(x, height, z) {
+                continue;
+            }
+            let surface = generator.get_block_at(IVec3::new(x, height, z));
+            if (sea_level..=sea_level + BEACH_HEIGHT).contains(&height) {
+                assert_eq!(surface, generator.blocks.sand);
+                beaches += 1;
+            } else if height < sea_level - SHALLOW_WATER_DEPTH {
+                assert_eq!(surface, generator.blocks.gravel);
+                deep_floors += 1;
+            }
+        }
+        assert!(beaches > 10 && deep_floors > 10, "{} beaches, {} deep floors", beaches, deep_floors);
+    }
+
+    #[test]
+    fn test_lakes() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let config = &generator.config;
+        let lakes: Vec<Lake> = (-20..20)
+            .flat_map(|x| (-20..20).map(move |z| (x, z)))
+            .filter_map(|(x, z)| crate::lakes::plan_lake_site(&config.lakes, config.seed, x, z))
+            .filter_map(|site| generator.resolve_lake(site))
+            .collect();
+        assert!(lakes.len() >= 3, "only {} lakes", lakes.len());
+
+        for lake in lakes {
+            assert!(lake.level > config.sea_level);
+            let (x, z) = lake.site.center();
+            let height = generator.get_height(x, z);
+            assert!(height < lake.level);
+            assert_eq!(generator.get_water_level(x, z), Some(lake.level));
+            assert_eq!(generator.get_block_at(IVec3::new(x, lake.level, z)), generator.blocks.water);
+            assert_eq!(generator.get_block_at(IVec3::new(x, lake.level + 1, z)), generator.blocks.air);
+
+            // The rim holds the water in
+            for (x, z) in lake.site.rim() {
+                assert!(generator.get_height(x, z) >= lake.level);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cubic_chunks_match_block_sampling() {
+        let generator = TerrainGenerator::new(TerrainConfig::cubic());
+        let layout = ChunkLayout::Cubic;
+        let surface = generator.get_height(40, -20);
+
+        for chunk_y in [-20, -3, -1, 0, surface.div_euclid(CHUNK_SIZE), surface.div_euclid(CHUNK_SIZE) + 1] {
+            let chunk_pos = IVec3::new(2, chunk_y, -2);
+            let chunk = generator.generate_chunk(chunk_pos);
+            assert_eq!(chunk.layout(), layout);
+
+            for index in 0..layout.chunk_volume() {
+                let local_pos = voxel_core::index_to_local(index).unwrap();
+                let world_pos = layout.chunk_local_to_world(chunk_pos, local_pos);
+                assert_eq!(chunk.get_block(local_pos), generator.get_block_at(world_pos), "at {:?}", world_pos);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cubic_deep_caves_and_tall_mountains() {
+        let generator = TerrainGenerator::new(TerrainConfig::cubic());
+        let air = generator.registry.get_by_name("air").unwrap().id;
+        let stone = generator.registry.get_by_name("stone").unwrap().id;
+
+        // Some mountains rise past the column height limit
+        let peak = (-64..64)
+            .flat_map(|x| (-64..64).map(move |z| (x * 16, z * 16)))
+            .map(|(x, z)| generator.get_height(x, z))
+            .max()
+            .unwrap();
+        assert!(peak > CHUNK_HEIGHT, "highest peak is {}", peak);
+
+        // Caves reach far below y = 0 but never through the world floor
+        let deep_cave = (-64..64)
+            .flat_map(|x| (-400..-100).map(move |y| IVec3::new(x, y, 7)))
+            .any(|pos| generator.get_block_at(pos) == air);
+        assert!(deep_cave);
+        assert_eq!(generator.get_block_at(IVec3::new(3, generator.config.min_height, 3)), stone);
+        assert_eq!(generator.get_block_at(IVec3::new(3, generator.config.max_height + 1, 3)), air);
+    }
+
+    #[test]
+    fn test_cubic_chunks_around() {
+        let config = TerrainConfig {
+            min_height: -32,
+            max_height: 200,
+            ..TerrainConfig::cubic()
+        };
+        let generator = TerrainGenerator::new(config);
+        let mut manager = ChunkManager::with_layout(ChunkLayout::Cubic);
+
+        generator.generate_chunks_around(IVec3::ZERO, 0, &mut manager);
+        assert_eq!(manager.chunk_count(), (-2..=12).count());
+        assert!(manager.get_chunk(IVec3::new(0, -2, 0)).is_some());
+
+        // World coordinates resolve to the right cube, including negative y
+        let world_pos = IVec3::new(5, -20, 9);
+        assert_eq!(manager.get_block(world_pos), generator.get_block_at(world_pos));
     }
 }
@@ -0,0 +1,175 @@
+//! Inland lakes.
+//!
+//! A lake site is planned per region from the seed. The generator keeps a
+//! site only above sea level where the middle lies low compared to the rim,
+//! sets the water level just under the lowest point of the rim, and digs a
+//! bowl that fills up to that level.
+
+use std::f64::consts::TAU;
+
+use voxel_core::IVec3;
+use crate::features::FeatureRng;
+use serde::{Deserialize, Serialize};
+
+/// Width of the regions lakes are planned in.
+pub const LAKE_REGION_SIZE: i32 = 64;
+
+/// Points sampled around a lake's rim to find its water level.
+pub(crate) const LAKE_RIM_SAMPLES: usize = 16;
+
+/// Largest radius that keeps a lake and its rim inside its region.
+const MAX_LAKE_RADIUS: f64 = (LAKE_REGION_SIZE / 2 - 2) as f64;
+
+const LAKE_SALT: u64 = 32;
+
+/// Lake settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LakeConfig {
+    /// Chance of a lake site per region. Sites outside depressions stay dry.
+    pub chance: f64,
+    pub min_radius: f64,
+    /// Largest radius; radii over `LAKE_REGION_SIZE / 2 - 2` are clamped
+    pub max_radius: f64,
+    /// Deepest point of the bowl dug under the water level
+    pub max_depth: i32,
+}
+
+impl Default for LakeConfig {
+    fn default() -> Self {
+        Self {
+            chance: 0.5,
+            min_radius: 6.0,
+            max_radius: 14.0,
+            max_depth: 4,
+        }
+    }
+}
+
+/// A place a lake may form.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct LakeSite {
+    pub x: f64,
+    pub z: f64,
+    pub radius: f64,
+    pub depth: i32,
+}
+
+impl LakeSite {
+    /// Columns sampled around the rim, just outside the lake.
+    pub fn rim(&self) -> [(i32, i32); LAKE_RIM_SAMPLES] {
+        let distance = self.radius + 1.0;
+        std::array::from_fn(|i| {
+            let angle = i as f64 / LAKE_RIM_SAMPLES as f64 * TAU;
+            (
+                (self.x + angle.cos() * distance).floor() as i32,
+                (self.z + angle.sin() * distance).floor() as i32,
+            )
+        })
+    }
+
+    /// Column the lake is centred on.
+    pub fn center(&self) -> (i32, i32) {
+        (self.x.floor() as i32, self.z.floor() as i32)
+    }
+
+    /// Whether the lake can cover any column in the inclusive bounds.
+    pub fn may_cover(&self, min_x: i32, min_z: i32, max_x: i32, max_z: i32) -> bool {
+        let reach = self.radius.ceil() as i32;
+        let (x, z) = self.center();
+        x + reach >= min_x && x - reach <= max_x && z + reach >= min_z && z - reach <= max_z
+    }
+}
+
+/// A lake with its water level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Lake {
+    pub site: LakeSite,
+    pub level: i32,
+}
+
+impl Lake {
+    /// Depth of the bowl under the water level at a column, or `None`
+    /// outside the lake.
+    pub fn bowl_depth(&self, x: i32, z: i32) -> Option<i32> {
+        let (dx, dz) = (x as f64 + 0.5 - self.site.x, z as f64 + 0.5 - self.site.z);
+        let falloff = 1.0 - (dx * dx + dz * dz) / (self.site.radius * self.site.radius);
+        (falloff > 0.0).then(|| (falloff * self.site.depth as f64).round() as i32)
+    }
+}
+
+/// The lake site of a region, if it has one.
+pub(crate) fn plan_lake_site(config: &LakeConfig, seed: u32, region_x: i32, region_z: i32) -> Option<LakeSite> {
+    let mut rng = FeatureRng::new(seed, IVec3::new(region_x, 0, region_z), LAKE_SALT);
+    if rng.next_f64() >= config.chance {
+        return None;
+    }
+
+    let radius = config.min_radius + rng.next_f64() * (config.max_radius - config.min_radius);
+    // Keep the lake inside its region so sites never overlap and are found
+    // by scanning the regions a column lies in
+    let radius = radius.min(MAX_LAKE_RADIUS);
+    let margin = radius.ceil() + 2.0;
+    let span = LAKE_REGION_SIZE as f64 - margin * 2.0;
+    Some(LakeSite {
+        x: (region_x * LAKE_REGION_SIZE) as f64 + margin + rng.next_f64() * span.max(0.0),
+        z: (region_z * LAKE_REGION_SIZE) as f64 + margin + rng.next_f64() * span.max(0.0),
+        radius,
+        depth: rng.range(1, config.max_depth.max(1)),
+    })
+}
+
+/// Lake sites that can cover a column in the inclusive bounds.
+pub(crate) fn lake_sites_near(config: &LakeConfig, seed: u32, min_x: i32, min_z: i32, max_x: i32, max_z: i32) -> Vec<LakeSite> {
+    let regions = |min: i32, max: i32| min.div_euclid(LAKE_REGION_SIZE)..=max.div_euclid(LAKE_REGION_SIZE);
+    let mut sites = Vec::new();
+    for region_x in regions(min_x, max_x) {
+        for region_z in regions(min_z, max_z) {
+            if let Some(site) = plan_lake_site(config, seed, region_x, region_z) {
+                if site.may_cover(min_x, min_z, max_x, max_z) {
+                    sites.push(site);
+                }
+            }
+        }
+    }
+    sites
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sites_inside_regions() {
+        // Radii too large for a region are clamped
+        for max_radius in [30.0, 40.0] {
+            let config = LakeConfig { chance: 1.0, max_radius, ..LakeConfig::default() };
+            for region_x in -10..10 {
+                let site = plan_lake_site(&config, 9, region_x, 4).unwrap();
+                assert_eq!(site, plan_lake_site(&config, 9, region_x, 4).unwrap());
+                assert!(site.radius <= MAX_LAKE_RADIUS);
+                for (x, z) in site.rim() {
+                    assert_eq!(x.div_euclid(LAKE_REGION_SIZE), region_x);
+                    assert_eq!(z.div_euclid(LAKE_REGION_SIZE), 4);
+                }
+
+                // Found from any column it covers
+                let (x, z) = site.center();
+                assert_eq!(lake_sites_near(&config, 9, x, z, x, z), vec![site]);
+                let edge = x - site.radius.floor() as i32 + 1;
+                assert!(site.may_cover(edge, z, edge, z));
+                assert_eq!(lake_sites_near(&config, 9, edge, z, edge, z), vec![site]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bowl_depth() {
+        let site = LakeSite { x: 0.5, z: 0.5, radius: 8.0, depth: 4 };
+        let lake = Lake { site, level: 70 };
+        assert_eq!(lake.bowl_depth(0, 0), Some(4));
+        assert_eq!(lake.bowl_depth(4, 0), Some(3));
+        assert_eq!(lake.bowl_depth(8, 0), None);
+        assert_eq!(lake.bowl_depth(-6, -6), None);
+    }
+}
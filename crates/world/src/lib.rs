@@ -1,8 +1,30 @@
 //! World data structures, chunk management, and terrain generation.
 
+pub mod background;
+pub mod caves;
 pub mod chunk;
+pub mod features;
 pub mod generation;
+pub mod lakes;
+pub mod region;
+pub mod section;
+pub mod storage;
+pub mod streaming;
+pub mod structure;
+pub mod terrain_noise;
+pub mod world_generator;
 
+pub use background::*;
+pub use caves::*;
 pub use chunk::*;
+pub use features::*;
 pub use generation::*;
+pub use lakes::*;
+pub use region::*;
+pub use section::*;
+pub use storage::*;
+pub use streaming::*;
+pub use structure::*;
+pub use terrain_noise::*;
+pub use world_generator::*;
 
@@ -0,0 +1,790 @@
+//! Region-file persistence for chunks.
+//!
+//! Chunks are grouped into regions of `REGION_SIZE x REGION_SIZE` chunks, one
+//! file per region (and per chunk layer in the cubic layout). Each file
+//! starts with an offset table holding the byte offset and length of every
+//! chunk slot (zero length = not stored), followed by the zlib-compressed
+//! chunk payloads.
+//!
+//! Chunks store registry block IDs, so the block name → ID table they were
+//! written with is kept next to the regions in [`BLOCK_TABLE_FILE`]. Open a
+//! world with [`open_world`] to bring its chunks in line with the current
+//! registry.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use voxel_core::{BlockId, BlockIdTable, BlockRegistry, IVec3, Palette, AIR_BLOCK, CHUNK_VOLUME};
+use crate::chunk::{Chunk, ChunkManager};
+use crate::section::{Section, SECTIONS_PER_CHUNK, SECTION_VOLUME};
+use crate::storage::PackedVoxels;
+
+/// Width and depth of a region in chunks.
+pub const REGION_SIZE: i32 = 32;
+
+/// File in a world directory holding the block ID table of its chunks.
+pub const BLOCK_TABLE_FILE: &str = "blocks.json";
+
+/// Block ID table of an unfinished remap, whose regions are waiting in
+/// `REMAPPED_EXTENSION` files. Its presence means every remapped region
+/// was written.
+const PENDING_TABLE_FILE: &str = "blocks.json.pending";
+const REMAPPED_EXTENSION: &str = "region.remap";
+
+const REGION_SLOTS: usize = (REGION_SIZE * REGION_SIZE) as usize;
+const HEADER_LEN: usize = REGION_SLOTS * 8;
+/// Version 4 records the section count (for cubic chunks), version 3 stores
+/// a palette per section, version 2 widened palette indices to 16 bits.
+/// Older payloads are still readable.
+const CHUNK_FORMAT_VERSION: u8 = 4;
+
+/// Region coordinates containing a chunk.
+///
+/// Regions are one chunk tall, so the region y is the chunk y (always 0 for
+/// column chunks).
+pub fn chunk_to_region(chunk_pos: IVec3) -> IVec3 {
+    IVec3::new(
+        chunk_pos.x.div_euclid(REGION_SIZE),
+        chunk_pos.y,
+        chunk_pos.z.div_euclid(REGION_SIZE),
+    )
+}
+
+/// Path of the region file for the given region coordinates.
+pub fn region_path(dir: &Path, region_pos: IVec3) -> PathBuf {
+    if region_pos.y == 0 {
+        dir.join(format!("r.{}.{}.region", region_pos.x, region_pos.z))
+    } else {
+        dir.join(format!("r.{}.{}.{}.region", region_pos.x, region_pos.y, region_pos.z))
+    }
+}
+
+fn slot_index(chunk_pos: IVec3) -> usize {
+    let x = chunk_pos.x.rem_euclid(REGION_SIZE);
+    let z = chunk_pos.z.rem_euclid(REGION_SIZE);
+    (z * REGION_SIZE + x) as usize
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Compressed chunk payloads of one region, indexed by slot.
+struct Region {
+    slots: Vec<Option<Vec<u8>>>,
+}
+
+impl Region {
+    fn empty() -> Self {
+        Self {
+            slots: vec![None; REGION_SLOTS],
+        }
+    }
+
+    /// Read a region file, or an empty region if the file doesn't exist.
+    fn read(path: &Path) -> io::Result<Self> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::empty()),
+            Err(err) => return Err(err),
+        };
+
+        if data.len() < HEADER_LEN {
+            return Err(invalid_data(format!("{}: truncated region header", path.display())));
+        }
+
+        let mut region = Self::empty();
+        for (slot, entry) in data[..HEADER_LEN].chunks_exact(8).enumerate() {
+            let offset = u32::from_le_bytes(entry[0..4].try_into().unwrap()) as usize;
+            let len = u32::from_le_bytes(entry[4..8].try_into().unwrap()) as usize;
+            if len == 0 {
+                continue;
+            }
+
+            let payload = offset.checked_add(len)
+                .and_then(|end| data.get(offset..end))
+                .ok_or_else(|| invalid_data(format!("{}: chunk slot {} out of bounds", path.display(), slot)))?;
+            region.slots[slot] = Some(payload.to_vec());
+        }
+
+        Ok(region)
+    }
+
+    /// Write the region to `path`, replacing the old file atomically.
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let tmp_path = path.with_extension("region.tmp");
+        self.write_new(&tmp_path)?;
+        fs::rename(tmp_path, path)
+    }
+
+    /// Write the region to a new file at `path` and flush it to disk.
+    fn write_new(&self, path: &Path) -> io::Result<()> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        let mut body = Vec::new();
+
+        for slot in &self.slots {
+            let (offset, len) = match slot {
+                Some(payload) => {
+                    let offset = HEADER_LEN + body.len();
+                    body.extend_from_slice(payload);
+                    (offset as u32, payload.len() as u32)
+                }
+                None => (0, 0),
+            };
+            header.extend_from_slice(&offset.to_le_bytes());
+            header.extend_from_slice(&len.to_le_bytes());
+        }
+
+        let mut file = fs::File::create(path)?;
+        file.write_all(&header)?;
+        file.write_all(&body)?;
+        file.sync_all()
+    }
+}
+
+/// Append a palette followed by one index per voxel, using one byte per
+/// index unless the palette needs wider ones.
+fn encode_indices(raw: &mut Vec<u8>, palette: &Palette, voxels: &PackedVoxels) {
+    for (_, block_id) in palette.iter() {
+        raw.extend_from_slice(&block_id.to_le_bytes());
+    }
+    
+    if palette.len() <= 256 {
+        raw.push(1);
+        raw.extend(voxels.iter().map(|palette_id| palette_id as u8));
+    } else {
+        raw.push(2);
+        for palette_id in voxels.iter() {
+            raw.extend_from_slice(&palette_id.to_le_bytes());
+        }
+    }
+}
+
+/// Serialize and compress a chunk.
+///
+/// Each section is stored with its own palette; all-air sections only take
+/// a zero palette length.
+fn encode_chunk(chunk: &Chunk) -> io::Result<Vec<u8>> {
+    let mut raw = Vec::with_capacity(CHUNK_VOLUME + 64);
+    raw.push(CHUNK_FORMAT_VERSION);
+    for coord in [chunk.position.x, chunk.position.y, chunk.position.z] {
+        raw.extend_from_slice(&coord.to_le_bytes());
+    }
+
+    raw.push(chunk.sections().len() as u8);
+    for section in chunk.sections() {
+        match section {
+            Some(section) => {
+                // A section uses at most `SECTION_VOLUME` entries, so only
+                // stale ones can push its palette length past 16 bits
+                let section = if section.palette.len() > u16::MAX as usize {
+                    let mut compacted = section.clone();
+                    compacted.compact();
+                    Cow::Owned(compacted)
+                } else {
+                    Cow::Borrowed(section)
+                };
+                raw.extend_from_slice(&(section.palette.len() as u16).to_le_bytes());
+                encode_indices(&mut raw, &section.palette, &section.voxels);
+            }
+            None => raw.extend_from_slice(&0u16.to_le_bytes()),
+        }
+    }
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&raw)?;
+    encoder.finish()
+}
+
+/// Cursor over decompressed chunk data.
+struct ByteReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(invalid_data("truncated chunk data"));
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Read `len` block IDs into a palette, checking that air comes first
+    /// and no entry repeats.
+    fn palette(&mut self, len: usize) -> io::Result<Palette> {
+        let mut palette = Palette::new();
+        for expected in 0..len {
+            let block_id = self.u16()?;
+            if expected == 0 && block_id != AIR_BLOCK {
+                return Err(invalid_data("palette must start with air"));
+            }
+            if palette.add_block(block_id) as usize != expected {
+                return Err(invalid_data("duplicate palette entry"));
+            }
+        }
+        Ok(palette)
+    }
+
+    /// Read `count` palette indices of `width` bytes each, checking that
+    /// they all refer to `palette`.
+    fn indices(&mut self, width: u8, count: usize, palette: &Palette) -> io::Result<Vec<u16>> {
+        let indices: Vec<u16> = match width {
+            1 => self.take(count)?.iter().map(|&palette_id| palette_id as u16).collect(),
+            2 => self.take(count * 2)?.chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .collect(),
+            _ => return Err(invalid_data(format!("unsupported voxel width {}", width))),
+        };
+        if indices.iter().any(|&palette_id| palette_id as usize >= palette.len()) {
+            return Err(invalid_data("voxel refers to missing palette entry"));
+        }
+        Ok(indices)
+    }
+}
+
+/// Decompress and deserialize a chunk.
+fn decode_chunk(payload: &[u8]) -> io::Result<Chunk> {
+    let mut raw = Vec::new();
+    ZlibDecoder::new(payload).read_to_end(&mut raw)?;
+    let mut reader = ByteReader { data: &raw };
+
+    let version = reader.u8()?;
+    if version == 0 || version > CHUNK_FORMAT_VERSION {
+        return Err(invalid_data(format!("unsupported chunk format version {}", version)));
+    }
+
+    let position = IVec3::new(reader.i32()?, reader.i32()?, reader.i32()?);
+
+    // Versions 1 and 2 store a single palette for the whole column
+    if version < 3 {
+        let palette_len = if version == 1 {
+            reader.u16()? as usize
+        } else {
+            reader.u32()? as usize
+        };
+        let palette = reader.palette(palette_len)?;
+        let width = if version == 1 { 1 } else { reader.u8()? };
+        let indices = reader.indices(width, CHUNK_VOLUME, &palette)?;
+        return Ok(Chunk::from_palette_indices(position, &palette, &indices));
+    }
+
+    let section_count = if version == 3 { SECTIONS_PER_CHUNK } else { reader.u8()? as usize };
+    if section_count != 1 && section_count != SECTIONS_PER_CHUNK {
+        return Err(invalid_data(format!("unsupported section count {}", section_count)));
+    }
+
+    let mut sections = Vec::with_capacity(section_count);
+    for _ in 0..section_count {
+        let palette_len = reader.u16()? as usize;
+        if palette_len == 0 {
+            sections.push(None);
+            continue;
+        }
+
+        let palette = reader.palette(palette_len)?;
+        let width = reader.u8()?;
+        let indices = reader.indices(width, SECTION_VOLUME, &palette)?;
+        sections.push(Some(Section {
+            palette,
+            voxels: PackedVoxels::from_slice(&indices),
+        }));
+    }
+
+    Ok(Chunk::from_sections(position, sections))
+}
+
+/// Write chunks to their region files, rewriting each touched region once.
+pub fn save_chunks<'a>(dir: &Path, chunks: impl IntoIterator<Item = &'a Chunk>) -> io::Result<usize> {
+    let mut by_region: HashMap<IVec3, Vec<&Chunk>> = HashMap::new();
+    for chunk in chunks {
+        by_region.entry(chunk_to_region(chunk.position)).or_default().push(chunk);
+    }
+
+    fs::create_dir_all(dir)?;
+
+    let mut saved = 0;
+    for (region_pos, chunks) in by_region {
+        let path = region_path(dir, region_pos);
+        let mut region = Region::read(&path)?;
+        for chunk in chunks {
+            region.slots[slot_index(chunk.position)] = Some(encode_chunk(chunk)?);
+            saved += 1;
+        }
+        region.write(&path)?;
+    }
+
+    Ok(saved)
+}
+
+/// Save every dirty chunk in `chunk_manager` and mark it clean, returning
+/// how many chunks were written. Palettes are compacted first.
+pub fn save_dirty(chunk_manager: &mut ChunkManager, dir: &Path) -> io::Result<usize> {
+    let dirty: Vec<IVec3> = chunk_manager.dirty_chunks()
+        .map(|chunk| chunk.position)
+        .collect();
+    for &chunk_pos in &dirty {
+        if let Some(chunk) = chunk_manager.get_chunk_mut(chunk_pos) {
+            chunk.compact();
+        }
+    }
+
+    let saved = save_chunks(dir, dirty.iter().filter_map(|&pos| chunk_manager.get_chunk(pos)))?;
+
+    for chunk_pos in dirty {
+        if let Some(chunk) = chunk_manager.get_chunk_mut(chunk_pos) {
+            chunk.mark_clean();
+        }
+    }
+
+    Ok(saved)
+}
+
+/// Load a chunk from its region file, or `None` if it was never saved.
+pub fn load_chunk(dir: &Path, chunk_pos: IVec3) -> io::Result<Option<Chunk>> {
+    let region = Region::read(&region_path(dir, chunk_to_region(chunk_pos)))?;
+    let Some(payload) = &region.slots[slot_index(chunk_pos)] else {
+        return Ok(None);
+    };
+
+    let chunk = decode_chunk(payload)?;
+    if chunk.position != chunk_pos {
+        return Err(invalid_data(format!(
+            "region slot for chunk {:?} holds chunk {:?}",
+            chunk_pos, chunk.position
+        )));
+    }
+    Ok(Some(chunk))
+}
+
+/// Write `registry`'s block ID table to the world directory.
+pub fn save_block_table(dir: &Path, registry: &BlockRegistry) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    write_block_table(&dir.join(BLOCK_TABLE_FILE), &registry.id_table())
+}
+
+/// Write a block ID table to `path`, replacing the old file atomically.
+fn write_block_table(path: &Path, table: &BlockIdTable) -> io::Result<()> {
+    let json = serde_json::to_vec_pretty(table).map_err(io::Error::from)?;
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+    }
+    fs::rename(tmp_path, path)
+}
+
+/// Read the block ID table of a world, or `None` if it has none.
+///
+/// Every entry must own at least one ID, stay within the ID space and not
+/// overlap another entry.
+pub fn load_block_table(dir: &Path) -> io::Result<Option<BlockIdTable>> {
+    let json = match fs::read(dir.join(BLOCK_TABLE_FILE)) {
+        Ok(json) => json,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let table: BlockIdTable = serde_json::from_slice(&json)
+        .map_err(|err| invalid_data(format!("{}: {}", BLOCK_TABLE_FILE, err)))?;
+
+    let mut ranges: Vec<(usize, usize, &str)> = Vec::with_capacity(table.blocks.len());
+    for entry in &table.blocks {
+        let start = entry.id as usize;
+        let end = start.saturating_add(entry.states);
+        if entry.states == 0 || end > BlockId::MAX as usize + 1 {
+            return Err(invalid_data(format!(
+                "{}: {} has {} states from ID {}",
+                BLOCK_TABLE_FILE, entry.name, entry.states, entry.id
+            )));
+        }
+        ranges.push((start, end, &entry.name));
+    }
+    ranges.sort_unstable();
+    for pair in ranges.windows(2) {
+        let ((_, end, name), (start, _, next)) = (pair[0], pair[1]);
+        if start < end {
+            return Err(invalid_data(format!("{}: IDs of {} and {} overlap", BLOCK_TABLE_FILE, name, next)));
+        }
+    }
+
+    Ok(Some(table))
+}
+
+/// Finish or roll back a remap interrupted by a crash. Once the pending
+/// table is written the remapped regions are moved into place; before that
+/// they're incomplete and dropped.
+fn finish_remap(dir: &Path) -> io::Result<()> {
+    let pending = dir.join(PENDING_TABLE_FILE);
+    let committed = pending.exists();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.to_str().is_some_and(|name| name.ends_with(REMAPPED_EXTENSION)) {
+            continue;
+        }
+        if committed {
+            fs::rename(&path, path.with_extension(""))?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    if committed {
+        fs::rename(pending, dir.join(BLOCK_TABLE_FILE))?;
+    }
+    Ok(())
+}
+
+/// Prepare a world directory for loading and saving chunks with
+/// `registry`, returning how many stored chunks had to be rewritten.
+///
+/// If the world was saved with other block IDs, every stored chunk is
+/// remapped to `registry`'s IDs, registering placeholders for blocks it
+/// doesn't define. A world without a table is taken to use `registry`'s IDs.
+///
+/// Remapped regions are written next to the old ones and only moved into
+/// place once all of them and the new table are on disk, so a crash leaves
+/// either the old world or one the next open finishes.
+pub fn open_world(dir: &Path, registry: &mut BlockRegistry) -> io::Result<usize> {
+    if dir.is_dir() {
+        finish_remap(dir)?;
+    }
+
+    let mut rewritten = 0;
+    if let Some(table) = load_block_table(dir)? {
+        let remap = registry.remap_from(&table)
+            .map_err(|err| invalid_data(format!("{}: {}", BLOCK_TABLE_FILE, err)))?;
+        if !remap.is_identity() {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.extension().is_none_or(|ext| ext != "region") {
+                    continue;
+                }
+
+                let mut region = Region::read(&path)?;
+                for payload in region.slots.iter_mut().flatten() {
+                    let mut chunk = decode_chunk(payload)?;
+                    chunk.remap_blocks(&remap);
+                    *payload = encode_chunk(&chunk)?;
+                    rewritten += 1;
+                }
+                region.write_new(&path.with_extension(REMAPPED_EXTENSION))?;
+            }
+
+            write_block_table(&dir.join(PENDING_TABLE_FILE), &registry.id_table())?;
+            return finish_remap(dir).map(|()| rewritten);
+        }
+    }
+
+    save_block_table(dir, registry)?;
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::{TerrainConfig, TerrainGenerator};
+    use crate::world_generator::WorldGenerator;
+
+    fn assert_same_blocks(a: &Chunk, b: &Chunk) {
+        assert_eq!(a.position, b.position);
+        assert_eq!(a.sections(), b.sections());
+    }
+
+    #[test]
+    fn test_region_coordinates() {
+        assert_eq!(chunk_to_region(IVec3::new(0, 0, 31)), IVec3::new(0, 0, 0));
+        assert_eq!(chunk_to_region(IVec3::new(32, 0, -1)), IVec3::new(1, 0, -1));
+        assert_eq!(chunk_to_region(IVec3::new(-33, 0, 5)), IVec3::new(-2, 0, 0));
+        assert_eq!(slot_index(IVec3::new(-1, 0, -1)), REGION_SLOTS - 1);
+
+        // Cubic chunk layers get their own regions
+        assert_eq!(chunk_to_region(IVec3::new(-33, -4, 5)), IVec3::new(-2, -4, 0));
+        assert_ne!(
+            region_path(Path::new("world"), IVec3::new(1, -4, 2)),
+            region_path(Path::new("world"), IVec3::new(1, 0, 2))
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let mut manager = ChunkManager::new();
+
+        let mut chunk = generator.generate_chunk(IVec3::new(-3, 0, 40));
+        chunk.set_block(IVec3::new(1, 200, 1), 4);
+        manager.insert_chunk(chunk);
+
+        assert_eq!(save_dirty(&mut manager, dir.path()).unwrap(), 1);
+        assert!(manager.dirty_chunks().next().is_none());
+        assert_eq!(save_dirty(&mut manager, dir.path()).unwrap(), 0);
+
+        let loaded = load_chunk(dir.path(), IVec3::new(-3, 0, 40)).unwrap().unwrap();
+        assert!(!loaded.is_dirty());
+        assert_same_blocks(&loaded, manager.get_chunk(IVec3::new(-3, 0, 40)).unwrap());
+        assert_eq!(loaded.get_block(IVec3::new(1, 200, 1)), 4);
+
+        assert!(load_chunk(dir.path(), IVec3::new(-2, 0, 40)).unwrap().is_none());
+        assert!(load_chunk(dir.path(), IVec3::new(500, 0, 500)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_partial_region_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = ChunkManager::new();
+
+        manager.set_block(IVec3::new(0, 10, 0), 1);
+        save_dirty(&mut manager, dir.path()).unwrap();
+
+        // A second chunk in the same region leaves the first one intact
+        manager.set_block(IVec3::new(20, 10, 0), 2);
+        assert_eq!(save_dirty(&mut manager, dir.path()).unwrap(), 1);
+
+        // Overwriting the first chunk leaves the second one intact
+        manager.set_block(IVec3::new(0, 11, 0), 3);
+        assert_eq!(save_dirty(&mut manager, dir.path()).unwrap(), 1);
+
+        let first = load_chunk(dir.path(), IVec3::ZERO).unwrap().unwrap();
+        assert_eq!(first.get_block(IVec3::new(0, 10, 0)), 1);
+        assert_eq!(first.get_block(IVec3::new(0, 11, 0)), 3);
+
+        let second = load_chunk(dir.path(), IVec3::new(1, 0, 0)).unwrap().unwrap();
+        assert_eq!(second.get_block(IVec3::new(4, 10, 0)), 2);
+
+        let files: Vec<_> = fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn test_corrupt_region_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(region_path(dir.path(), IVec3::ZERO), b"not a region").unwrap();
+
+        let err = load_chunk(dir.path(), IVec3::ZERO).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_round_trip_wide_palette() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut chunk = Chunk::new(IVec3::new(2, 0, -7));
+        // A thousand distinct blocks in one section need 16-bit indices
+        for block_id in 1..=1000u16 {
+            chunk.set_block(voxel_core::index_to_local(block_id as usize * 4).unwrap(), block_id);
+        }
+        assert_eq!(chunk.section(0).unwrap().palette.len(), 1001);
+
+        save_chunks(dir.path(), [&chunk]).unwrap();
+        let loaded = load_chunk(dir.path(), chunk.position).unwrap().unwrap();
+        assert_same_blocks(&loaded, &chunk);
+    }
+
+    #[test]
+    fn test_round_trip_full_palette() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut chunk = Chunk::new(IVec3::new(1, 0, 1));
+        // Every block ID passes through one voxel, filling the palette
+        for block_id in 1..=u16::MAX {
+            chunk.set_block(IVec3::new(3, 5, 3), block_id);
+        }
+        chunk.set_block(IVec3::new(4, 5, 3), 7);
+        assert_eq!(chunk.section(0).unwrap().palette.len(), u16::MAX as usize + 1);
+
+        save_chunks(dir.path(), [&chunk]).unwrap();
+        let loaded = load_chunk(dir.path(), chunk.position).unwrap().unwrap();
+        assert!(loaded.iter_blocks().eq(chunk.iter_blocks()));
+        assert_eq!(loaded.section(0).unwrap().palette.len(), 3);
+    }
+
+    #[test]
+    fn test_reads_version_1_payloads() {
+        let mut raw = vec![1u8];
+        for coord in [4i32, 0, 5] {
+            raw.extend_from_slice(&coord.to_le_bytes());
+        }
+        raw.extend_from_slice(&2u16.to_le_bytes());
+        raw.extend_from_slice(&AIR_BLOCK.to_le_bytes());
+        raw.extend_from_slice(&9u16.to_le_bytes());
+        let mut voxels = vec![0u8; CHUNK_VOLUME];
+        voxels[0] = 1;
+        raw.extend_from_slice(&voxels);
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw).unwrap();
+        let chunk = decode_chunk(&encoder.finish().unwrap()).unwrap();
+
+        assert_eq!(chunk.position, IVec3::new(4, 0, 5));
+        assert_eq!(chunk.get_block(IVec3::ZERO), 9);
+        assert_eq!(chunk.count_solid_blocks(), 1);
+    }
+
+    #[test]
+    fn test_round_trip_cubic_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = ChunkManager::with_layout(voxel_core::ChunkLayout::Cubic);
+        for y in [-40, -1, 0, 15, 16, 300] {
+            manager.set_block(IVec3::new(3, y, -5), 7);
+        }
+        assert_eq!(save_dirty(&mut manager, dir.path()).unwrap(), 5);
+
+        for chunk_pos in [IVec3::new(0, -3, -1), IVec3::new(0, -1, -1), IVec3::new(0, 0, -1), IVec3::new(0, 18, -1)] {
+            let loaded = load_chunk(dir.path(), chunk_pos).unwrap().unwrap();
+            assert_same_blocks(&loaded, manager.get_chunk(chunk_pos).unwrap());
+            assert_eq!(loaded.layout(), voxel_core::ChunkLayout::Cubic);
+        }
+        assert!(load_chunk(dir.path(), IVec3::new(0, 2, -1)).unwrap().is_none());
+        assert!(region_path(dir.path(), IVec3::new(0, -3, -1)).exists());
+    }
+
+    #[test]
+    fn test_open_world_remaps_blocks() {
+        use voxel_core::{BlockDef, BlockKind};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut saved = BlockRegistry::new();
+        let ore = saved.register(BlockDef::new("mymod:ore", BlockKind::Solid, 9));
+        let gem = saved.register(BlockDef::new("mymod:gem", BlockKind::Solid, 9));
+        assert_eq!(open_world(dir.path(), &mut saved).unwrap(), 0);
+
+        let mut manager = ChunkManager::new();
+        manager.set_block(IVec3::new(0, 10, 0), ore);
+        manager.set_block(IVec3::new(1, 10, 0), gem);
+        manager.set_block(IVec3::new(2, 10, 0), 1);
+        manager.set_block(IVec3::new(40, 10, 0), gem);
+        assert_eq!(save_dirty(&mut manager, dir.path()).unwrap(), 2);
+        assert_eq!(load_block_table(dir.path()).unwrap(), Some(saved.id_table()));
+
+        // Opening with the same registry leaves the chunks alone
+        assert_eq!(open_world(dir.path(), &mut saved.clone()).unwrap(), 0);
+
+        // Another registry defines the gem first and lacks the ore
+        let mut registry = BlockRegistry::new();
+        let new_gem = registry.register(BlockDef::new("mymod:gem", BlockKind::Solid, 9));
+        assert_ne!(new_gem, gem);
+        assert_eq!(open_world(dir.path(), &mut registry).unwrap(), 2);
+
+        let chunk = load_chunk(dir.path(), IVec3::ZERO).unwrap().unwrap();
+        let placeholder = chunk.get_block(IVec3::new(0, 10, 0));
+        assert!(registry.is_placeholder(placeholder));
+        assert_eq!(registry.get(placeholder).unwrap().name, "mymod:ore");
+        assert_eq!(chunk.get_block(IVec3::new(1, 10, 0)), new_gem);
+        assert_eq!(chunk.get_block(IVec3::new(2, 10, 0)), 1);
+        let far = load_chunk(dir.path(), IVec3::new(2, 0, 0)).unwrap().unwrap();
+        assert_eq!(far.get_block(IVec3::new(8, 10, 0)), new_gem);
+        assert_eq!(open_world(dir.path(), &mut registry).unwrap(), 0);
+
+        // Once the mod is back, the ore replaces its placeholder
+        let mut restored = BlockRegistry::new();
+        restored.register(BlockDef::new("mymod:gem", BlockKind::Solid, 9));
+        let restored_ore = restored.register(BlockDef::new("mymod:ore", BlockKind::Solid, 9));
+        open_world(dir.path(), &mut restored).unwrap();
+        let chunk = load_chunk(dir.path(), IVec3::ZERO).unwrap().unwrap();
+        assert_eq!(chunk.get_block(IVec3::new(0, 10, 0)), restored_ore);
+        assert!(!restored.is_placeholder(restored_ore));
+    }
+
+    #[test]
+    fn test_open_world_without_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = ChunkManager::new();
+        manager.set_block(IVec3::new(0, 10, 0), 3);
+        save_dirty(&mut manager, dir.path()).unwrap();
+        assert_eq!(load_block_table(dir.path()).unwrap(), None);
+
+        let mut registry = BlockRegistry::new();
+        assert_eq!(open_world(dir.path(), &mut registry).unwrap(), 0);
+        assert_eq!(load_block_table(dir.path()).unwrap(), Some(registry.id_table()));
+        assert_eq!(load_chunk(dir.path(), IVec3::ZERO).unwrap().unwrap().get_block(IVec3::new(0, 10, 0)), 3);
+
+        fs::write(dir.path().join(BLOCK_TABLE_FILE), "{").unwrap();
+        assert_eq!(load_block_table(dir.path()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Entries must own IDs inside the ID space without overlapping
+        for blocks in [
+            r#"[{"name":"core:air","id":0,"states":0}]"#,
+            r#"[{"name":"mymod:dial","id":65000,"states":600}]"#,
+            r#"[{"name":"core:air","id":0,"states":1},{"name":"mymod:dial","id":5,"states":1000000000000}]"#,
+            r#"[{"name":"mymod:dial","id":3,"states":4},{"name":"core:stone","id":6,"states":1}]"#,
+        ] {
+            fs::write(dir.path().join(BLOCK_TABLE_FILE), format!(r#"{{"blocks":{}}}"#, blocks)).unwrap();
+            assert_eq!(load_block_table(dir.path()).unwrap_err().kind(), io::ErrorKind::InvalidData, "{}", blocks);
+            assert_eq!(open_world(dir.path(), &mut registry).unwrap_err().kind(), io::ErrorKind::InvalidData, "{}", blocks);
+        }
+        fs::write(dir.path().join(BLOCK_TABLE_FILE), r#"{"blocks":[{"name":"mymod:dial","id":65280,"states":256}]}"#).unwrap();
+        assert!(load_block_table(dir.path()).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_open_world_interrupted_remap() {
+        use voxel_core::{BlockDef, BlockKind};
+
+        let mut saved = BlockRegistry::new();
+        saved.register(BlockDef::new("mymod:ore", BlockKind::Solid, 9));
+        let gem = saved.register(BlockDef::new("mymod:gem", BlockKind::Solid, 9));
+        let registry = || {
+            let mut registry = BlockRegistry::new();
+            let gem = registry.register(BlockDef::new("mymod:gem", BlockKind::Solid, 9));
+            (registry, gem)
+        };
+
+        let original = tempfile::tempdir().unwrap();
+        open_world(original.path(), &mut saved).unwrap();
+        let mut manager = ChunkManager::new();
+        manager.set_block(IVec3::new(0, 10, 0), gem);
+        save_dirty(&mut manager, original.path()).unwrap();
+        let region_name = region_path(original.path(), IVec3::ZERO).file_name().unwrap().to_owned();
+
+        let copy_world = |to: &Path| {
+            for name in [Path::new(BLOCK_TABLE_FILE), Path::new(&region_name)] {
+                fs::copy(original.path().join(name), to.join(name)).unwrap();
+            }
+        };
+        let remapped = tempfile::tempdir().unwrap();
+        copy_world(remapped.path());
+        let (mut current, new_gem) = registry();
+        assert_eq!(open_world(remapped.path(), &mut current).unwrap(), 1);
+        let remapped_region = remapped.path().join(&region_name);
+
+        // Crashed after the pending table was written: the remapped regions
+        // are moved into place without remapping again
+        let interrupted = tempfile::tempdir().unwrap();
+        copy_world(interrupted.path());
+        fs::copy(&remapped_region, interrupted.path().join(&region_name).with_extension(REMAPPED_EXTENSION)).unwrap();
+        fs::copy(remapped.path().join(BLOCK_TABLE_FILE), interrupted.path().join(PENDING_TABLE_FILE)).unwrap();
+        assert_eq!(open_world(interrupted.path(), &mut registry().0).unwrap(), 0);
+        assert!(!interrupted.path().join(PENDING_TABLE_FILE).exists());
+        let chunk = load_chunk(interrupted.path(), IVec3::ZERO).unwrap().unwrap();
+        assert_eq!(chunk.get_block(IVec3::new(0, 10, 0)), new_gem);
+
+        // Crashed while writing the remapped regions: the partial ones are
+        // dropped and the remap runs again
+        let partial = original.path().join(&region_name).with_extension(REMAPPED_EXTENSION);
+        fs::write(&partial, "partial").unwrap();
+        assert_eq!(open_world(original.path(), &mut registry().0).unwrap(), 1);
+        assert!(!partial.exists());
+        assert_eq!(fs::read(original.path().join(&region_name)).unwrap(), fs::read(&remapped_region).unwrap());
+    }
+}
@@ -0,0 +1,283 @@
+//! Vertical 16×16×16 sections of a chunk column.
+
+use voxel_core::{BlockId, BlockRemap, Palette, AIR_BLOCK, CHUNK_HEIGHT, CHUNK_SIZE};
+use serde::{Deserialize, Serialize};
+use crate::storage::PackedVoxels;
+
+/// Edge length of a cubic section in blocks.
+pub const SECTION_SIZE: i32 = CHUNK_SIZE;
+/// Number of voxels in one section.
+pub const SECTION_VOLUME: usize = (SECTION_SIZE * SECTION_SIZE * SECTION_SIZE) as usize;
+/// Number of sections stacked in a chunk column.
+pub const SECTIONS_PER_CHUNK: usize = (CHUNK_HEIGHT / SECTION_SIZE) as usize;
+
+/// A 16×16×16 slice of a chunk with its own palette.
+///
+/// Voxels are indexed like chunk voxels (`y * 256 + z * 16 + x`) with `y`
+/// relative to the bottom of the section, so section `i` of a chunk holds
+/// chunk indices `i * SECTION_VOLUME..(i + 1) * SECTION_VOLUME`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "SectionData")]
+pub struct Section {
+    /// Palette for block ID compression
+    pub palette: Palette,
+    /// Voxel data as bit-packed palette indices (run-length encoded when
+    /// serialized)
+    #[serde(with = "voxel_rle")]
+    pub voxels: PackedVoxels,
+}
+
+/// A deserialized section whose voxels haven't been checked against its
+/// palette yet.
+#[derive(Deserialize)]
+struct SectionData {
+    palette: Palette,
+    #[serde(with = "voxel_rle")]
+    voxels: PackedVoxels,
+}
+
+impl TryFrom<SectionData> for Section {
+    type Error = String;
+
+    fn try_from(data: SectionData) -> Result<Self, Self::Error> {
+        if data.voxels.iter().any(|palette_id| palette_id as usize >= data.palette.len()) {
+            return Err("voxel refers to missing palette entry".to_string());
+        }
+        Ok(Self {
+            palette: data.palette,
+            voxels: data.voxels,
+        })
+    }
+}
+
+/// Run-length encoding of voxel arrays as `(palette index, run length)` pairs.
+mod voxel_rle {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::SECTION_VOLUME;
+    use crate::storage::PackedVoxels;
+
+    pub fn serialize<S: Serializer>(voxels: &PackedVoxels, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut runs: Vec<(u16, u32)> = Vec::new();
+        for palette_id in voxels.iter() {
+            match runs.last_mut() {
+                Some((last, len)) if *last == palette_id => *len += 1,
+                _ => runs.push((palette_id, 1)),
+            }
+        }
+        runs.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PackedVoxels, D::Error> {
+        let runs = Vec::<(u16, u32)>::deserialize(deserializer)?;
+        let mut voxels = Vec::with_capacity(SECTION_VOLUME);
+        for (palette_id, len) in runs {
+            if voxels.len() + len as usize > SECTION_VOLUME {
+                return Err(D::Error::custom("voxel runs exceed section volume"));
+            }
+            voxels.resize(voxels.len() + len as usize, palette_id);
+        }
+        if voxels.len() != SECTION_VOLUME {
+            return Err(D::Error::custom(format!(
+                "voxel runs cover {} of {} voxels",
+                voxels.len(),
+                SECTION_VOLUME
+            )));
+        }
+        Ok(PackedVoxels::from_slice(&voxels))
+    }
+}
+
+impl Section {
+    /// Create a section filled with air.
+    pub fn new() -> Self {
+        Self {
+            palette: Palette::new(),
+            voxels: PackedVoxels::new(SECTION_VOLUME), // All air (palette index 0)
+        }
+    }
+
+    /// Create a section filled with a single block type.
+    pub fn filled(block_id: BlockId) -> Self {
+        let mut palette = Palette::new();
+        let palette_id = palette.add_block(block_id);
+        Self {
+            palette,
+            voxels: PackedVoxels::filled(SECTION_VOLUME, palette_id),
+        }
+    }
+
+    /// Build a section from indices into `palette`, keeping only the palette
+    /// entries it uses. Returns `None` if every voxel is air.
+    pub fn from_indices(palette: &Palette, indices: &[u16]) -> Option<Self> {
+        assert_eq!(indices.len(), SECTION_VOLUME, "section needs {} voxels", SECTION_VOLUME);
+
+        let mut section = Self::new();
+        let mut remap: Vec<Option<u16>> = vec![None; palette.len()];
+        remap[0] = Some(0);
+        let voxels: Vec<u16> = indices.iter()
+            .map(|&palette_id| {
+                *remap[palette_id as usize].get_or_insert_with(|| {
+                    section.palette.add_block(palette.get_block(palette_id))
+                })
+            })
+            .collect();
+        section.voxels = PackedVoxels::from_slice(&voxels);
+
+        (!section.is_empty()).then_some(section)
+    }
+
+    /// Get the block ID at a section voxel index.
+    pub fn get_block(&self, index: usize) -> BlockId {
+        self.palette.get_block(self.voxels.get(index))
+    }
+
+    /// Set the block ID at a section voxel index.
+    pub fn set_block(&mut self, index: usize, block_id: BlockId) {
+        let palette_id = self.palette.add_block(block_id);
+        self.voxels.set(index, palette_id);
+    }
+
+    /// Check if the section is entirely air.
+    pub fn is_empty(&self) -> bool {
+        self.count_solid_blocks() == 0
+    }
+
+    /// Get the number of non-air blocks in this section.
+    pub fn count_solid_blocks(&self) -> usize {
+        if self.palette.len() == 1 && self.palette.get_block(0) == AIR_BLOCK {
+            return 0;
+        }
+
+        self.voxels.iter()
+            .filter(|&palette_id| self.palette.get_block(palette_id) != AIR_BLOCK)
+            .count()
+    }
+
+    /// Drop palette entries no longer referenced by any voxel and remap the
+    /// voxel indices, returning how many entries were removed. Air always
+    /// stays at index 0.
+    pub fn compact(&mut self) -> usize {
+        let mut used = vec![false; self.palette.len()];
+        for palette_id in self.voxels.iter() {
+            used[palette_id as usize] = true;
+        }
+
+        let unused = used.iter().filter(|&&used| !used).count();
+        // Air at index 0 is kept even when nothing refers to it
+        if unused == 0 || (unused == 1 && !used[0]) {
+            return 0;
+        }
+
+        let mut palette = Palette::new();
+        let remap: Vec<u16> = self.palette.iter()
+            .map(|(palette_id, block_id)| {
+                if used[palette_id as usize] {
+                    palette.add_block(block_id)
+                } else {
+                    0
+                }
+            })
+            .collect();
+        let voxels: Vec<u16> = self.voxels.iter()
+            .map(|palette_id| remap[palette_id as usize])
+            .collect();
+
+        let removed = self.palette.len() - palette.len();
+        self.palette = palette;
+        self.voxels = PackedVoxels::from_slice(&voxels);
+        removed
+    }
+
+    /// Replace every block ID with its remapped ID, merging palette entries
+    /// that end up the same. Voxels are only rewritten when that happens.
+    pub fn remap_blocks(&mut self, remap: &BlockRemap) {
+        let mut palette = Palette::new();
+        let indices: Vec<u16> = self.palette.iter()
+            .map(|(_, block_id)| palette.add_block(remap.get(block_id)))
+            .collect();
+        
+        if indices.iter().enumerate().any(|(old, &new)| old != new as usize) {
+            let voxels: Vec<u16> = self.voxels.iter()
+                .map(|palette_id| indices[palette_id as usize])
+                .collect();
+            self.voxels = PackedVoxels::from_slice(&voxels);
+        }
+        self.palette = palette;
+    }
+
+    /// Heap memory used by the packed voxels, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.voxels.memory_usage()
+    }
+}
+
+impl Default for Section {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_section_constants() {
+        assert_eq!(SECTION_VOLUME, 4096);
+        assert_eq!(SECTIONS_PER_CHUNK * SECTION_VOLUME, voxel_core::CHUNK_VOLUME);
+    }
+
+    #[test]
+    fn test_from_indices_keeps_used_entries() {
+        let mut palette = Palette::new();
+        for block_id in [5, 6, 7] {
+            palette.add_block(block_id);
+        }
+
+        assert!(Section::from_indices(&palette, &[0; SECTION_VOLUME]).is_none());
+
+        let mut indices = vec![0u16; SECTION_VOLUME];
+        indices[10] = 3;
+        indices[11] = 1;
+        let section = Section::from_indices(&palette, &indices).unwrap();
+        assert_eq!(section.palette.iter().map(|(_, block_id)| block_id).collect::<Vec<_>>(), vec![AIR_BLOCK, 7, 5]);
+        assert_eq!(section.get_block(10), 7);
+        assert_eq!(section.get_block(11), 5);
+        assert_eq!(section.count_solid_blocks(), 2);
+    }
+
+    #[test]
+    fn test_remap_blocks() {
+        let mut saved = voxel_core::BlockRegistry::new();
+        let ore = saved.register(voxel_core::BlockDef::new("mymod:ore", voxel_core::BlockKind::Solid, 9));
+        let mut registry = voxel_core::BlockRegistry::new();
+        registry.register(voxel_core::BlockDef::new("mymod:gem", voxel_core::BlockKind::Solid, 9));
+        let remap = registry.remap_from(&saved.id_table()).unwrap();
+
+        let mut section = Section::filled(1);
+        section.set_block(5, ore);
+        section.set_block(6, 500); // Not in the table
+        section.remap_blocks(&remap);
+        assert_eq!(section.get_block(0), 1);
+        assert!(registry.is_placeholder(section.get_block(5)));
+        assert_eq!(section.get_block(6), AIR_BLOCK);
+        assert_eq!(section.count_solid_blocks(), SECTION_VOLUME - 1);
+    }
+
+    #[test]
+    fn test_section_serde() {
+        let mut section = Section::filled(3);
+        section.set_block(100, AIR_BLOCK);
+
+        let json = serde_json::to_string(&section).unwrap();
+        assert_eq!(serde_json::from_str::<Section>(&json).unwrap(), section);
+
+        let short = r#"{"palette":[0],"voxels":[[0,4095]]}"#;
+        assert!(serde_json::from_str::<Section>(short).is_err());
+        let valid = r#"{"palette":[0,3],"voxels":[[1,4095],[0,1]]}"#;
+        assert_eq!(serde_json::from_str::<Section>(valid).unwrap().count_solid_blocks(), 4095);
+        let missing_entry = r#"{"palette":[0,3],"voxels":[[1,4095],[2,1]]}"#;
+        assert!(serde_json::from_str::<Section>(missing_entry).is_err());
+    }
+}
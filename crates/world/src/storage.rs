@@ -0,0 +1,218 @@
+//! Bit-packed storage for palette indices.
+
+/// Fixed-length array of palette indices packed into `u64` words.
+///
+/// The bit width grows with the largest stored index (0, 1, 2, 4, 8 or 16
+/// bits per value) and the array is repacked transparently when a wider value is
+/// written. With 0 bits every entry holds the same value and no words are
+/// allocated.
+#[derive(Debug, Clone)]
+pub struct PackedVoxels {
+    len: usize,
+    bits: u8,
+    /// The value of every entry while `bits == 0`
+    uniform: u16,
+    words: Vec<u64>,
+}
+
+/// Smallest supported bit width that can hold `max_value`.
+fn bits_for(max_value: u16) -> u8 {
+    match max_value {
+        0..=1 => 1,
+        2..=3 => 2,
+        4..=15 => 4,
+        16..=255 => 8,
+        _ => 16,
+    }
+}
+
+impl PackedVoxels {
+    /// Create an array of `len` zeros.
+    pub fn new(len: usize) -> Self {
+        Self::filled(len, 0)
+    }
+
+    /// Create an array of `len` copies of `value`.
+    pub fn filled(len: usize, value: u16) -> Self {
+        Self {
+            len,
+            bits: 0,
+            uniform: value,
+            words: Vec::new(),
+        }
+    }
+
+    /// Pack a slice of values using the narrowest width that fits them.
+    pub fn from_slice(values: &[u16]) -> Self {
+        let Some(&first) = values.first() else {
+            return Self::new(0);
+        };
+        if values.iter().all(|&value| value == first) {
+            return Self::filled(values.len(), first);
+        }
+
+        let max = values.iter().copied().max().unwrap_or(0);
+        let mut packed = Self::filled(values.len(), 0);
+        packed.pack(bits_for(max), values.iter().copied());
+        packed
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Current number of bits stored per value.
+    pub fn bits_per_value(&self) -> u8 {
+        self.bits
+    }
+
+    /// Heap memory used by the packed words, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.words.len() * std::mem::size_of::<u64>()
+    }
+
+    /// Get the value at `index`. Panics if out of bounds.
+    pub fn get(&self, index: usize) -> u16 {
+        assert!(index < self.len, "index {} out of bounds for {} voxels", index, self.len);
+        if self.bits == 0 {
+            return self.uniform;
+        }
+
+        let per_word = 64 / self.bits as usize;
+        let shift = (index % per_word) * self.bits as usize;
+        let mask = (1u64 << self.bits) - 1;
+        ((self.words[index / per_word] >> shift) & mask) as u16
+    }
+
+    /// Set the value at `index`, widening the storage if needed. Panics if
+    /// out of bounds.
+    pub fn set(&mut self, index: usize, value: u16) {
+        assert!(index < self.len, "index {} out of bounds for {} voxels", index, self.len);
+        if self.bits == 0 {
+            if value == self.uniform {
+                return;
+            }
+            self.repack(bits_for(value.max(self.uniform)));
+        } else if bits_for(value) > self.bits {
+            self.repack(bits_for(value));
+        }
+
+        let per_word = 64 / self.bits as usize;
+        let shift = (index % per_word) * self.bits as usize;
+        let mask = (1u64 << self.bits) - 1;
+        let word = &mut self.words[index / per_word];
+        *word = (*word & !(mask << shift)) | ((value as u64) << shift);
+    }
+
+    /// Set every value to `value`, releasing the packed words.
+    pub fn fill(&mut self, value: u16) {
+        *self = Self::filled(self.len, value);
+    }
+
+    /// Iterate over all values in index order.
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..self.len).map(move |index| self.get(index))
+    }
+
+    /// Unpack into one `u16` per value.
+    pub fn to_vec(&self) -> Vec<u16> {
+        self.iter().collect()
+    }
+
+    fn repack(&mut self, bits: u8) {
+        let values = self.to_vec();
+        self.pack(bits, values.into_iter());
+    }
+
+    fn pack(&mut self, bits: u8, values: impl Iterator<Item = u16>) {
+        let per_word = 64 / bits as usize;
+        let mut words = vec![0u64; self.len.div_ceil(per_word)];
+        for (index, value) in values.enumerate() {
+            words[index / per_word] |= (value as u64) << ((index % per_word) * bits as usize);
+        }
+
+        self.bits = bits;
+        self.words = words;
+    }
+}
+
+impl PartialEq for PackedVoxels {
+    /// Arrays are equal if they hold the same values, whatever their width.
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for PackedVoxels {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bit_width_growth() {
+        let mut packed = PackedVoxels::new(100);
+        assert_eq!(packed.bits_per_value(), 0);
+        assert_eq!(packed.memory_usage(), 0);
+
+        packed.set(5, 0); // Same as the uniform value
+        assert_eq!(packed.bits_per_value(), 0);
+
+        packed.set(5, 1);
+        assert_eq!(packed.bits_per_value(), 1);
+        packed.set(6, 3);
+        assert_eq!(packed.bits_per_value(), 2);
+        packed.set(7, 9);
+        assert_eq!(packed.bits_per_value(), 4);
+        packed.set(99, 200);
+        assert_eq!(packed.bits_per_value(), 8);
+        packed.set(98, 1000);
+        assert_eq!(packed.bits_per_value(), 16);
+
+        assert_eq!(packed.get(5), 1);
+        assert_eq!(packed.get(6), 3);
+        assert_eq!(packed.get(7), 9);
+        assert_eq!(packed.get(99), 200);
+        assert_eq!(packed.get(98), 1000);
+        assert_eq!(packed.iter().filter(|&value| value == 0).count(), 95);
+    }
+
+    #[test]
+    fn test_uniform_non_zero() {
+        let mut packed = PackedVoxels::filled(64, 7);
+        assert_eq!(packed.get(63), 7);
+
+        packed.set(0, 0);
+        assert_eq!(packed.bits_per_value(), 4);
+        assert_eq!(packed.get(0), 0);
+        assert_eq!(packed.get(1), 7);
+
+        packed.fill(2);
+        assert_eq!(packed.bits_per_value(), 0);
+        assert_eq!(packed.to_vec(), vec![2; 64]);
+    }
+
+    #[test]
+    fn test_from_slice_round_trip() {
+        let values: Vec<u16> = (0..1000).map(|i| (i * 7 % 13) as u16).collect();
+        let packed = PackedVoxels::from_slice(&values);
+        assert_eq!(packed.bits_per_value(), 4);
+        assert_eq!(packed.to_vec(), values);
+
+        assert_eq!(PackedVoxels::from_slice(&[3, 3, 3]), PackedVoxels::filled(3, 3));
+        assert!(PackedVoxels::from_slice(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_equality_ignores_width() {
+        let mut wide = PackedVoxels::new(10);
+        wide.set(0, 200);
+        wide.set(0, 0);
+        assert_eq!(wide.bits_per_value(), 8);
+        assert_eq!(wide, PackedVoxels::new(10));
+    }
+}
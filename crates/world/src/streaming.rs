@@ -0,0 +1,311 @@
+//! Viewer-centered chunk streaming with a per-tick generation budget.
+
+use std::collections::VecDeque;
+
+use voxel_core::{world_to_chunk, IVec3};
+use crate::background::BackgroundGenerator;
+use crate::chunk::ChunkManager;
+use crate::world_generator::WorldGenerator;
+
+/// Chunk streaming configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamingConfig {
+    /// Radius in chunks that is kept loaded around the viewer
+    pub load_radius: i32,
+    /// Radius in chunks beyond which chunks are unloaded (>= `load_radius`)
+    pub unload_radius: i32,
+    /// Maximum number of chunks generated per tick
+    pub max_generated_per_tick: usize,
+}
+
+impl Default for StreamingConfig {
+    fn default() -> Self {
+        Self {
+            load_radius: 8,
+            unload_radius: 10,
+            max_generated_per_tick: 4,
+        }
+    }
+}
+
+/// Streaming statistics for debug display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamingStats {
+    /// Chunks still waiting to be generated
+    pub queued: usize,
+    /// Chunks being generated in the background
+    pub in_flight: usize,
+    /// Chunks generated during the last tick
+    pub generated: usize,
+    /// Chunks unloaded during the last tick
+    pub unloaded: usize,
+    /// Chunks generated since the streamer was created
+    pub total_generated: usize,
+    /// Chunks unloaded since the streamer was created
+    pub total_unloaded: usize,
+}
+
+/// Loads missing chunks nearest-first around a moving viewer and unloads
+/// chunks that fall outside the hysteresis radius.
+#[derive(Debug, Clone)]
+pub struct ChunkStreamer {
+    config: StreamingConfig,
+    center: Option<IVec3>,
+    queue: VecDeque<IVec3>,
+    stats: StreamingStats,
+}
+
+impl ChunkStreamer {
+    pub fn new(mut config: StreamingConfig) -> Self {
+        config.load_radius = config.load_radius.max(0);
+        config.unload_radius = config.unload_radius.max(config.load_radius);
+
+        Self {
+            config,
+            center: None,
+            queue: VecDeque::new(),
+            stats: StreamingStats::default(),
+        }
+    }
+
+    /// Advance streaming by one tick for a viewer at `viewer_pos` (world
+    /// block coordinates), generating chunks synchronously.
+    pub fn update(
+        &mut self,
+        viewer_pos: IVec3,
+        generator: &dyn WorldGenerator,
+        chunk_manager: &mut ChunkManager,
+    ) -> StreamingStats {
+        let unloaded = self.begin_tick(viewer_pos, chunk_manager);
+
+        let mut generated = 0;
+        while generated < self.config.max_generated_per_tick {
+            let Some(chunk_pos) = self.queue.pop_front() else {
+                break;
+            };
+            if chunk_manager.get_chunk(chunk_pos).is_some() {
+                continue;
+            }
+            chunk_manager.insert_chunk(generator.generate_chunk(chunk_pos));
+            generated += 1;
+        }
+
+        self.finish_tick(generated, unloaded, 0)
+    }
+
+    /// Advance streaming by one tick, handing generation to `background`.
+    ///
+    /// Up to `max_generated_per_tick` new jobs are started per tick; finished
+    /// chunks are inserted as they arrive and jobs for chunks that left the
+    /// load radius are cancelled.
+    pub fn update_background(
+        &mut self,
+        viewer_pos: IVec3,
+        background: &mut BackgroundGenerator,
+        chunk_manager: &mut ChunkManager,
+    ) -> StreamingStats {
+        let unloaded = self.begin_tick(viewer_pos, chunk_manager);
+        if let Some(center) = self.center {
+            background.cancel_outside(center, self.config.load_radius);
+        }
+
+        let generated = background.poll(chunk_manager);
+
+        let mut started = 0;
+        while started < self.config.max_generated_per_tick {
+            let Some(chunk_pos) = self.queue.pop_front() else {
+                break;
+            };
+            if chunk_manager.get_chunk(chunk_pos).is_some() || !background.request(chunk_pos) {
+                continue;
+            }
+            started += 1;
+        }
+
+        self.finish_tick(generated, unloaded, background.pending_count())
+    }
+
+    /// Force the load queue to be rebuilt on the next update, e.g. after
+    /// chunks inside the load radius were removed externally.
+    pub fn invalidate(&mut self) {
+        self.center = None;
+    }
+
+    /// Check if no chunks within the load radius are waiting to be started.
+    pub fn is_idle(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn config(&self) -> &StreamingConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> StreamingStats {
+        self.stats
+    }
+
+    /// Recenter on the viewer and unload distant chunks, returning how many
+    /// were unloaded.
+    fn begin_tick(&mut self, viewer_pos: IVec3, chunk_manager: &mut ChunkManager) -> usize {
+        let center = world_to_chunk(viewer_pos);
+        if self.center != Some(center) {
+            self.center = Some(center);
+            self.rebuild_queue(center, chunk_manager);
+        }
+
+        let before = chunk_manager.chunk_count();
+        chunk_manager.unload_distant_chunks(center, self.config.unload_radius);
+        before - chunk_manager.chunk_count()
+    }
+
+    fn finish_tick(&mut self, generated: usize, unloaded: usize, in_flight: usize) -> StreamingStats {
+        self.stats.queued = self.queue.len();
+        self.stats.in_flight = in_flight;
+        self.stats.generated = generated;
+        self.stats.unloaded = unloaded;
+        self.stats.total_generated += generated;
+        self.stats.total_unloaded += unloaded;
+        self.stats
+    }
+
+    fn rebuild_queue(&mut self, center: IVec3, chunk_manager: &ChunkManager) {
+        self.queue = chunks_nearest_first(center, self.config.load_radius)
+            .into_iter()
+            .filter(|&chunk_pos| chunk_manager.get_chunk(chunk_pos).is_none())
+            .collect();
+    }
+}
+
+impl Default for ChunkStreamer {
+    fn default() -> Self {
+        Self::new(StreamingConfig::default())
+    }
+}
+
+/// All chunk positions within `radius` of `center` (horizontal distance),
+/// ordered nearest-first.
+pub fn chunks_nearest_first(center: IVec3, radius: i32) -> Vec<IVec3> {
+    let radius_sq = radius * radius;
+    let mut offsets = Vec::new();
+
+    for x in -radius..=radius {
+        for z in -radius..=radius {
+            let distance_sq = x * x + z * z;
+            if distance_sq <= radius_sq {
+                offsets.push((distance_sq, x, z));
+            }
+        }
+    }
+
+    offsets.sort_unstable();
+    offsets.into_iter()
+        .map(|(_, x, z)| IVec3::new(center.x + x, 0, center.z + z))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::{TerrainConfig, TerrainGenerator};
+    use std::sync::Arc;
+    use voxel_core::CHUNK_SIZE;
+
+    fn streamer(load_radius: i32, unload_radius: i32, budget: usize) -> ChunkStreamer {
+        ChunkStreamer::new(StreamingConfig {
+            load_radius,
+            unload_radius,
+            max_generated_per_tick: budget,
+        })
+    }
+
+    #[test]
+    fn test_nearest_first_order() {
+        let center = IVec3::new(3, 0, -2);
+        let order = chunks_nearest_first(center, 2);
+
+        assert_eq!(order[0], center);
+        assert_eq!(order.len(), 13); // Disc of radius 2
+
+        let distances: Vec<i32> = order.iter()
+            .map(|&pos| (pos - center).length_squared())
+            .collect();
+        assert!(distances.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_generation_budget() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let mut manager = ChunkManager::new();
+        let mut streamer = streamer(1, 2, 2);
+
+        let stats = streamer.update(IVec3::ZERO, &generator, &mut manager);
+        assert_eq!(stats.generated, 2);
+        assert_eq!(stats.queued, 3);
+        assert_eq!(manager.chunk_count(), 2);
+        assert!(manager.get_chunk(IVec3::ZERO).is_some()); // Nearest first
+
+        streamer.update(IVec3::ZERO, &generator, &mut manager);
+        let stats = streamer.update(IVec3::ZERO, &generator, &mut manager);
+        assert_eq!(stats.generated, 1);
+        assert_eq!(stats.queued, 0);
+        assert_eq!(stats.total_generated, 5);
+        assert!(streamer.is_idle());
+
+        let stats = streamer.update(IVec3::ZERO, &generator, &mut manager);
+        assert_eq!(stats.generated, 0);
+        assert_eq!(manager.chunk_count(), 5);
+    }
+
+    #[test]
+    fn test_unload_hysteresis() {
+        let generator = TerrainGenerator::new(TerrainConfig::default());
+        let mut manager = ChunkManager::new();
+        let mut streamer = streamer(0, 1, 8);
+
+        streamer.update(IVec3::ZERO, &generator, &mut manager);
+        assert_eq!(manager.chunk_count(), 1);
+
+        // Moving one chunk over stays within the unload radius
+        let stats = streamer.update(IVec3::new(CHUNK_SIZE, 0, 0), &generator, &mut manager);
+        assert_eq!(stats.unloaded, 0);
+        assert_eq!(stats.generated, 1);
+        assert_eq!(manager.chunk_count(), 2);
+
+        // Moving further drops chunks beyond the unload radius
+        let stats = streamer.update(IVec3::new(3 * CHUNK_SIZE, 0, 0), &generator, &mut manager);
+        assert_eq!(stats.unloaded, 2);
+        assert_eq!(stats.total_unloaded, 2);
+        assert_eq!(manager.chunk_count(), 1);
+        assert!(manager.get_chunk(IVec3::new(3, 0, 0)).is_some());
+    }
+
+    #[test]
+    fn test_unload_radius_never_below_load_radius() {
+        let streamer = streamer(4, 2, 1);
+        assert_eq!(streamer.config().unload_radius, 4);
+    }
+
+    #[test]
+    fn test_background_streaming() {
+        let generator = Arc::new(TerrainGenerator::new(TerrainConfig::default()));
+        let mut background = BackgroundGenerator::new(generator);
+        let mut manager = ChunkManager::new();
+        let mut streamer = streamer(1, 1, 3);
+
+        let stats = streamer.update_background(IVec3::ZERO, &mut background, &mut manager);
+        assert_eq!(stats.in_flight, 3);
+        assert_eq!(stats.queued, 2);
+
+        streamer.update_background(IVec3::ZERO, &mut background, &mut manager);
+        assert!(streamer.is_idle());
+        background.finish(&mut manager);
+        assert_eq!(manager.chunk_count(), 5);
+
+        // Jobs for chunks left behind are cancelled rather than delivered
+        streamer.update_background(IVec3::new(10 * CHUNK_SIZE, 0, 0), &mut background, &mut manager);
+        assert_eq!(manager.chunk_count(), 0);
+        streamer.update_background(IVec3::new(20 * CHUNK_SIZE, 0, 0), &mut background, &mut manager);
+        background.finish(&mut manager);
+        assert!(manager.loaded_chunks().all(|pos| (pos - IVec3::new(20, 0, 0)).length_squared() <= 1));
+    }
+}
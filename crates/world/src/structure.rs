@@ -0,0 +1,690 @@
+//! Structure templates stamped into the world: villages, dungeons, ruins.
+//!
+//! A `Structure` is a block array with a palette, loaded from a file or
+//! built in code. A `Placement` puts it at a world position, optionally
+//! rotated and mirrored around its origin. Placements can be written into
+//! a `ChunkManager` directly, or picked by a `StructureRule` during
+//! generation: the world is split into a grid of `spacing` chunks and each
+//! grid cell gets at most one structure, planned from the seed and the cell
+//! alone so chunks generated in any order agree on it.
+//!
+//! A structure file lists layers from the bottom up, rows along +z and
+//! characters along +x, e.g. in RON:
+//!
+//! ```ron
+//! (
+//!     palette: { '#': "stone", 'L': "oak_log[axis=y]", 'a': "air" },
+//!     layers: [
+//!         ["###", "###", "###"],
+//!         ["#a#", "aLa", "#a#"],
+//!     ],
+//!     origin: (x: 1, y: 0, z: 1),
+//!     placement: (spacing: 12, separation: 4),
+//! )
+//! ```
+//!
+//! Spaces and `.` leave the world block unchanged. Block states aren't
+//! rotated with the structure.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use voxel_core::{load_config, BlockId, BlockRegistry, ConfigError, IVec3, CHUNK_SIZE};
+use crate::chunk::ChunkManager;
+use crate::features::FeatureRng;
+use serde::{Deserialize, Serialize};
+
+/// Layer characters that leave the world block unchanged.
+const VOID_KEYS: [char; 2] = [' ', '.'];
+
+/// A rotation around the vertical axis, clockwise seen from above.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::None,
+        Rotation::Clockwise90,
+        Rotation::Clockwise180,
+        Rotation::Clockwise270,
+    ];
+
+    /// Rotate a horizontal offset. A quarter turn takes +x to +z.
+    pub fn apply(self, x: i32, z: i32) -> (i32, i32) {
+        match self {
+            Rotation::None => (x, z),
+            Rotation::Clockwise90 => (-z, x),
+            Rotation::Clockwise180 => (-x, -z),
+            Rotation::Clockwise270 => (z, -x),
+        }
+    }
+
+    /// The rotation undoing this one.
+    pub fn inverse(self) -> Self {
+        match self {
+            Rotation::Clockwise90 => Rotation::Clockwise270,
+            Rotation::Clockwise270 => Rotation::Clockwise90,
+            rotation => rotation,
+        }
+    }
+}
+
+/// Where and how a structure is placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Placement {
+    /// World position of the structure's origin
+    pub position: IVec3,
+    pub rotation: Rotation,
+    /// Flip along x before rotating
+    pub mirror: bool,
+}
+
+impl Placement {
+    pub fn new(position: IVec3) -> Self {
+        Self {
+            position,
+            rotation: Rotation::None,
+            mirror: false,
+        }
+    }
+
+    pub fn rotated(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn mirrored(mut self) -> Self {
+        self.mirror = !self.mirror;
+        self
+    }
+}
+
+/// A template of blocks. Void positions keep whatever the world has there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Structure {
+    size: IVec3,
+    /// Local position placed at `Placement::position`
+    origin: IVec3,
+    /// Block of each palette index; index 0 is void
+    palette: Vec<Option<BlockId>>,
+    /// Palette indices, x fastest, then z, then y
+    blocks: Vec<u16>,
+}
+
+impl Structure {
+    /// An all-void structure with its origin at the low corner.
+    pub fn new(size: IVec3) -> Self {
+        assert!(size.x > 0 && size.y > 0 && size.z > 0, "structure size must be positive");
+        Self {
+            size,
+            origin: IVec3::ZERO,
+            palette: vec![None],
+            blocks: vec![0; (size.x * size.y * size.z) as usize],
+        }
+    }
+
+    pub fn with_origin(mut self, origin: IVec3) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn size(&self) -> IVec3 {
+        self.size
+    }
+
+    pub fn origin(&self) -> IVec3 {
+        self.origin
+    }
+
+    fn index(&self, local_pos: IVec3) -> Option<usize> {
+        if local_pos.clamp(IVec3::ZERO, self.size - IVec3::new(1, 1, 1)) != local_pos {
+            return None;
+        }
+        Some(((local_pos.y * self.size.z + local_pos.z) * self.size.x + local_pos.x) as usize)
+    }
+
+    /// Block at a local position, or `None` for void and positions outside.
+    pub fn get(&self, local_pos: IVec3) -> Option<BlockId> {
+        self.index(local_pos).and_then(|index| self.palette[self.blocks[index] as usize])
+    }
+
+    /// Set a local position; `None` makes it void. Positions outside are
+    /// ignored.
+    pub fn set(&mut self, local_pos: IVec3, block: Option<BlockId>) {
+        let Some(index) = self.index(local_pos) else {
+            return;
+        };
+        let palette_id = match self.palette.iter().position(|&entry| entry == block) {
+            Some(palette_id) => palette_id,
+            None => {
+                self.palette.push(block);
+                self.palette.len() - 1
+            }
+        };
+        self.blocks[index] = palette_id as u16;
+    }
+
+    /// Build a structure from a parsed file. `path` only names the file in
+    /// errors.
+    pub fn from_file(file: &StructureFile, path: &Path, registry: &BlockRegistry) -> Result<Self, StructureError> {
+        let shape_error = |message: String| StructureError::Shape { path: path.to_path_buf(), message };
+
+        let mut keys = BTreeMap::new();
+        for (&key, name) in &file.palette {
+            match resolve_block(registry, name) {
+                Some(block) => keys.insert(key, block),
+                None => return Err(StructureError::UnknownBlock { path: path.to_path_buf(), block: name.clone() }),
+            };
+        }
+
+        let depth = file.layers.first().map_or(0, Vec::len);
+        let width = file.layers.first().and_then(|layer| layer.first()).map_or(0, |row| row.chars().count());
+        if depth == 0 || width == 0 {
+            return Err(shape_error("structure has no blocks".to_string()));
+        }
+
+        let size = IVec3::new(width as i32, file.layers.len() as i32, depth as i32);
+        let mut structure = Structure::new(size).with_origin(file.origin);
+        for (y, layer) in file.layers.iter().enumerate() {
+            if layer.len() != depth {
+                return Err(shape_error(format!("layer {} has {} rows but layer 0 has {}", y, layer.len(), depth)));
+            }
+            for (z, row) in layer.iter().enumerate() {
+                if row.chars().count() != width {
+                    return Err(shape_error(format!("row {} of layer {} isn't {} blocks wide", z, y, width)));
+                }
+                for (x, key) in row.chars().enumerate() {
+                    if VOID_KEYS.contains(&key) {
+                        continue;
+                    }
+                    let Some(&block) = keys.get(&key) else {
+                        return Err(StructureError::UnknownKey { path: path.to_path_buf(), key });
+                    };
+                    structure.set(IVec3::new(x as i32, y as i32, z as i32), Some(block));
+                }
+            }
+        }
+        Ok(structure)
+    }
+
+    /// Load a structure from a RON, TOML or JSON file, ignoring any
+    /// placement rule in it.
+    pub fn load(path: &Path, registry: &BlockRegistry) -> Result<Self, StructureError> {
+        let file: StructureFile = load_config(path)?;
+        Self::from_file(&file, path, registry)
+    }
+
+    /// World position of a local position.
+    pub fn to_world(&self, placement: Placement, local_pos: IVec3) -> IVec3 {
+        let offset = local_pos - self.origin;
+        let x = if placement.mirror { -offset.x } else { offset.x };
+        let (x, z) = placement.rotation.apply(x, offset.z);
+        placement.position + IVec3::new(x, offset.y, z)
+    }
+
+    /// Local position of a world position.
+    pub fn to_local(&self, placement: Placement, world_pos: IVec3) -> IVec3 {
+        let offset = world_pos - placement.position;
+        let (x, z) = placement.rotation.inverse().apply(offset.x, offset.z);
+        let x = if placement.mirror { -x } else { x };
+        self.origin + IVec3::new(x, offset.y, z)
+    }
+
+    /// Inclusive world bounds of a placement.
+    pub fn bounds(&self, placement: Placement) -> (IVec3, IVec3) {
+        let a = self.to_world(placement, IVec3::ZERO);
+        let b = self.to_world(placement, self.size - IVec3::new(1, 1, 1));
+        (a.min(b), a.max(b))
+    }
+
+    /// Block a placement puts at a world position, or `None` if it leaves
+    /// the position alone.
+    pub fn block_at(&self, placement: Placement, world_pos: IVec3) -> Option<BlockId> {
+        self.get(self.to_local(placement, world_pos))
+    }
+
+    /// World positions and blocks of a placement, void left out.
+    pub fn blocks(&self, placement: Placement) -> impl Iterator<Item = (IVec3, BlockId)> + '_ {
+        (0..self.size.y).flat_map(move |y| {
+            (0..self.size.z).flat_map(move |z| {
+                (0..self.size.x).filter_map(move |x| {
+                    let local_pos = IVec3::new(x, y, z);
+                    self.get(local_pos).map(|block| (self.to_world(placement, local_pos), block))
+                })
+            })
+        })
+    }
+
+    /// Write a placement into loaded or new chunks, marking them for
+    /// remeshing.
+    pub fn place(&self, chunk_manager: &mut ChunkManager, placement: Placement) {
+        for (pos, block) in self.blocks(placement) {
+            chunk_manager.set_block(pos, block);
+        }
+    }
+
+    /// Furthest any block lies from the origin horizontally, in any
+    /// rotation.
+    pub fn reach(&self) -> i32 {
+        let far = self.size - self.origin - IVec3::new(1, 1, 1);
+        self.origin.x.max(self.origin.z).max(far.x).max(far.z)
+    }
+}
+
+/// Look up a block name, optionally with state values like
+/// `oak_log[axis=x]`.
+pub(crate) fn resolve_block(registry: &BlockRegistry, name: &str) -> Option<BlockId> {
+    let Some((block, state)) = name.strip_suffix(']').and_then(|name| name.split_once('[')) else {
+        return registry.get_by_name(name).map(|block| block.id);
+    };
+    let mut values = Vec::new();
+    for pair in state.split(',').filter(|pair| !pair.trim().is_empty()) {
+        let (property, value) = pair.split_once('=')?;
+        values.push((property.trim(), value.trim()));
+    }
+    registry.state(block, values)
+}
+
+fn default_origin() -> IVec3 {
+    IVec3::ZERO
+}
+
+/// Contents of a structure file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructureFile {
+    /// Block name of each layer character
+    pub palette: BTreeMap<char, String>,
+    /// Layers from the bottom up, each a list of rows along +z
+    pub layers: Vec<Vec<String>>,
+    #[serde(default = "default_origin")]
+    pub origin: IVec3,
+    /// How generation places the structure; files without one are only
+    /// placed by hand
+    #[serde(default)]
+    pub placement: Option<StructurePlacement>,
+}
+
+/// Height a generated structure's origin is placed at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StructureHeight {
+    /// On the terrain surface block, skipping columns under water
+    Surface,
+    /// Anywhere in `min..=max`, e.g. for dungeons
+    Range(i32, i32),
+}
+
+/// Grid rule deciding where generation places a structure.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StructurePlacement {
+    /// Grid cell width in chunks; each cell gets at most one structure
+    pub spacing: i32,
+    /// Least distance in chunks between structures of neighbouring cells
+    pub separation: i32,
+    /// Chance a cell gets a structure
+    pub chance: f64,
+    pub height: StructureHeight,
+    /// Pick a random rotation per placement
+    pub rotate: bool,
+    /// Mirror half the placements
+    pub mirror: bool,
+}
+
+impl Default for StructurePlacement {
+    fn default() -> Self {
+        Self {
+            spacing: 12,
+            separation: 4,
+            chance: 1.0,
+            height: StructureHeight::Surface,
+            rotate: true,
+            mirror: true,
+        }
+    }
+}
+
+/// Why a structure couldn't be loaded.
+#[derive(Debug)]
+pub enum StructureError {
+    /// The file couldn't be read or parsed
+    Config(ConfigError),
+    /// A directory couldn't be listed
+    Io { path: PathBuf, error: io::Error },
+    /// The palette names a block that isn't registered
+    UnknownBlock { path: PathBuf, block: String },
+    /// A layer uses a character missing from the palette
+    UnknownKey { path: PathBuf, key: char },
+    /// The layers aren't a box of blocks
+    Shape { path: PathBuf, message: String },
+}
+
+impl fmt::Display for StructureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructureError::Config(error) => error.fmt(f),
+            StructureError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            StructureError::UnknownBlock { path, block } => {
+                write!(f, "{}: block \"{}\" isn't registered", path.display(), block)
+            }
+            StructureError::UnknownKey { path, key } => {
+                write!(f, "{}: '{}' isn't in the palette", path.display(), key)
+            }
+            StructureError::Shape { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for StructureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StructureError::Config(error) => Some(error),
+            StructureError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConfigError> for StructureError {
+    fn from(error: ConfigError) -> Self {
+        StructureError::Config(error)
+    }
+}
+
+/// A structure generation places by a grid rule.
+#[derive(Debug, Clone)]
+pub struct StructureRule {
+    /// Names the rule and picks its random sequence, so rules on the same
+    /// grid don't line up
+    pub name: String,
+    pub structure: Arc<Structure>,
+    pub placement: StructurePlacement,
+}
+
+impl StructureRule {
+    pub fn new(name: impl Into<String>, structure: Structure, placement: StructurePlacement) -> Self {
+        Self {
+            name: name.into(),
+            structure: Arc::new(structure),
+            placement,
+        }
+    }
+
+    /// Load the structure files in `dir` that have a placement rule, named
+    /// after their file stems. Files are read in name order and every
+    /// problem found is reported. A missing directory loads nothing.
+    pub fn load_dir(dir: &Path, registry: &BlockRegistry) -> Result<Vec<Self>, Vec<StructureError>> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(vec![StructureError::Io { path: dir.to_path_buf(), error }]),
+        };
+
+        let mut paths = Vec::new();
+        let mut errors = Vec::new();
+        for entry in entries {
+            match entry {
+                Ok(entry) => paths.push(entry.path()),
+                Err(error) => errors.push(StructureError::Io { path: dir.to_path_buf(), error }),
+            }
+        }
+        paths.sort();
+
+        let mut rules = Vec::new();
+        for path in paths {
+            let file: StructureFile = match load_config(&path) {
+                Ok(file) => file,
+                Err(ConfigError::UnknownFormat { .. }) => continue, // Not a structure file
+                Err(error) => {
+                    errors.push(error.into());
+                    continue;
+                }
+            };
+            let structure = match Structure::from_file(&file, &path, registry) {
+                Ok(structure) => structure,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+            if let Some(placement) = file.placement {
+                let name = path.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+                rules.push(Self::new(name, structure, placement));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(rules)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Width of a grid cell in blocks.
+    fn cell_size(&self) -> i32 {
+        self.placement.spacing.max(1) * CHUNK_SIZE
+    }
+
+    /// Grid cells whose structure can have its origin in the inclusive
+    /// column bounds.
+    pub(crate) fn cells(&self, min_x: i32, min_z: i32, max_x: i32, max_z: i32) -> impl Iterator<Item = (i32, i32)> {
+        let size = self.cell_size();
+        let (min_x, max_x) = (min_x.div_euclid(size), max_x.div_euclid(size));
+        let (min_z, max_z) = (min_z.div_euclid(size), max_z.div_euclid(size));
+        (min_x..=max_x).flat_map(move |cell_x| (min_z..=max_z).map(move |cell_z| (cell_x, cell_z)))
+    }
+
+    /// The placement planned for a grid cell, if it has one. With
+    /// `StructureHeight::Surface` the height is left at 0 for the caller to
+    /// fill in.
+    pub(crate) fn plan(&self, seed: u32, cell_x: i32, cell_z: i32) -> Option<Placement> {
+        let salt = self.name.bytes().fold(0xCBF2_9CE4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+        });
+        let mut rng = FeatureRng::new(seed, IVec3::new(cell_x, 0, cell_z), salt);
+        if rng.next_f64() >= self.placement.chance {
+            return None;
+        }
+
+        // The origin's chunk stays `separation` chunks clear of the next
+        // cell's first chunk
+        let spacing = self.placement.spacing.max(1);
+        let last_chunk = (spacing - self.placement.separation.max(0) - 1).max(0);
+        let mut coordinate = |cell: i32| {
+            (cell * spacing + rng.range(0, last_chunk)) * CHUNK_SIZE + rng.range(0, CHUNK_SIZE - 1)
+        };
+        let (x, z) = (coordinate(cell_x), coordinate(cell_z));
+        let y = match self.placement.height {
+            StructureHeight::Surface => 0,
+            StructureHeight::Range(min, max) => rng.range(min, max.max(min)),
+        };
+        let rotation = Rotation::ALL[rng.range(0, 3) as usize];
+        let mirror = rng.next_u64() & 1 == 1;
+
+        Some(Placement {
+            position: IVec3::new(x, y, z),
+            rotation: if self.placement.rotate { rotation } else { Rotation::None },
+            mirror: self.placement.mirror && mirror,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 3x2x4 structure with a different block at every position.
+    fn numbered_structure() -> Structure {
+        let mut structure = Structure::new(IVec3::new(3, 2, 4)).with_origin(IVec3::new(1, 0, 1));
+        let mut id = 1;
+        for y in 0..2 {
+            for z in 0..4 {
+                for x in 0..3 {
+                    structure.set(IVec3::new(x, y, z), Some(id));
+                    id += 1;
+                }
+            }
+        }
+        structure
+    }
+
+    #[test]
+    fn test_rotation_and_mirroring() {
+        let structure = numbered_structure();
+        let position = IVec3::new(100, 64, -20);
+        let east = structure.origin() + IVec3::X;
+
+        let placement = Placement::new(position);
+        assert_eq!(structure.to_world(placement, east), position + IVec3::X);
+        assert_eq!(structure.to_world(placement.rotated(Rotation::Clockwise90), east), position + IVec3::Z);
+        assert_eq!(structure.to_world(placement.rotated(Rotation::Clockwise180), east), position - IVec3::X);
+        assert_eq!(structure.to_world(placement.mirrored(), east), position - IVec3::X);
+
+        for rotation in Rotation::ALL {
+            for mirror in [false, true] {
+                let placement = Placement { position, rotation, mirror };
+                assert_eq!(structure.to_world(placement, structure.origin()), position);
+
+                let (min, max) = structure.bounds(placement);
+                let blocks: Vec<_> = structure.blocks(placement).collect();
+                assert_eq!(blocks.len(), 24);
+                assert_eq!((max - min + IVec3::new(1, 1, 1)).dot(IVec3::new(1, 1, 1)), 3 + 2 + 4);
+                for (pos, block) in blocks {
+                    assert_eq!(pos.clamp(min, max), pos);
+                    assert_eq!(structure.block_at(placement, pos), Some(block));
+                }
+            }
+        }
+
+        let (min, max) = structure.bounds(placement.rotated(Rotation::Clockwise90));
+        assert_eq!(max - min, IVec3::new(3, 1, 2));
+        assert_eq!(structure.reach(), 2);
+    }
+
+    #[test]
+    fn test_load_structure_file() {
+        let registry = BlockRegistry::new();
+        let id = |name: &str| registry.get_by_name(name).unwrap().id;
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = dir.path().join("hut.ron");
+        fs::write(&path, r#"(
+            palette: { 'S': "stone", 'L': "oak_log[axis=x]", 'a': "air" },
+            layers: [["SSS", "S.S"], ["La ", "aaa"]],
+            origin: (x: 1, y: 0, z: 0),
+        )"#).unwrap();
+        let structure = Structure::load(&path, &registry).unwrap();
+        assert_eq!(structure.size(), IVec3::new(3, 2, 2));
+        assert_eq!(structure.origin(), IVec3::new(1, 0, 0));
+        assert_eq!(structure.get(IVec3::new(0, 0, 0)), Some(id("stone")));
+        assert_eq!(structure.get(IVec3::new(1, 0, 1)), None);
+        assert_eq!(structure.get(IVec3::new(0, 1, 0)), registry.state("oak_log", [("axis", "x")]));
+        assert_eq!(structure.get(IVec3::new(1, 1, 1)), Some(id("air")));
+        assert_eq!(structure.get(IVec3::new(2, 1, 0)), None);
+
+        let broken = [
+            ("unknown_block.ron", r#"(palette: { 'S': "marble" }, layers: [["S"]])"#),
+            ("unknown_key.ron", r#"(palette: {}, layers: [["S"]])"#),
+            ("ragged.ron", r#"(palette: { 'S': "stone" }, layers: [["SS", "S"]])"#),
+            ("empty.ron", r#"(palette: {}, layers: [])"#),
+        ];
+        for (name, contents) in broken {
+            fs::write(dir.path().join(name), contents).unwrap();
+        }
+        let result = |name: &str| Structure::load(&dir.path().join(name), &registry);
+        assert!(matches!(result("unknown_block.ron"), Err(StructureError::UnknownBlock { block, .. }) if block == "marble"));
+        assert!(matches!(result("unknown_key.ron"), Err(StructureError::UnknownKey { key: 'S', .. })));
+        assert!(matches!(result("ragged.ron"), Err(StructureError::Shape { .. })));
+        assert!(matches!(result("empty.ron"), Err(StructureError::Shape { .. })));
+        assert!(matches!(result("missing.ron"), Err(StructureError::Config(ConfigError::Io { .. }))));
+
+        // Only valid files with a placement rule become rules, and every
+        // broken file is reported
+        let errors = StructureRule::load_dir(dir.path(), &registry).unwrap_err();
+        assert_eq!(errors.len(), 4);
+        fs::write(dir.path().join("notes.txt"), "not a structure").unwrap();
+        for (name, _) in broken {
+            fs::remove_file(dir.path().join(name)).unwrap();
+        }
+        assert!(StructureRule::load_dir(dir.path(), &registry).unwrap().is_empty());
+        fs::write(dir.path().join("tower.toml"), "layers = [[\"S\"]]\n[palette]\nS = \"stone\"\n[placement]\nspacing = 8\n").unwrap();
+        let rules = StructureRule::load_dir(dir.path(), &registry).unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].name, "tower");
+        assert_eq!(rules[0].placement, StructurePlacement { spacing: 8, ..StructurePlacement::default() });
+    }
+
+    #[test]
+    fn test_bundled_structures() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../assets/structures");
+        let rules = StructureRule::load_dir(&dir, &BlockRegistry::new()).unwrap();
+        assert!(!rules.is_empty());
+    }
+
+    #[test]
+    fn test_place_across_chunks() {
+        let mut structure = Structure::new(IVec3::new(5, 3, 5)).with_origin(IVec3::new(2, 0, 2));
+        for pos in [IVec3::new(0, 0, 0), IVec3::new(4, 2, 4), IVec3::new(4, 0, 0), IVec3::new(0, 1, 4)] {
+            structure.set(pos, Some(1));
+        }
+
+        let mut chunks = ChunkManager::new();
+        chunks.set_block(IVec3::new(16, 70, 16), 3);
+        let placement = Placement::new(IVec3::new(16, 70, 16));
+        structure.place(&mut chunks, placement);
+
+        for (pos, block) in structure.blocks(placement) {
+            assert_eq!(chunks.get_block(pos), block);
+        }
+        assert_eq!(chunks.get_block(IVec3::new(14, 70, 14)), 1);
+        assert_eq!(chunks.get_block(IVec3::new(18, 72, 18)), 1);
+        // Void keeps the existing block
+        assert_eq!(chunks.get_block(IVec3::new(16, 70, 16)), 3);
+        for chunk_pos in [IVec3::new(0, 0, 0), IVec3::new(1, 0, 0), IVec3::new(0, 0, 1), IVec3::new(1, 0, 1)] {
+            assert!(chunks.get_chunk(chunk_pos).is_some());
+        }
+    }
+
+    #[test]
+    fn test_placement_grid() {
+        let placement = StructurePlacement { spacing: 6, separation: 2, ..StructurePlacement::default() };
+        let rule = StructureRule::new("hut", numbered_structure(), placement.clone());
+        let other = StructureRule::new("well", numbered_structure(), placement);
+
+        let mut rotations = std::collections::HashSet::new();
+        let mut differing = 0;
+        for cell_x in -8..8 {
+            for cell_z in -8..8 {
+                let planned = rule.plan(7, cell_x, cell_z).unwrap();
+                assert_eq!(rule.plan(7, cell_x, cell_z), Some(planned));
+                rotations.insert((planned.rotation, planned.mirror));
+                if other.plan(7, cell_x, cell_z) != Some(planned) {
+                    differing += 1;
+                }
+
+                // Inside the cell and clear of the next cells
+                let (x, z) = (planned.position.x, planned.position.z);
+                assert_eq!(rule.cells(x, z, x, z).collect::<Vec<_>>(), vec![(cell_x, cell_z)]);
+                let next = rule.plan(7, cell_x + 1, cell_z).unwrap().position;
+                assert!(next.x - x > 2 * CHUNK_SIZE);
+                let next = rule.plan(7, cell_x, cell_z + 1).unwrap().position;
+                assert!(next.z - z > 2 * CHUNK_SIZE);
+            }
+        }
+        assert_eq!(rotations.len(), 8);
+        assert!(differing > 200);
+
+        let rare = StructurePlacement { chance: 0.0, ..StructurePlacement::default() };
+        assert_eq!(StructureRule::new("hut", numbered_structure(), rare).plan(7, 0, 0), None);
+    }
+}
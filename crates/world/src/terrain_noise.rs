@@ -0,0 +1,327 @@
+//! Fractal noise stack describing the shape of the terrain surface.
+//!
+//! The stack is plain data (`TerrainNoise`) so it can live in a settings
+//! file; `HeightNoise` builds the noise functions for a seed and evaluates
+//! it. A sample warps the column position, sums the weighted layers and
+//! remaps the sum through a spline.
+
+use noise::{NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
+
+/// How the octaves of a layer are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FractalKind {
+    /// Fractal Brownian motion: rolling hills
+    Fbm,
+    /// Inverted absolute noise: sharp crests like mountain ridges
+    Ridged,
+}
+
+/// One fractal noise layer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoiseLayer {
+    pub kind: FractalKind,
+    pub octaves: u32,
+    /// Frequency of the first octave, in cycles per block
+    pub frequency: f64,
+    /// Frequency multiplier between octaves
+    pub lacunarity: f64,
+    /// Amplitude multiplier between octaves
+    pub persistence: f64,
+    /// Factor applied to the layer's output, which is in roughly `-1..1`
+    pub weight: f64,
+}
+
+impl NoiseLayer {
+    pub fn fbm(octaves: u32, frequency: f64, weight: f64) -> Self {
+        Self {
+            kind: FractalKind::Fbm,
+            octaves,
+            frequency,
+            lacunarity: 2.0,
+            persistence: 0.5,
+            weight,
+        }
+    }
+
+    pub fn ridged(octaves: u32, frequency: f64, weight: f64) -> Self {
+        Self {
+            kind: FractalKind::Ridged,
+            ..Self::fbm(octaves, frequency, weight)
+        }
+    }
+}
+
+/// Offsets column positions by fBm noise before the layers are sampled,
+/// bending straight features into natural shapes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainWarp {
+    pub octaves: u32,
+    pub frequency: f64,
+    /// Largest offset in blocks
+    pub amplitude: f64,
+}
+
+/// The terrain noise stack.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainNoise {
+    pub layers: Vec<NoiseLayer>,
+    pub warp: Option<DomainWarp>,
+    /// `(input, output)` points of a piecewise-linear curve applied to the
+    /// summed layers, sorted by input. Values outside the points are clamped
+    /// to the end points; no points leaves the sum unchanged.
+    pub spline: Vec<(f64, f64)>,
+}
+
+impl Default for TerrainNoise {
+    fn default() -> Self {
+        Self {
+            layers: vec![
+                NoiseLayer::fbm(5, 0.008, 0.8),
+                NoiseLayer::ridged(4, 0.004, 0.4),
+            ],
+            warp: Some(DomainWarp {
+                octaves: 2,
+                frequency: 0.004,
+                amplitude: 24.0,
+            }),
+            // Flatten valleys and steepen peaks
+            spline: vec![(-1.0, -0.6), (-0.2, -0.1), (0.3, 0.2), (1.0, 1.0)],
+        }
+    }
+}
+
+impl TerrainNoise {
+    /// A single octave of Perlin noise, the original smooth terrain.
+    pub fn single_octave(frequency: f64) -> Self {
+        Self {
+            layers: vec![NoiseLayer::fbm(1, frequency, 1.0)],
+            warp: None,
+            spline: Vec::new(),
+        }
+    }
+
+    /// The same stack with every frequency multiplied by `factor`, making
+    /// features `1 / factor` times wider.
+    pub fn scaled(mut self, factor: f64) -> Self {
+        for layer in &mut self.layers {
+            layer.frequency *= factor;
+        }
+        if let Some(warp) = &mut self.warp {
+            warp.frequency *= factor;
+            warp.amplitude /= factor;
+        }
+        self
+    }
+
+    /// Check that every layer and the warp have octaves and a positive
+    /// frequency, and that the spline inputs strictly increase.
+    pub fn validate(&self) -> Result<(), String> {
+        for (index, layer) in self.layers.iter().enumerate() {
+            if !(layer.octaves > 0 && layer.frequency > 0.0) {
+                return Err(format!("noise layer {} needs octaves and a positive frequency", index));
+            }
+        }
+        if let Some(warp) = &self.warp {
+            if !(warp.octaves > 0 && warp.frequency > 0.0) {
+                return Err("domain warp needs octaves and a positive frequency".to_string());
+            }
+        }
+        if !self.spline.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            return Err("spline inputs must strictly increase".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Octaves of one fractal layer.
+#[derive(Debug, Clone)]
+struct Fractal {
+    kind: FractalKind,
+    octaves: Vec<Perlin>,
+    frequency: f64,
+    lacunarity: f64,
+    persistence: f64,
+}
+
+impl Fractal {
+    fn new(kind: FractalKind, octaves: u32, frequency: f64, lacunarity: f64, persistence: f64, seed: u32) -> Self {
+        Self {
+            kind,
+            // Every octave gets its own noise so they don't line up at the
+            // origin
+            octaves: (0..octaves).map(|octave| Perlin::new(seed.wrapping_add(octave))).collect(),
+            frequency,
+            lacunarity,
+            persistence,
+        }
+    }
+
+    /// Sample the layer, normalized to roughly `-1..1`.
+    fn sample(&self, x: f64, z: f64) -> f64 {
+        let mut frequency = self.frequency;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut total_amplitude = 0.0;
+
+        for perlin in &self.octaves {
+            let value = perlin.get([x * frequency, z * frequency]);
+            total += amplitude * match self.kind {
+                FractalKind::Fbm => value,
+                FractalKind::Ridged => {
+                    let ridge = 1.0 - value.abs();
+                    ridge * ridge * 2.0 - 1.0
+                }
+            };
+            total_amplitude += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+
+        if total_amplitude > 0.0 {
+            total / total_amplitude
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A `TerrainNoise` stack ready to be sampled for one seed.
+#[derive(Debug, Clone)]
+pub struct HeightNoise {
+    layers: Vec<(Fractal, f64)>,
+    /// Offset noise for x and z, and the warp amplitude
+    warp: Option<(Fractal, Fractal, f64)>,
+    spline: Vec<(f64, f64)>,
+}
+
+impl HeightNoise {
+    pub fn new(config: &TerrainNoise, seed: u32) -> Self {
+        // Layer `i` uses seeds from `seed + 16 * (i + 1)` on, leaving room for
+        // their octaves and keeping them clear of the generator's own noise
+        let layer_seed = |i: usize| seed.wrapping_add(16 * (i as u32 + 1));
+        let layers = config.layers.iter().enumerate()
+            .map(|(i, layer)| {
+                let fractal = Fractal::new(
+                    layer.kind,
+                    layer.octaves,
+                    layer.frequency,
+                    layer.lacunarity,
+                    layer.persistence,
+                    layer_seed(i),
+                );
+                (fractal, layer.weight)
+            })
+            .collect();
+
+        let warp_seed = layer_seed(config.layers.len());
+        let warp = config.warp.as_ref().map(|warp| {
+            let offset = |seed| Fractal::new(FractalKind::Fbm, warp.octaves, warp.frequency, 2.0, 0.5, seed);
+            (offset(warp_seed), offset(warp_seed.wrapping_add(8)), warp.amplitude)
+        });
+
+        Self {
+            layers,
+            warp,
+            spline: config.spline.clone(),
+        }
+    }
+
+    /// Height factor of a column, roughly in `-1..1`.
+    pub fn sample(&self, x: f64, z: f64) -> f64 {
+        let (x, z) = match &self.warp {
+            Some((warp_x, warp_z, amplitude)) => (
+                x + warp_x.sample(x, z) * amplitude,
+                z + warp_z.sample(x, z) * amplitude,
+            ),
+            None => (x, z),
+        };
+
+        let value = self.layers.iter()
+            .map(|(fractal, weight)| fractal.sample(x, z) * weight)
+            .sum();
+        remap(&self.spline, value)
+    }
+}
+
+/// Evaluate a piecewise-linear curve through `points`.
+fn remap(points: &[(f64, f64)], value: f64) -> f64 {
+    let (Some(&first), Some(&last)) = (points.first(), points.last()) else {
+        return value;
+    };
+    if value <= first.0 {
+        return first.1;
+    }
+    if value >= last.0 {
+        return last.1;
+    }
+
+    let end = points.iter().position(|&(input, _)| input > value).unwrap();
+    let (x0, y0) = points[end - 1];
+    let (x1, y1) = points[end];
+    y0 + (value - x0) / (x1 - x0) * (y1 - y0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remap() {
+        let points = [(-1.0, -0.5), (0.0, 0.0), (1.0, 2.0)];
+        assert_eq!(remap(&points, -3.0), -0.5);
+        assert_eq!(remap(&points, -0.5), -0.25);
+        assert_eq!(remap(&points, 0.0), 0.0);
+        assert_eq!(remap(&points, 0.25), 0.5);
+        assert_eq!(remap(&points, 5.0), 2.0);
+        assert_eq!(remap(&[], 0.7), 0.7);
+    }
+
+    #[test]
+    fn test_layers() {
+        let ridged = Fractal::new(FractalKind::Ridged, 3, 0.05, 2.0, 0.5, 7);
+        let fbm = Fractal::new(FractalKind::Fbm, 3, 0.05, 2.0, 0.5, 7);
+        for i in 0..400 {
+            let (x, z) = (i as f64 * 3.7, i as f64 * -1.3);
+            for value in [ridged.sample(x, z), fbm.sample(x, z)] {
+                assert!((-1.0..=1.0).contains(&value), "{}", value);
+            }
+        }
+
+        // A single ridged octave peaks where the plain noise crosses zero
+        let ridged = Fractal::new(FractalKind::Ridged, 1, 0.05, 2.0, 0.5, 7);
+        let plain = Fractal::new(FractalKind::Fbm, 1, 0.05, 2.0, 0.5, 7);
+        for i in 0..100 {
+            let (x, z) = (i as f64 * 2.3, 5.0);
+            let expected = (1.0 - plain.sample(x, z).abs()).powi(2) * 2.0 - 1.0;
+            assert!((ridged.sample(x, z) - expected).abs() < 1e-12);
+        }
+        assert_eq!(ridged.sample(0.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn test_warp_moves_features() {
+        let plain = TerrainNoise { warp: None, ..TerrainNoise::default() };
+        let warped = TerrainNoise::default();
+        let (plain, warped) = (HeightNoise::new(&plain, 3), HeightNoise::new(&warped, 3));
+
+        let differing = (0..100)
+            .filter(|&i| plain.sample(i as f64 * 5.0, 0.0) != warped.sample(i as f64 * 5.0, 0.0))
+            .count();
+        assert!(differing > 90);
+
+        // Deterministic for a seed
+        let again = HeightNoise::new(&TerrainNoise::default(), 3);
+        assert_eq!(warped.sample(123.0, -45.0), again.sample(123.0, -45.0));
+    }
+
+    #[test]
+    fn test_scaled() {
+        let noise = TerrainNoise::default();
+        let wide = noise.clone().scaled(0.5);
+        let (a, b) = (HeightNoise::new(&noise, 1), HeightNoise::new(&wide, 1));
+        assert!((a.sample(10.0, 20.0) - b.sample(20.0, 40.0)).abs() < 1e-9);
+    }
+}
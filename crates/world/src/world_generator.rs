@@ -0,0 +1,397 @@
+//! Pluggable world generators.
+//!
+//! Anything that fills in chunks implements `WorldGenerator`: the noise
+//! `TerrainGenerator`, flat layers for creative worlds, an empty void, and a
+//! grid of every block state for checking textures and models. World
+//! settings pick one by name.
+
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use voxel_core::{load_config, BlockId, BlockRegistry, ConfigError, IVec3, AIR_BLOCK, CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::chunk::{Chunk, ChunkManager};
+use crate::generation::{TerrainConfig, TerrainGenerator, UnknownBlockError};
+use crate::section::Section;
+use crate::structure::{resolve_block, StructureRule};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Height of the debug grid's blocks.
+pub const DEBUG_GRID_Y: i32 = 70;
+
+/// Distance between neighbouring blocks of the debug grid.
+pub const DEBUG_GRID_SPACING: i32 = 2;
+
+/// Fills in new chunks.
+///
+/// Generation must be deterministic: a chunk comes out the same however
+/// often and in whatever order chunks are generated.
+pub trait WorldGenerator: Send + Sync {
+    /// Generate a single chunk.
+    fn generate_chunk(&self, chunk_pos: IVec3) -> Chunk;
+
+    /// Blocks the generated chunks are made of.
+    fn registry(&self) -> &BlockRegistry;
+
+    /// Chunk y coordinates generated for each column of chunks.
+    fn chunk_layers(&self) -> RangeInclusive<i32> {
+        0..=0
+    }
+
+    /// Generate several chunks in parallel on the rayon thread pool.
+    ///
+    /// Output is identical to calling `generate_chunk` for each position.
+    fn generate_chunks_parallel(&self, positions: &[IVec3]) -> Vec<Chunk> {
+        positions.par_iter()
+            .map(|&chunk_pos| self.generate_chunk(chunk_pos))
+            .collect()
+    }
+
+    /// Generate the missing chunks in a radius around `center`, on every
+    /// layer of `chunk_layers`.
+    fn generate_chunks_around(&self, center: IVec3, radius: i32, chunk_manager: &mut ChunkManager) {
+        let mut missing = Vec::new();
+        for x in (center.x - radius)..=(center.x + radius) {
+            for z in (center.z - radius)..=(center.z + radius) {
+                for y in self.chunk_layers() {
+                    let chunk_pos = IVec3::new(x, y, z);
+
+                    // Only generate if chunk doesn't exist
+                    if chunk_manager.get_chunk(chunk_pos).is_none() {
+                        missing.push(chunk_pos);
+                    }
+                }
+            }
+        }
+
+        for chunk in self.generate_chunks_parallel(&missing) {
+            chunk_manager.insert_chunk(chunk);
+        }
+    }
+}
+
+/// One layer of a superflat world.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlatLayer {
+    /// Block name, optionally with state values like `oak_log[axis=x]`
+    pub block: String,
+    pub thickness: i32,
+}
+
+impl FlatLayer {
+    pub fn new(block: impl Into<String>, thickness: i32) -> Self {
+        Self {
+            block: block.into(),
+            thickness,
+        }
+    }
+}
+
+/// Superflat world settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SuperflatConfig {
+    /// Layers from y = 0 up. Layers above the chunk height are cut off.
+    pub layers: Vec<FlatLayer>,
+}
+
+impl Default for SuperflatConfig {
+    fn default() -> Self {
+        // Grass at the noise terrain's sea level
+        Self {
+            layers: vec![
+                FlatLayer::new("stone", 60),
+                FlatLayer::new("dirt", 3),
+                FlatLayer::new("grass", 1),
+            ],
+        }
+    }
+}
+
+/// Identical chunks of flat layers.
+pub struct SuperflatGenerator {
+    registry: BlockRegistry,
+    /// Sections every chunk is a copy of
+    sections: Vec<Option<Section>>,
+}
+
+impl SuperflatGenerator {
+    /// Fails if a layer's block isn't registered.
+    pub fn new(config: &SuperflatConfig, registry: BlockRegistry) -> Result<Self, UnknownBlockError> {
+        let mut template = Chunk::new(IVec3::ZERO);
+        let mut y = 0;
+        for layer in &config.layers {
+            let Some(block) = resolve_block(&registry, &layer.block) else {
+                return Err(UnknownBlockError { block: layer.block.clone() });
+            };
+            for _ in 0..layer.thickness.max(0) {
+                if y >= CHUNK_HEIGHT {
+                    break;
+                }
+                for z in 0..CHUNK_SIZE {
+                    for x in 0..CHUNK_SIZE {
+                        template.set_block(IVec3::new(x, y, z), block);
+                    }
+                }
+                y += 1;
+            }
+        }
+
+        Ok(Self {
+            registry,
+            sections: template.sections().to_vec(),
+        })
+    }
+}
+
+impl WorldGenerator for SuperflatGenerator {
+    fn generate_chunk(&self, chunk_pos: IVec3) -> Chunk {
+        Chunk::from_sections(chunk_pos, self.sections.clone())
+    }
+
+    fn registry(&self) -> &BlockRegistry {
+        &self.registry
+    }
+}
+
+/// Nothing but air.
+pub struct VoidGenerator {
+    registry: BlockRegistry,
+}
+
+impl VoidGenerator {
+    pub fn new(registry: BlockRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+impl WorldGenerator for VoidGenerator {
+    fn generate_chunk(&self, chunk_pos: IVec3) -> Chunk {
+        Chunk::new(chunk_pos)
+    }
+
+    fn registry(&self) -> &BlockRegistry {
+        &self.registry
+    }
+}
+
+/// Every registered block state once, in a square grid at `DEBUG_GRID_Y`
+/// starting at x = z = 0, with air in between.
+pub struct DebugGridGenerator {
+    registry: BlockRegistry,
+    /// States in ID order
+    states: Vec<BlockId>,
+    /// States per grid row
+    width: i32,
+}
+
+impl DebugGridGenerator {
+    pub fn new(registry: BlockRegistry) -> Self {
+        let mut states: Vec<BlockId> = registry.iter()
+            .filter(|block| !registry.is_placeholder(block.id))
+            .flat_map(|block| block.states())
+            .filter(|&state| state != AIR_BLOCK)
+            .collect();
+        states.sort_unstable();
+        let width = ((states.len() as f64).sqrt().ceil() as i32).max(1);
+
+        Self {
+            registry,
+            states,
+            width,
+        }
+    }
+
+    /// Where the grid shows a block state.
+    pub fn position_of(&self, state: BlockId) -> Option<IVec3> {
+        let index = self.states.binary_search(&state).ok()? as i32;
+        Some(IVec3::new(
+            index % self.width * DEBUG_GRID_SPACING,
+            DEBUG_GRID_Y,
+            index / self.width * DEBUG_GRID_SPACING,
+        ))
+    }
+
+    /// The block state shown at a world position, if any.
+    pub fn state_at(&self, world_pos: IVec3) -> Option<BlockId> {
+        let IVec3 { x, y, z } = world_pos;
+        if y != DEBUG_GRID_Y || x < 0 || z < 0 || x % DEBUG_GRID_SPACING != 0 || z % DEBUG_GRID_SPACING != 0 {
+            return None;
+        }
+        let (column, row) = (x / DEBUG_GRID_SPACING, z / DEBUG_GRID_SPACING);
+        if column >= self.width {
+            return None;
+        }
+        self.states.get((row * self.width + column) as usize).copied()
+    }
+}
+
+impl WorldGenerator for DebugGridGenerator {
+    fn generate_chunk(&self, chunk_pos: IVec3) -> Chunk {
+        let mut chunk = Chunk::new(chunk_pos);
+        for local_z in 0..CHUNK_SIZE {
+            for local_x in 0..CHUNK_SIZE {
+                let world_pos = IVec3::new(chunk_pos.x * CHUNK_SIZE + local_x, DEBUG_GRID_Y, chunk_pos.z * CHUNK_SIZE + local_z);
+                if let Some(state) = self.state_at(world_pos) {
+                    chunk.set_block(IVec3::new(local_x, DEBUG_GRID_Y, local_z), state);
+                }
+            }
+        }
+        chunk.mark_clean();
+        chunk
+    }
+
+    fn registry(&self) -> &BlockRegistry {
+        &self.registry
+    }
+}
+
+/// Which generator builds the world.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeneratorSettings {
+    /// Noise terrain shaped by a `TerrainConfig`
+    #[default]
+    Noise,
+    Superflat(SuperflatConfig),
+    Void,
+    DebugGrid,
+}
+
+impl GeneratorSettings {
+    /// Build the generator. Only the noise generator uses `terrain` and
+    /// `structures`. Fails if a block the generator needs isn't registered.
+    pub fn build(
+        &self,
+        terrain: TerrainConfig,
+        registry: BlockRegistry,
+        structures: Vec<StructureRule>,
+    ) -> Result<Box<dyn WorldGenerator>, UnknownBlockError> {
+        Ok(match self {
+            GeneratorSettings::Noise => {
+                Box::new(TerrainGenerator::with_registry(terrain, registry)?.with_structures(structures))
+            }
+            GeneratorSettings::Superflat(config) => Box::new(SuperflatGenerator::new(config, registry)?),
+            GeneratorSettings::Void => Box::new(VoidGenerator::new(registry)),
+            GeneratorSettings::DebugGrid => Box::new(DebugGridGenerator::new(registry)),
+        })
+    }
+}
+
+/// World settings. Fields missing from a settings file keep their default
+/// values.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldSettings {
+    pub generator: GeneratorSettings,
+}
+
+impl WorldSettings {
+    /// Load settings from a RON, TOML or JSON file.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        load_config(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use voxel_core::parse_by_extension;
+
+    #[test]
+    fn test_superflat() {
+        let registry = BlockRegistry::new();
+        let id = |name: &str| registry.get_by_name(name).unwrap().id;
+        let (stone, dirt, grass) = (id("stone"), id("dirt"), id("grass"));
+        let generator = SuperflatGenerator::new(&SuperflatConfig::default(), registry.clone()).unwrap();
+
+        for chunk_pos in [IVec3::ZERO, IVec3::new(-7, 0, 12)] {
+            let chunk = generator.generate_chunk(chunk_pos);
+            assert_eq!(chunk.position, chunk_pos);
+            assert!(!chunk.is_dirty());
+            for (x, z) in [(0, 0), (15, 3), (8, 15)] {
+                assert_eq!(chunk.get_block(IVec3::new(x, 0, z)), stone);
+                assert_eq!(chunk.get_block(IVec3::new(x, 59, z)), stone);
+                assert_eq!(chunk.get_block(IVec3::new(x, 62, z)), dirt);
+                assert_eq!(chunk.get_block(IVec3::new(x, 63, z)), grass);
+                assert_eq!(chunk.get_block(IVec3::new(x, 64, z)), AIR_BLOCK);
+            }
+        }
+
+        // Layers accept block states and stop at the top of the chunk
+        let config = SuperflatConfig {
+            layers: vec![FlatLayer::new("oak_log[axis=z]", 2), FlatLayer::new("sand", CHUNK_HEIGHT)],
+        };
+        let chunk = SuperflatGenerator::new(&config, registry.clone()).unwrap().generate_chunk(IVec3::ZERO);
+        assert_eq!(Some(chunk.get_block(IVec3::new(3, 1, 3))), registry.state("oak_log", [("axis", "z")]));
+        assert_eq!(chunk.get_block(IVec3::new(3, CHUNK_HEIGHT - 1, 3)), id("sand"));
+    }
+
+    #[test]
+    fn test_superflat_unknown_block() {
+        let config = SuperflatConfig { layers: vec![FlatLayer::new("stone", 1), FlatLayer::new("marble", 1)] };
+        let error = SuperflatGenerator::new(&config, BlockRegistry::new()).err().unwrap();
+        assert_eq!(error, UnknownBlockError { block: "marble".into() });
+        assert_eq!(error.to_string(), "block marble isn't registered");
+
+        let settings = GeneratorSettings::Superflat(config);
+        assert!(settings.build(TerrainConfig::default(), BlockRegistry::new(), Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_debug_grid() {
+        let registry = BlockRegistry::new();
+        let generator = DebugGridGenerator::new(registry.clone());
+        let mut manager = ChunkManager::new();
+        generator.generate_chunks_around(IVec3::ZERO, 1, &mut manager);
+
+        let mut shown = Vec::new();
+        for chunk_pos in manager.loaded_chunks() {
+            for (local_pos, block) in manager.get_chunk(chunk_pos).unwrap().iter_blocks() {
+                assert_eq!(local_pos.y, DEBUG_GRID_Y);
+                let world_pos = IVec3::new(chunk_pos.x * CHUNK_SIZE + local_pos.x, DEBUG_GRID_Y, chunk_pos.z * CHUNK_SIZE + local_pos.z);
+                assert_eq!(generator.position_of(block), Some(world_pos));
+                shown.push(block);
+            }
+        }
+
+        // Every state but air, once
+        let mut expected: Vec<BlockId> = registry.iter()
+            .flat_map(|block| block.states())
+            .filter(|&state| state != AIR_BLOCK)
+            .collect();
+        expected.sort_unstable();
+        shown.sort_unstable();
+        assert_eq!(shown, expected);
+        assert_eq!(generator.position_of(AIR_BLOCK), None);
+    }
+
+    #[test]
+    fn test_settings_pick_generator() {
+        let registry = BlockRegistry::new();
+        let build = |settings: &str| {
+            let settings: WorldSettings = parse_by_extension(Path::new("world.ron"), settings).unwrap().unwrap();
+            settings.generator.build(TerrainConfig::default(), registry.clone(), Vec::new()).unwrap()
+        };
+
+        let chunk_pos = IVec3::new(2, 0, -1);
+        let noise = build("(generator: noise)").generate_chunk(chunk_pos);
+        let expected = TerrainGenerator::new(TerrainConfig::default()).generate_chunk(chunk_pos);
+        assert_eq!(noise.sections(), expected.sections());
+
+        let flat = build("(generator: superflat((layers: [(block: \"sand\", thickness: 4)])))");
+        let chunk = flat.generate_chunk(chunk_pos);
+        assert_eq!(chunk.get_block(IVec3::new(1, 3, 1)), registry.get_by_name("sand").unwrap().id);
+        assert_eq!(chunk.get_block(IVec3::new(1, 4, 1)), AIR_BLOCK);
+
+        assert!(build("(generator: void)").generate_chunk(chunk_pos).is_empty());
+        assert!(!build("(generator: debug_grid)").generate_chunk(IVec3::ZERO).is_empty());
+        assert_eq!(build("()").generate_chunk(chunk_pos).sections(), expected.sections());
+    }
+
+    #[test]
+    fn test_bundled_world_settings() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../assets/world.ron");
+        assert_eq!(WorldSettings::load(&path).unwrap(), WorldSettings::default());
+    }
+}